@@ -0,0 +1,94 @@
+// Times the pipeline on its own and counts the allocations it makes per frame, once with a renderer that keeps its
+// buffers from frame to frame and once with a new renderer every frame, which is what every frame used to cost.
+// Run with `cargo bench --bench pipeline`.
+use std::alloc::{GlobalAlloc, Layout, System};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::time::Instant;
+
+use third_dimension::backend::NullBackend;
+use third_dimension::render::{Parallelism, Renderer, RESOLUTION};
+use third_dimension::stats::RenderStats;
+use third_dimension::{Mesh, Scene};
+
+
+const FRAMES: u32 = 300;
+const WARMUP_FRAMES: u32 = 10;
+
+
+// counts every allocation, on any thread
+struct CountingAllocator;
+static ALLOCATIONS: AtomicUsize = AtomicUsize::new(0);
+
+unsafe impl GlobalAlloc for CountingAllocator {
+    unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+        ALLOCATIONS.fetch_add(1, Ordering::Relaxed);
+        return unsafe { System.alloc(layout) };
+    }
+
+    unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+        unsafe { System.dealloc(ptr, layout) };
+    }
+
+    unsafe fn realloc(&self, ptr: *mut u8, layout: Layout, new_size: usize) -> *mut u8 {
+        ALLOCATIONS.fetch_add(1, Ordering::Relaxed);
+        return unsafe { System.realloc(ptr, layout, new_size) };
+    }
+}
+
+#[global_allocator]
+static ALLOCATOR: CountingAllocator = CountingAllocator;
+
+
+fn scene() -> Scene {
+    let mut scene = Scene::new();
+    for (x, z) in [(0.0, 8.0), (-5.0, 12.0), (5.0, 12.0)] {
+        let mut teapot = Mesh::load("assets/teapot.obj").expect("Failed to load teapot.");
+        teapot.translate(x, -1.5, z);
+        scene.add_mesh(teapot);
+    }
+    return scene;
+}
+
+// draws `FRAMES` frames after a few to warm up, `renderer` gives the renderer for each of them
+fn run<F: FnMut() -> Option<Renderer>>(name: &str, scene: &Scene, parallelism: &Parallelism, mut renderer: F) {
+    let mut backend = NullBackend::new(RESOLUTION[0], RESOLUTION[1]);
+    let mut kept = Renderer::new(parallelism.clone());
+
+    let mut frame = |kept: &mut Renderer| {
+        let mut stats = RenderStats::new();
+        match renderer() {
+            Some(mut fresh) => fresh.draw(&mut backend, scene, &scene.camera, &mut stats),
+            None => kept.draw(&mut backend, scene, &scene.camera, &mut stats)
+        }
+    };
+
+    for _ in 0..WARMUP_FRAMES {
+        frame(&mut kept);
+    }
+
+    let allocations = ALLOCATIONS.load(Ordering::Relaxed);
+    let start = Instant::now();
+    for _ in 0..FRAMES {
+        frame(&mut kept);
+    }
+    let elapsed = start.elapsed();
+    let allocations = ALLOCATIONS.load(Ordering::Relaxed) - allocations;
+
+    println!(
+        "{:<24}{:>2} threads {:8.3} ms/frame {:10.1} allocations/frame",
+        name,
+        parallelism.threads(),
+        elapsed.as_secs_f64() * 1000.0 / FRAMES as f64,
+        allocations as f64 / FRAMES as f64
+    );
+}
+
+fn main() {
+    let scene = scene();
+    println!("{} triangles, {} frames", scene.meshes.iter().map(|mesh| mesh.triangle_count()).sum::<usize>(), FRAMES);
+
+    for parallelism in [Parallelism::serial(), Parallelism::available()] {
+        run("reused buffers", &scene, &parallelism, || None);
+        run("new buffers every frame", &scene, &parallelism, || Some(Renderer::new(parallelism.clone())));
+    }
+}
@@ -0,0 +1,21 @@
+use third_dimension::{Engine, Mesh, Options};
+
+
+// Two stacked cubes and a teapot, with every command line option of `Options::from_args`
+fn main() {
+    let args: Vec<String> = std::env::args().skip(1).collect();
+    let options = Options::from_args(&args).unwrap_or_else(|e| panic!("{}", e));
+
+    let mut engine = Engine::new(options);
+
+    let mut meshes: Vec<Mesh> = vec![Mesh::cube(), Mesh::cube(), Mesh::load("assets/teapot.obj").expect("Failed to load the teapot")];
+
+    meshes[1].translate(0.0, 2.0, 0.0);
+    meshes[2].translate(5.0, 0.0, 0.0);
+
+    for mesh in meshes {
+        engine.add_mesh(mesh);
+    }
+
+    engine.run();
+}
@@ -0,0 +1,113 @@
+use sdl2::pixels::Color;
+use third_dimension::backend::Image;
+use third_dimension::engine::Backend;
+use third_dimension::material::Material;
+use third_dimension::shader::{self, DefaultShader, FnShader, Shader, Uniforms};
+use third_dimension::vertex::{Attributes, Vertex};
+use third_dimension::{Engine, Mesh, Options};
+
+
+// Shaders written outside of the engine. They only show with the software rasterizer, so that's the default here.
+// Pick one with the `shader` console command, or as the first file argument:
+//   cargo run --example shaders -- toon
+fn main() {
+    let args: Vec<String> = std::env::args().skip(1).collect();
+    let mut options = Options::from_args(&args).unwrap_or_else(|e| panic!("{}", e));
+    if !args.iter().any(|arg| arg == "--backend") {
+        options.backend = Backend::Software;
+    }
+    let first_shader = options.files.first().cloned().unwrap_or(String::from("toon"));
+
+    let mut engine = Engine::new(options);
+    engine.console.register("shader", "shader <default|toon|rim|normals>", "changes how everything is shaded", |app, args| {
+        let name = args.first().ok_or("which shader?")?;
+        app.scene.shader = by_name(name).ok_or(format!("no shader called '{}'", name))?;
+        return Ok(format!("shading with {}", name));
+    });
+    engine.scene().shader = by_name(&first_shader).unwrap_or(Box::new(DefaultShader));
+
+    let mut teapot = Mesh::load("assets/teapot.obj").expect("Failed to load the teapot");
+    teapot.translate(5.0, 0.0, 0.0);
+    engine.add_mesh(Mesh::cube());
+    engine.add_mesh(teapot);
+
+    engine.run();
+}
+
+fn by_name(name: &str) -> Option<Box<dyn Shader>> {
+    return match name {
+        "default" => Some(Box::new(DefaultShader)),
+        "toon" => Some(Box::new(Toon { bands: 3 })),
+        "rim" => Some(Box::new(Rim { color: Color::RGB(90, 170, 255), power: 2.0 })),
+        "normals" => Some(Box::new(normals())),
+        _ => None
+    };
+}
+
+
+fn scale(color: Color, amount: f32) -> Color {
+    let channel = |c: u8| (c as f32 * amount).clamp(0.0, 255.0) as u8;
+    return Color::RGBA(channel(color.r), channel(color.g), channel(color.b), color.a);
+}
+
+fn base_color(varyings: &Attributes, texture: Option<&Image>) -> Color {
+    return match texture {
+        Some(texture) => texture.sample(varyings.uv().x, varyings.uv().y),
+        None => Color::RGB(230, 230, 230)
+    };
+}
+
+
+// light in a few hard steps instead of a smooth falloff
+struct Toon {
+    bands: u32
+}
+impl Shader for Toon {
+    fn vertex(&self, vertex: &Vertex, uniforms: &Uniforms) -> Vertex {
+        return shader::transform(vertex, uniforms);
+    }
+
+    fn fragment(&self, varyings: &Attributes, uniforms: &Uniforms, material: &Material, texture: Option<&Image>) -> Option<Color> {
+        let light = uniforms.light_direction.dot(&shader::surface_normal(varyings, material)).max(0.0);
+        let band = (light * self.bands as f32).ceil() / self.bands as f32;
+        return Some(shader::fog(scale(base_color(varyings, texture), 0.2 + band * 0.8), varyings, uniforms));
+    }
+}
+
+
+// edges seen from the side glow, which needs the world position of every pixel
+struct Rim {
+    color: Color,
+    power: f32
+}
+impl Shader for Rim {
+    fn vertex(&self, vertex: &Vertex, uniforms: &Uniforms) -> Vertex {
+        return shader::transform(vertex, uniforms);
+    }
+
+    fn fragment(&self, varyings: &Attributes, uniforms: &Uniforms, material: &Material, texture: Option<&Image>) -> Option<Color> {
+        let normal = shader::surface_normal(varyings, material);
+        let to_camera = (uniforms.camera_position - varyings.position()).normalized();
+
+        let light = uniforms.light_direction.dot(&normal).max(0.1);
+        let rim = (1.0 - normal.dot(&to_camera).abs()).powf(self.power);
+        let lit = scale(base_color(varyings, texture), light);
+        let glow = scale(self.color, rim);
+        let color = Color::RGB(lit.r.saturating_add(glow.r), lit.g.saturating_add(glow.g), lit.b.saturating_add(glow.b));
+        return Some(shader::fog(color, varyings, uniforms));
+    }
+}
+
+
+// false colour debug view, every axis of the normal as a colour channel
+fn normals() -> impl Shader {
+    return FnShader {
+        vertex: |vertex: &Vertex, uniforms: &Uniforms| {
+            shader::transform(vertex, uniforms)
+        },
+        fragment: |varyings: &Attributes, _uniforms: &Uniforms, _material: &Material, _texture: Option<&Image>| {
+            let n = varyings.normal().normalized();
+            Some(Color::RGB(((n.x * 0.5 + 0.5) * 255.0) as u8, ((n.y * 0.5 + 0.5) * 255.0) as u8, ((n.z * 0.5 + 0.5) * 255.0) as u8))
+        }
+    };
+}
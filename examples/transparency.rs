@@ -0,0 +1,50 @@
+use sdl2::pixels::Color;
+use third_dimension::backend::Image;
+use third_dimension::material::{BlendMode, Material};
+use third_dimension::{Engine, Mesh, Options, Scene};
+
+
+// A teapot behind a row of cubes made of glass, foliage, and additive and multiplicative light.
+// Cut out foliage and per-material textures need the software rasterizer: `--backend software`
+fn main() {
+    let args: Vec<String> = std::env::args().skip(1).collect();
+    let options = Options::from_args(&args).unwrap_or_else(|e| panic!("{}", e));
+
+    let mut engine = Engine::new(options);
+    build(engine.scene());
+    engine.run();
+}
+
+fn build(scene: &mut Scene) {
+    let mut teapot = Mesh::load("assets/teapot.obj").expect("Failed to load the teapot");
+    teapot.translate(0.5, 0.0, 2.0);
+    scene.add_mesh(teapot);
+
+    let materials = [
+        Material::transparent(Color::RGB(120, 200, 255), 0.35),
+        Material::cutout(leaves(), 0.5),
+        Material { color: Color::RGB(255, 140, 40), opacity: 0.8, blend_mode: BlendMode::Additive, ..Material::new() },
+        Material { color: Color::RGB(120, 255, 120), blend_mode: BlendMode::Multiply, ..Material::new() }
+    ];
+    for (i, material) in materials.into_iter().enumerate() {
+        let mut cube = Mesh::cube();
+        cube.translate(i as f32 * 1.2 - 1.9, 0.6, 0.0);
+        cube.set_material(scene.add_material(material));
+        scene.add_mesh(cube);
+    }
+}
+
+// green blobs on nothing, the gaps between them are fully transparent
+fn leaves() -> Image {
+    let mut image = Image::new(64, 64, Color::RGBA(0, 0, 0, 0));
+    for y in 0..64 {
+        for x in 0..64 {
+            let (dx, dy) = ((x % 16) as f32 - 7.5, (y % 16) as f32 - 7.5);
+            if dx * dx + dy * dy * 2.0 < 40.0 {
+                let shade = 120 + ((x * 7 + y * 13) % 60) as u8;
+                image.set(x, y, Color::RGB(40, shade, 30));
+            }
+        }
+    }
+    return image;
+}
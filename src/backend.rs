@@ -0,0 +1,361 @@
+use sdl2::gfx::primitives::DrawRenderer;
+use sdl2::image::LoadSurface;
+use sdl2::pixels::{Color, PixelFormatEnum};
+use sdl2::render::{Canvas, RenderTarget, Texture};
+use sdl2::surface::Surface;
+
+use crate::material::{BlendMode, Material};
+use crate::math::{Vec2, Vec3};
+use crate::mesh::Triangle;
+use crate::post::PostProcess;
+use crate::render::{Antialiasing, RenderMode};
+use crate::shader::{Shader, Uniforms};
+use crate::ssao::Ssao;
+
+
+// Where the finished screen space triangles go. `Renderer` only talks to this, so the same pipeline can draw
+// into a window, into memory, or nowhere at all.
+pub trait RenderBackend {
+    // width and height in pixels, triangles are clipped to this
+    fn size(&self) -> (u32, u32);
+
+    // whether every pixel is shaded on its own, which antialiasing, ambient occlusion and post processing need
+    fn shades_pixels(&self) -> bool;
+
+    fn begin_frame(&mut self, clear_color: Color);
+
+    // how many samples to take of every pixel, backends that can't ignore it
+    fn set_antialiasing(&mut self, antialiasing: Antialiasing);
+
+    // Triangles are already clipped to the screen and sorted back to front, `shader` colours their pixels and
+    // `materials` are what their `material` points at. All of them are either opaque or transparent: the opaque ones
+    // come first, the transparent ones in a second call.
+    fn draw_triangles(&mut self, triangles: &[Triangle], render_mode: RenderMode, shader: &dyn Shader, uniforms: &Uniforms, materials: &[Material]);
+
+    // Works out with `ssao` how much ambient light reaches every pixel of the opaque `triangles` about to be drawn, for
+    // shaders to read from `Attribute::Occlusion`. Backends that don't shade pixels have nothing to do.
+    fn ambient_occlusion(&mut self, triangles: &[Triangle], uniforms: &Uniforms, materials: &[Material], ssao: &Ssao);
+
+    // `image` covers the whole screen, behind everything drawn after it
+    fn draw_background(&mut self, image: &Image);
+
+    fn draw_line(&mut self, from: Vec2, to: Vec2, color: Color);
+
+    // Turns what's been drawn since `begin_frame` into the finished frame with `post`. Backends that draw straight
+    // to the screen have nothing to do.
+    fn end_frame(&mut self, post: &PostProcess);
+
+    fn present(&mut self);
+
+    // what's been drawn so far, `None` if the backend doesn't keep any pixels
+    fn read_pixels(&mut self) -> Option<Image>;
+}
+
+
+// SDL2_gfx takes its colours byte-swapped on macOS
+#[cfg(target_os = "macos")]
+pub fn reverse_color(color: Color) -> Color {
+    return Color::RGBA(color.a, color.b, color.g, color.r);
+}
+#[cfg(not(target_os = "macos"))]
+pub fn reverse_color(color: Color) -> Color {
+    return color;
+}
+
+
+// Plain RGBA pixels in memory, row by row from the top left
+#[derive(Clone)]
+pub struct Image {
+    pub width: u32,
+    pub height: u32,
+    pub pixels: Vec<Color>
+}
+impl Image {
+    pub fn new(width: u32, height: u32, color: Color) -> Self {
+        return Self { width, height, pixels: vec![color; (width * height) as usize] };
+    }
+
+    // `bytes` are RGBA, `pitch` bytes per row
+    pub fn from_rgba(width: u32, height: u32, pitch: usize, bytes: &[u8]) -> Self {
+        let mut pixels: Vec<Color> = Vec::with_capacity((width * height) as usize);
+        for y in 0..height as usize {
+            for x in 0..width as usize {
+                let i = y * pitch + x * 4;
+                pixels.push(Color::RGBA(bytes[i], bytes[i + 1], bytes[i + 2], bytes[i + 3]));
+            }
+        }
+        return Self { width, height, pixels };
+    }
+
+    pub fn load(filename: &str) -> Result<Self, String> {
+        let surface = Surface::from_file(filename).map_err(|e| format!("{}: {}", filename, e))?;
+        let surface = surface.convert_format(PixelFormatEnum::RGBA32)?;
+        let (width, height, pitch) = (surface.width(), surface.height(), surface.pitch() as usize);
+        return Ok(surface.with_lock(|bytes| Self::from_rgba(width, height, pitch, bytes)));
+    }
+
+    pub fn to_rgba(&self) -> Vec<u8> {
+        return self.pixels.iter().flat_map(|c| [c.r, c.g, c.b, c.a]).collect();
+    }
+
+    // into RGBA `bytes` that are `pitch` bytes per row, like a locked texture
+    pub fn write_rgba(&self, bytes: &mut [u8], pitch: usize) {
+        for (y, row) in self.pixels.chunks(self.width as usize).enumerate() {
+            for (x, c) in row.iter().enumerate() {
+                let i = y * pitch + x * 4;
+                bytes[i..i + 4].copy_from_slice(&[c.r, c.g, c.b, c.a]);
+            }
+        }
+    }
+
+    pub fn save_bmp(&self, filename: &str) -> Result<(), String> {
+        let mut bytes = self.to_rgba();
+        let surface = Surface::from_data(&mut bytes, self.width, self.height, self.width * 4, PixelFormatEnum::RGBA32)?;
+        return surface.save_bmp(filename).map_err(|e| format!("{}: {}", filename, e));
+    }
+
+    pub fn fill(&mut self, color: Color) {
+        self.pixels.fill(color);
+    }
+
+    pub fn get(&self, x: u32, y: u32) -> Color {
+        return self.pixels[(y * self.width + x) as usize];
+    }
+
+    pub fn set(&mut self, x: u32, y: u32, color: Color) {
+        self.pixels[(y * self.width + x) as usize] = color;
+    }
+
+    // nearest pixel at texture coordinates `u` and `v`, repeating outside of 0 to 1
+    pub fn sample(&self, u: f32, v: f32) -> Color {
+        let x = ((u * self.width as f32).floor() as i64).rem_euclid(self.width as i64) as u32;
+        let y = ((v * self.height as f32).floor() as i64).rem_euclid(self.height as i64) as u32;
+        return self.get(x, y);
+    }
+
+    // A tangent-space normal map for `Material::normal_map` from a height map, brighter being higher. `strength`
+    // is how steep the slopes are, the edges wrap around like `sample` does.
+    pub fn normal_map_from_height(&self, strength: f32) -> Image {
+        let (width, height) = (self.width as i64, self.height as i64);
+        let at = |x: i64, y: i64| -> f32 {
+            let c = self.get(x.rem_euclid(width) as u32, y.rem_euclid(height) as u32);
+            return (c.r as f32 * 0.299 + c.g as f32 * 0.587 + c.b as f32 * 0.114) / 255.0;
+        };
+
+        let mut image = Image::new(self.width, self.height, Color::RGB(128, 128, 255));
+        for y in 0..height {
+            for x in 0..width {
+                // sobel, green points up the image while its rows go down
+                let dx = (at(x + 1, y - 1) + 2.0 * at(x + 1, y) + at(x + 1, y + 1)) - (at(x - 1, y - 1) + 2.0 * at(x - 1, y) + at(x - 1, y + 1));
+                let dy = (at(x - 1, y + 1) + 2.0 * at(x, y + 1) + at(x + 1, y + 1)) - (at(x - 1, y - 1) + 2.0 * at(x, y - 1) + at(x + 1, y - 1));
+                let normal = Vec3::new(-dx * strength, dy * strength, 1.0).normalized();
+                let channel = |n: f32| ((n * 0.5 + 0.5) * 255.0).round() as u8;
+                image.set(x as u32, y as u32, Color::RGB(channel(normal.x), channel(normal.y), channel(normal.z)));
+            }
+        }
+        return image;
+    }
+}
+
+
+// Draws with SDL's renderer, either into a window or, with a surface canvas, into memory
+pub struct CanvasBackend<'t, T: RenderTarget> {
+    pub canvas: Canvas<T>,
+    texture: Option<Texture<'t>>,
+    background: Option<Texture<'t>>
+}
+impl<'t, T: RenderTarget> CanvasBackend<'t, T> {
+    // `texture` must come from the canvas' texture creator
+    pub fn new(canvas: Canvas<T>, texture: Option<Texture<'t>>) -> Self {
+        return Self { canvas, texture, background: None };
+    }
+
+    // Backgrounds are uploaded into `texture`, a streaming RGBA32 one from the canvas' texture creator as big as the
+    // canvas. Without it there's no background.
+    pub fn with_background(mut self, texture: Texture<'t>) -> Self {
+        self.background = Some(texture);
+        return self;
+    }
+
+    fn draw_filled(&mut self, triangle: &Triangle, material: &Material) {
+        let p = triangle.points();
+        let color = material.tint(triangle.get_color());
+        self.canvas.filled_trigon(p[0].x as i16, p[0].y as i16, p[1].x as i16, p[1].y as i16, p[2].x as i16, p[2].y as i16, reverse_color(color)).ok();
+    }
+
+    fn draw_outline(&mut self, triangle: &Triangle, color: Color) {
+        let p = triangle.points();
+        self.canvas.trigon(p[0].x as i16, p[0].y as i16, p[1].x as i16, p[1].y as i16, p[2].x as i16, p[2].y as i16, reverse_color(color)).ok();
+    }
+
+    // with SDL's renderer instead of SDL_gfx, which only ever blends by alpha
+    fn draw_geometry(&mut self, triangle: &Triangle, material: &Material, textured: bool) {
+        use sdl2::render::Vertex;
+        use sdl2::render::VertexIndices;
+        use sdl2::rect::FPoint;
+
+        let vertex = |i: usize| {
+            let (position, attributes) = (triangle.vertices[i].position, triangle.vertices[i].attributes);
+            Vertex {
+                position: FPoint::new(position.x, position.y),
+                color: material.tint(attributes.color()),
+                tex_coord: FPoint::new(attributes.uv().x, attributes.uv().y)
+            }
+        };
+        let texture = if textured { self.texture.as_ref() } else { None };
+        self.canvas.render_geometry(&[vertex(0), vertex(1), vertex(2)], texture, VertexIndices::Sequential).expect("no textured triangle :(");
+    }
+
+    // the alpha cutoff needs a shader, SDL draws cut out parts of opaque materials like the rest
+    fn draw_blended(&mut self, triangle: &Triangle, material: &Material, textured: bool) {
+        let blend_mode = match material.blend_mode {
+            BlendMode::Opaque => sdl2::render::BlendMode::None,
+            BlendMode::Alpha => sdl2::render::BlendMode::Blend,
+            BlendMode::Additive => sdl2::render::BlendMode::Add,
+            BlendMode::Multiply => sdl2::render::BlendMode::Mul
+        };
+        let previous = self.texture.as_ref().map(|texture| texture.blend_mode());
+
+        self.canvas.set_blend_mode(blend_mode);
+        if let Some(texture) = self.texture.as_mut() {
+            texture.set_blend_mode(blend_mode);
+        }
+        self.draw_geometry(triangle, material, textured);
+
+        self.canvas.set_blend_mode(sdl2::render::BlendMode::None);
+        if let (Some(texture), Some(previous)) = (self.texture.as_mut(), previous) {
+            texture.set_blend_mode(previous);
+        }
+    }
+}
+impl<'t, T: RenderTarget> RenderBackend for CanvasBackend<'t, T> {
+    fn size(&self) -> (u32, u32) {
+        return self.canvas.output_size().unwrap_or((0, 0));
+    }
+
+    // SDL's renderer only shades the corners
+    fn shades_pixels(&self) -> bool {
+        return false;
+    }
+
+    fn begin_frame(&mut self, clear_color: Color) {
+        self.canvas.set_draw_color(clear_color);
+        self.canvas.clear();
+    }
+
+    // SDL_gfx only draws aliased triangles
+    fn set_antialiasing(&mut self, _antialiasing: Antialiasing) {}
+
+    // SDL can't run `shader` per pixel, the corners keep the colours the vertex shader gave them
+    fn draw_triangles(&mut self, triangles: &[Triangle], render_mode: RenderMode, _shader: &dyn Shader, _uniforms: &Uniforms, materials: &[Material]) {
+        for triangle in triangles {
+            let material = &materials[triangle.material as usize];
+            let textured = matches!(render_mode, RenderMode::Textured | RenderMode::Pbr) && self.texture.is_some();
+            match render_mode {
+                RenderMode::Outline => { self.draw_outline(triangle, Color::WHITE); },
+                _ if material.is_transparent() => { self.draw_blended(triangle, material, textured); },
+                _ if textured => { self.draw_geometry(triangle, material, true); },
+                _ => { self.draw_filled(triangle, material); }
+            }
+        }
+    }
+
+    fn ambient_occlusion(&mut self, _triangles: &[Triangle], _uniforms: &Uniforms, _materials: &[Material], _ssao: &Ssao) {}
+
+    fn draw_background(&mut self, image: &Image) {
+        if let Some(background) = self.background.as_mut() {
+            background.with_lock(None, |bytes, pitch| image.write_rgba(bytes, pitch)).ok();
+            self.canvas.copy(background, None, None).ok();
+        }
+    }
+
+    fn draw_line(&mut self, from: Vec2, to: Vec2, color: Color) {
+        self.canvas.line(from.x as i16, from.y as i16, to.x as i16, to.y as i16, reverse_color(color)).ok();
+    }
+
+    // SDL's renderer has no HDR buffer to post process
+    fn end_frame(&mut self, _post: &PostProcess) {}
+
+    fn present(&mut self) {
+        self.canvas.present();
+    }
+
+    fn read_pixels(&mut self) -> Option<Image> {
+        let (width, height) = self.size();
+        let bytes = self.canvas.read_pixels(None, PixelFormatEnum::RGBA32).ok()?;
+        return Some(Image::from_rgba(width, height, width as usize * 4, &bytes));
+    }
+}
+
+
+// Throws everything away, for measuring the pipeline without any drawing
+pub struct NullBackend {
+    pub width: u32,
+    pub height: u32,
+    pub triangles: usize // drawn since `begin_frame`
+}
+impl NullBackend {
+    pub fn new(width: u32, height: u32) -> Self {
+        return Self { width, height, triangles: 0 };
+    }
+}
+impl RenderBackend for NullBackend {
+    fn size(&self) -> (u32, u32) {
+        return (self.width, self.height);
+    }
+
+    fn shades_pixels(&self) -> bool {
+        return false;
+    }
+
+    fn begin_frame(&mut self, _clear_color: Color) {
+        self.triangles = 0;
+    }
+
+    fn set_antialiasing(&mut self, _antialiasing: Antialiasing) {}
+
+    fn draw_triangles(&mut self, triangles: &[Triangle], _render_mode: RenderMode, _shader: &dyn Shader, _uniforms: &Uniforms, _materials: &[Material]) {
+        self.triangles += triangles.len();
+    }
+
+    fn ambient_occlusion(&mut self, _triangles: &[Triangle], _uniforms: &Uniforms, _materials: &[Material], _ssao: &Ssao) {}
+
+    fn draw_background(&mut self, _image: &Image) {}
+
+    fn draw_line(&mut self, _from: Vec2, _to: Vec2, _color: Color) {}
+
+    fn end_frame(&mut self, _post: &PostProcess) {}
+
+    fn present(&mut self) {}
+
+    fn read_pixels(&mut self) -> Option<Image> {
+        return None;
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn flat_height_maps_face_straight_out() {
+        let normals = Image::new(8, 8, Color::RGB(90, 90, 90)).normal_map_from_height(2.0);
+        assert!(normals.pixels.iter().all(|pixel| *pixel == Color::RGB(128, 128, 255)));
+    }
+
+    #[test]
+    fn ramps_tilt_away_from_the_slope() {
+        // higher to the right
+        let mut heights = Image::new(8, 8, Color::BLACK);
+        for y in 0..8 {
+            for x in 0..8 {
+                heights.set(x, y, Color::RGB(x as u8 * 30, x as u8 * 30, x as u8 * 30));
+            }
+        }
+        let normal = heights.normal_map_from_height(2.0).get(4, 4);
+        assert!(normal.r < 128);
+        assert_eq!(normal.g, 128);
+        assert!(normal.b < 255);
+    }
+}
@@ -0,0 +1,117 @@
+use crate::math::{Matrix4x4, Vec3, WORLD_UP};
+
+
+#[derive(Copy, Clone, Eq, PartialEq)]
+pub enum Projection {
+    Perspective,
+    Orthographic
+}
+
+
+#[derive(Copy, Clone)]
+pub struct Camera {
+    pub pos: Vec3,
+    pub look_direction: Vec3,
+    pub yaw: f32,
+    pub pitch: f32,
+    pub roll: f32,
+    pub fov: f32, // vertical field of view, in degrees
+    pub near: f32,
+    pub far: f32,
+    pub projection: Projection,
+    pub ortho_size: f32 // half of the visible height when using orthographic projection, in world units
+}
+impl Camera {
+    pub const MIN_FOV: f32 = 10.0;
+    pub const MAX_FOV: f32 = 150.0;
+
+    pub fn new(pos: Vec3, look_direction: Vec3, yaw: f32, pitch: f32, roll: f32) -> Self {
+        return Self {
+            pos,
+            look_direction,
+            yaw,
+            pitch,
+            roll,
+            fov: 90.0,
+            near: 0.1,
+            far: 1000.0,
+            projection: Projection::Perspective,
+            ortho_size: 5.0
+        }
+    }
+
+    pub fn set_fov(&mut self, fov: f32) {
+        self.fov = fov.clamp(Self::MIN_FOV, Self::MAX_FOV);
+    }
+
+    // zooming in a perspective camera narrows the field of view, in an orthographic one it shrinks the view volume
+    pub fn zoom(&mut self, amount: f32) {
+        match self.projection {
+            Projection::Perspective => { self.set_fov(self.fov - amount * 5.0); },
+            Projection::Orthographic => { self.ortho_size = (self.ortho_size * 0.9_f32.powf(amount)).max(0.1); }
+        }
+    }
+
+    // camera between `previous` and `current`, used to draw frames that fall between two simulation steps
+    pub fn interpolate(previous: &Camera, current: &Camera, alpha: f32) -> Camera {
+        let mut camera = *current;
+
+        // yaw wraps around at 2 PI, so go the short way
+        let mut yaw_delta = (current.yaw - previous.yaw) % (2.0*std::f32::consts::PI);
+        if yaw_delta > std::f32::consts::PI {
+            yaw_delta -= 2.0*std::f32::consts::PI;
+        }
+        else if yaw_delta < -std::f32::consts::PI {
+            yaw_delta += 2.0*std::f32::consts::PI;
+        }
+
+        camera.pos = previous.pos + (current.pos - previous.pos) * alpha;
+        camera.yaw = previous.yaw + yaw_delta * alpha;
+        camera.pitch = previous.pitch + (current.pitch - previous.pitch) * alpha;
+        camera.fov = previous.fov + (current.fov - previous.fov) * alpha;
+        camera.ortho_size = previous.ortho_size + (current.ortho_size - previous.ortho_size) * alpha;
+        camera.look_at(camera.yaw, camera.pitch);
+        return camera;
+    }
+
+    pub fn toggle_projection(&mut self) {
+        self.projection = match self.projection {
+            Projection::Perspective => Projection::Orthographic,
+            Projection::Orthographic => Projection::Perspective
+        };
+    }
+
+    pub fn projection_matrix(&self, aspect_ratio: f32) -> Matrix4x4 {
+        return match self.projection {
+            Projection::Perspective => Matrix4x4::projection(aspect_ratio, 1.0 / (self.fov * 0.5).to_radians().tan(), self.near, self.far),
+            Projection::Orthographic => Matrix4x4::orthographic(aspect_ratio, self.ortho_size, self.near, self.far)
+        };
+    }
+
+    pub fn look_at(&mut self, yaw: f32, pitch: f32) {
+        self.look_direction.x = pitch.cos() * yaw.sin();
+        self.look_direction.y = pitch.sin();
+        self.look_direction.z = pitch.cos() * yaw.cos();
+        self.look_direction.normalize();
+    }
+
+    pub fn get_up(&self) -> Vec3 {
+        let forward = self.get_forward();
+        let right = self.get_right();
+        return forward.cross(&right);
+    }
+
+    pub fn get_right(&self) -> Vec3 {
+        let forward = self.get_forward();
+        return WORLD_UP.cross(&forward).normalized();
+    }
+
+    pub fn get_forward(&self) -> Vec3 {
+        return self.look_direction;
+    }
+
+    // in contrast with `get_forward()`, this function shows where the "front" is. If you're a nerd, imagine it like a vector tied to the XZ plane
+    pub fn get_front(&self) -> Vec3 {
+        return Vec3::new(self.yaw.sin(), 0.0, self.yaw.cos());
+    }
+}
@@ -0,0 +1,229 @@
+use std::fmt::Write;
+
+use crate::camera::Camera;
+use crate::math::{Quat, Vec3};
+
+
+#[derive(Copy, Clone)]
+pub struct CameraKeyframe {
+    pub time: f32, // seconds since the start of the path
+    pub pos: Vec3,
+    pub orientation: Quat,
+    pub fov: f32
+}
+impl CameraKeyframe {
+    pub fn from_camera(camera: &Camera, time: f32) -> Self {
+        return Self {
+            time,
+            pos: camera.pos,
+            orientation: Quat::from_yaw_pitch(camera.yaw, camera.pitch),
+            fov: camera.fov
+        };
+    }
+
+    pub fn apply(&self, camera: &mut Camera) {
+        let (yaw, pitch) = self.orientation.to_yaw_pitch();
+        camera.pos = self.pos;
+        camera.yaw = yaw;
+        camera.pitch = pitch;
+        camera.set_fov(self.fov);
+        camera.look_at(camera.yaw, camera.pitch);
+    }
+}
+
+
+#[derive(Copy, Clone, Eq, PartialEq)]
+pub enum PathInterpolation {
+    Linear,
+    CatmullRom
+}
+
+
+// Catmull-Rom segment between `p1` and `p2`, evaluated through its equivalent cubic Bézier
+fn catmull_rom(p0: Vec3, p1: Vec3, p2: Vec3, p3: Vec3, t: f32) -> Vec3 {
+    let b1 = p1 + (p2 - p0) * (1.0 / 6.0);
+    let b2 = p2 - (p3 - p1) * (1.0 / 6.0);
+    return bezier(p1, b1, b2, p2, t);
+}
+
+pub fn bezier(b0: Vec3, b1: Vec3, b2: Vec3, b3: Vec3, t: f32) -> Vec3 {
+    let u = 1.0 - t;
+    return b0 * (u * u * u) + b1 * (3.0 * u * u * t) + b2 * (3.0 * u * t * t) + b3 * (t * t * t);
+}
+
+
+pub struct CameraPath {
+    keyframes: Vec<CameraKeyframe>,
+    pub interpolation: PathInterpolation
+}
+impl CameraPath {
+    pub fn new() -> Self {
+        return Self { keyframes: Vec::new(), interpolation: PathInterpolation::CatmullRom };
+    }
+
+    pub fn keyframes(&self) -> &[CameraKeyframe] {
+        return &self.keyframes;
+    }
+
+    pub fn is_empty(&self) -> bool {
+        return self.keyframes.is_empty();
+    }
+
+    pub fn duration(&self) -> f32 {
+        return self.keyframes.last().map_or(0.0, |k| k.time);
+    }
+
+    // keyframes are kept sorted by time, so they can be recorded in any order
+    pub fn add_keyframe(&mut self, keyframe: CameraKeyframe) {
+        let index = self.keyframes.partition_point(|k| k.time <= keyframe.time);
+        self.keyframes.insert(index, keyframe);
+    }
+
+    pub fn record(&mut self, camera: &Camera, time: f32) {
+        self.add_keyframe(CameraKeyframe::from_camera(camera, time));
+    }
+
+    pub fn sample(&self, time: f32) -> Option<CameraKeyframe> {
+        let count = self.keyframes.len();
+        if count == 0 {
+            return None;
+        }
+        if count == 1 {
+            return Some(self.keyframes[0]);
+        }
+
+        let time = time.clamp(self.keyframes[0].time, self.duration());
+        let i = self.keyframes.partition_point(|k| k.time <= time).clamp(1, count - 1);
+
+        let k1 = &self.keyframes[i - 1];
+        let k2 = &self.keyframes[i];
+        let span = k2.time - k1.time;
+        let t = if span > 0.0 { (time - k1.time) / span } else { 1.0 };
+
+        // the outer control points are duplicated at the ends of the path
+        let k0 = &self.keyframes[i.saturating_sub(2)];
+        let k3 = &self.keyframes[(i + 1).min(count - 1)];
+
+        let (pos, fov) = match self.interpolation {
+            PathInterpolation::Linear => {
+                (k1.pos + (k2.pos - k1.pos) * t, k1.fov + (k2.fov - k1.fov) * t)
+            },
+            PathInterpolation::CatmullRom => {
+                let fov = catmull_rom(
+                    Vec3::new(k0.fov, 0.0, 0.0), Vec3::new(k1.fov, 0.0, 0.0),
+                    Vec3::new(k2.fov, 0.0, 0.0), Vec3::new(k3.fov, 0.0, 0.0), t
+                ).x;
+                (catmull_rom(k0.pos, k1.pos, k2.pos, k3.pos, t), fov)
+            }
+        };
+
+        return Some(CameraKeyframe {
+            time,
+            pos,
+            orientation: k1.orientation.slerp(k2.orientation, t),
+            fov
+        });
+    }
+
+    // one keyframe per line: `k <time> <x> <y> <z> <qw> <qx> <qy> <qz> <fov>`
+    pub fn serialize(&self) -> String {
+        let mut out = String::from("# time x y z qw qx qy qz fov\n");
+        for k in &self.keyframes {
+            writeln!(
+                out, "k {} {} {} {} {} {} {} {} {}",
+                k.time, k.pos.x, k.pos.y, k.pos.z,
+                k.orientation.w, k.orientation.x, k.orientation.y, k.orientation.z, k.fov
+            ).unwrap();
+        }
+        return out;
+    }
+
+    pub fn load(filename: &str) -> Result<Self, String> {
+        let content = std::fs::read_to_string(filename).map_err(|e| format!("{}: {}", filename, e))?;
+        return content.parse::<Self>();
+    }
+
+    pub fn save(&self, filename: &str) -> Result<(), String> {
+        return std::fs::write(filename, self.serialize()).map_err(|e| format!("{}: {}", filename, e));
+    }
+}
+impl Default for CameraPath {
+    fn default() -> Self {
+        return Self::new();
+    }
+}
+impl std::str::FromStr for CameraPath {
+    type Err = String;
+
+    // the format `serialize` writes
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let mut path = Self::new();
+
+        for (n, line) in s.lines().enumerate() {
+            let i: Vec<&str> = line.split_whitespace().collect();
+            if i.is_empty() || i[0] != "k" {
+                continue;
+            }
+            if i.len() != 10 {
+                return Err(format!("line {}: expected 9 values after 'k', got {}", n + 1, i.len() - 1));
+            }
+
+            let mut values = [0.0_f32; 9];
+            for (value, text) in values.iter_mut().zip(&i[1..]) {
+                *value = text.parse::<f32>().map_err(|e| format!("line {}: {}", n + 1, e))?;
+            }
+
+            path.add_keyframe(CameraKeyframe {
+                time: values[0],
+                pos: Vec3::new(values[1], values[2], values[3]),
+                orientation: Quat::new(values[4], values[5], values[6], values[7]).normalized(),
+                fov: values[8]
+            });
+        }
+
+        return Ok(path);
+    }
+}
+
+
+// Plays a path back in real time. Only moves the camera while `playing` is set.
+pub struct CameraPathPlayer {
+    pub path: CameraPath,
+    pub time: f32,
+    pub playing: bool,
+    pub looping: bool
+}
+impl CameraPathPlayer {
+    pub fn new(path: CameraPath) -> Self {
+        return Self { path, time: 0.0, playing: false, looping: false };
+    }
+
+    pub fn play(&mut self) {
+        self.time = 0.0;
+        self.playing = !self.path.is_empty();
+    }
+
+    pub fn stop(&mut self) {
+        self.playing = false;
+    }
+
+    pub fn update(&mut self, camera: &mut Camera, dt: f32) {
+        if !self.playing {
+            return;
+        }
+
+        self.time += dt;
+        if self.time > self.path.duration() {
+            if self.looping {
+                self.time = 0.0;
+            } else {
+                self.time = self.path.duration();
+                self.playing = false;
+            }
+        }
+
+        if let Some(keyframe) = self.path.sample(self.time) {
+            keyframe.apply(camera);
+        }
+    }
+}
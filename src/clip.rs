@@ -0,0 +1,235 @@
+use crate::math::Vec4;
+use crate::mesh::Triangle;
+use crate::vertex::Vertex;
+
+
+// a triangle cut by all six planes has at most this many corners
+const MAX_VERTICES: usize = 9;
+
+
+#[derive(Copy, Clone, Eq, PartialEq)]
+pub enum Plane {
+    Left,
+    Right,
+    Bottom,
+    Top,
+    Near,
+    Far
+}
+impl Plane {
+    pub const ALL: [Plane; 6] = [Plane::Left, Plane::Right, Plane::Bottom, Plane::Top, Plane::Near, Plane::Far];
+}
+
+
+// What's left of a triangle after clipping, a convex polygon with up to `MAX_VERTICES` corners
+#[derive(Copy, Clone)]
+pub struct ClippedPolygon {
+    vertices: [Vertex; MAX_VERTICES],
+    count: usize,
+    material: u32
+}
+impl ClippedPolygon {
+    // corners that don't fit are dropped, which only rounding errors on nearly flat triangles can cause
+    fn push(&mut self, vertex: Vertex) {
+        if self.count < MAX_VERTICES {
+            self.vertices[self.count] = vertex;
+            self.count += 1;
+        }
+    }
+
+    pub fn vertices(&self) -> &[Vertex] {
+        return &self.vertices[..self.count];
+    }
+
+    // the polygon as a fan of triangles around its first corner
+    pub fn triangles(&self) -> impl Iterator<Item = Triangle> + '_ {
+        return (1..self.count.saturating_sub(1)).map(|i| Triangle { vertices: [self.vertices[0], self.vertices[i], self.vertices[i + 1]], material: self.material });
+    }
+}
+
+
+// Cuts triangles to the view frustum in clip space, where the six planes are -w <= x, y <= w and 0 <= z <= w.
+// Points are only divided by w once they're inside, so nothing behind the camera ever gets projected. Where an edge
+// is cut, every attribute of the new corner is interpolated from the two ends.
+//
+// With a guard band bigger than 1 the side planes are moved out to `guard_band` times the screen. Triangles poking out
+// of the screen by less than that are left for the rasterizer, which only draws what's on the screen anyway, so fewer
+// triangles get cut into pieces. Near and far are always clipped. Keep it small for `CanvasBackend`, SDL_gfx draws with
+// 16 bit coordinates.
+#[derive(Copy, Clone)]
+pub struct Clipper {
+    pub guard_band: f32,
+    pub width: u32,
+    pub height: u32
+}
+impl Clipper {
+    pub fn new(width: u32, height: u32, guard_band: f32) -> Self {
+        return Self { guard_band: guard_band.max(1.0), width, height };
+    }
+
+    // positive inside
+    fn distance(&self, plane: Plane, p: Vec4) -> f32 {
+        return match plane {
+            Plane::Left => p.x + self.guard_band * p.w,
+            Plane::Right => self.guard_band * p.w - p.x,
+            Plane::Bottom => p.y + self.guard_band * p.w,
+            Plane::Top => self.guard_band * p.w - p.y,
+            Plane::Near => p.z,
+            Plane::Far => p.w - p.z
+        };
+    }
+
+    // bit per plane the point is outside of
+    fn outcode(&self, p: Vec4) -> u8 {
+        let mut code = 0;
+        for (i, plane) in Plane::ALL.iter().enumerate() {
+            if self.distance(*plane, p) < 0.0 {
+                code |= 1 << i;
+            }
+        }
+        return code;
+    }
+
+    // Sutherland-Hodgman against one plane after another, keeping the winding of the triangle
+    pub fn clip(&self, triangle: &Triangle) -> ClippedPolygon {
+        let (triangle, material) = (triangle.vertices, triangle.material);
+        let mut polygon = ClippedPolygon { vertices: [triangle[0]; MAX_VERTICES], count: 0, material };
+        let codes = triangle.map(|vertex| self.outcode(vertex.position));
+
+        // all outside of the same plane
+        if codes[0] & codes[1] & codes[2] != 0 {
+            return polygon;
+        }
+
+        polygon.vertices[..3].copy_from_slice(&triangle);
+        polygon.count = 3;
+
+        // all inside, which is most of them
+        if codes[0] | codes[1] | codes[2] == 0 {
+            return polygon;
+        }
+
+        let mut next = polygon;
+        for (i, plane) in Plane::ALL.iter().enumerate() {
+            if (codes[0] | codes[1] | codes[2]) & (1 << i) == 0 {
+                continue;
+            }
+
+            next.count = 0;
+            for corner in 0..polygon.count {
+                let a = polygon.vertices[corner];
+                let b = polygon.vertices[(corner + 1) % polygon.count];
+                let (distance_a, distance_b) = (self.distance(*plane, a.position), self.distance(*plane, b.position));
+
+                if distance_a >= 0.0 {
+                    next.push(a);
+                }
+                if (distance_a >= 0.0) != (distance_b >= 0.0) {
+                    next.push(a.lerp(&b, distance_a / (distance_a - distance_b)));
+                }
+            }
+
+            std::mem::swap(&mut polygon, &mut next);
+            if polygon.count < 3 {
+                polygon.count = 0;
+                break;
+            }
+        }
+        return polygon;
+    }
+
+    // Perspective divide and viewport transform, flipping both axes like the pipeline always has.
+    // `z` keeps the depth between 0 at the near plane and 1 at the far one, `w` becomes 1 / w.
+    pub fn project(&self, triangle: Triangle) -> Triangle {
+        let mut projected = triangle;
+        for vertex in &mut projected.vertices {
+            let p = vertex.position;
+            vertex.position = Vec4::new(
+                (1.0 - p.x / p.w) * 0.5 * self.width as f32,
+                (1.0 - p.y / p.w) * 0.5 * self.height as f32,
+                p.z / p.w,
+                1.0 / p.w
+            );
+        }
+        return projected;
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use sdl2::pixels::Color;
+    use crate::backend::RenderBackend;
+    use crate::math::{Vec2, Vec3};
+    use crate::mesh::Mesh;
+    use crate::render::{Parallelism, RenderMode, Renderer};
+    use crate::scene::Scene;
+    use crate::software::SoftwareBackend;
+    use crate::stats::RenderStats;
+    use crate::vertex::Attributes;
+
+    // a triangle in clip space, with `u` of each corner its x
+    fn triangle(points: [(f32, f32, f32); 3]) -> Triangle {
+        return Triangle::from_vertices(points.map(|(x, y, z)| {
+            let mut attributes = Attributes::new();
+            attributes.set_uv(Vec2::new(x, 0.0));
+            return Vertex { position: Vec4::new(x, y, z, 1.0), attributes };
+        }));
+    }
+
+    #[test]
+    fn triangles_inside_are_left_alone() {
+        let clipper = Clipper::new(100, 100, 1.0);
+        let polygon = clipper.clip(&triangle([(0.0, 0.0, 0.5), (0.5, 0.0, 0.5), (0.0, 0.5, 0.5)]));
+        assert_eq!(polygon.vertices().len(), 3);
+        assert_eq!(polygon.triangles().count(), 1);
+    }
+
+    #[test]
+    fn triangles_outside_one_plane_are_dropped() {
+        let clipper = Clipper::new(100, 100, 1.0);
+        let polygon = clipper.clip(&triangle([(0.0, 0.0, -0.5), (0.5, 0.0, -0.5), (0.0, 0.5, -0.1)]));
+        assert_eq!(polygon.triangles().count(), 0);
+    }
+
+    #[test]
+    fn triangles_through_the_near_plane_are_cut_at_it() {
+        let clipper = Clipper::new(100, 100, 1.0);
+        // one corner behind the camera leaves a quad
+        let polygon = clipper.clip(&triangle([(-0.5, 0.0, 0.5), (0.5, 0.0, -0.5), (0.0, 0.5, 0.5)]));
+        assert_eq!(polygon.vertices().len(), 4);
+        assert_eq!(polygon.triangles().count(), 2);
+        assert!(polygon.vertices().iter().all(|vertex| vertex.position.z >= 0.0));
+
+        // and the attributes of the new corners are interpolated along the edges, the same as their position
+        for vertex in polygon.vertices() {
+            assert!((vertex.attributes.uv().x - vertex.position.x).abs() < 1e-6);
+        }
+    }
+
+    #[test]
+    fn the_guard_band_moves_the_side_planes_out() {
+        let poking_out = triangle([(0.0, 0.0, 0.5), (1.5, 0.0, 0.5), (0.0, 0.5, 0.5)]);
+        assert_eq!(Clipper::new(100, 100, 1.0).clip(&poking_out).vertices().len(), 4);
+        assert_eq!(Clipper::new(100, 100, 2.0).clip(&poking_out).vertices().len(), 3);
+    }
+
+    #[test]
+    fn floors_reaching_behind_the_camera_are_drawn_below_the_horizon() {
+        let mut scene = Scene::new();
+        let corners = [Vec3::new(-10.0, -1.0, -10.0), Vec3::new(10.0, -1.0, -10.0), Vec3::new(10.0, -1.0, 10.0), Vec3::new(-10.0, -1.0, 10.0)];
+        let uv = [Vec2::new(0.0, 0.0); 3];
+        scene.add_mesh(Mesh::new(vec![
+            Triangle::new([corners[0], corners[2], corners[1]], uv, Color::WHITE),
+            Triangle::new([corners[0], corners[3], corners[2]], uv, Color::WHITE)
+        ]));
+        scene.render_mode = RenderMode::Filled;
+
+        let mut backend = SoftwareBackend::new(40, 30, None);
+        Renderer::new(Parallelism::serial()).draw(&mut backend, &scene, &scene.camera, &mut RenderStats::new());
+        let image = backend.read_pixels().expect("Failed to read the frame");
+        assert!((0..image.width).all(|x| image.get(x, 0) == Color::BLACK));
+        assert!((0..image.width).all(|x| image.get(x, image.height - 1) != Color::BLACK));
+    }
+}
@@ -0,0 +1,57 @@
+use std::time::{Instant, Duration};
+
+
+// Fixed-rate simulation steps, decoupled from how fast frames are drawn.
+// Real frame time goes into an accumulator and is taken out again in `step` sized pieces; whatever is left
+// over is the `alpha` used to interpolate between the last two simulated states when rendering.
+pub struct FixedTimestep {
+    last_frame: Instant,
+    accumulator: Duration,
+    steps_this_frame: u32,
+    pub step: Duration,
+    pub max_frame_time: Duration, // longer frames (breakpoints, window drags) are cut down to this
+    pub max_steps: u32 // steps per frame before the rest of the accumulator is dropped
+}
+impl FixedTimestep {
+    pub fn new(updates_per_second: u32) -> Self {
+        return Self {
+            last_frame: Instant::now(),
+            accumulator: Duration::new(0, 0),
+            steps_this_frame: 0,
+            step: Duration::new(0, 1_000_000_000_u32/updates_per_second),
+            max_frame_time: Duration::from_millis(250),
+            max_steps: 8
+        };
+    }
+
+    // returns the real time since the last frame
+    pub fn begin_frame(&mut self) -> Duration {
+        let frame_time = self.last_frame.elapsed();
+        self.last_frame = Instant::now();
+        self.accumulator += frame_time.min(self.max_frame_time);
+        self.steps_this_frame = 0;
+        return frame_time;
+    }
+
+    // true while another simulation step is due this frame
+    pub fn step(&mut self) -> bool {
+        if self.accumulator < self.step {
+            return false;
+        }
+
+        // when the simulation can't keep up, slow it down instead of falling further behind every frame
+        if self.steps_this_frame >= self.max_steps {
+            self.accumulator = Duration::new(0, 0);
+            return false;
+        }
+
+        self.accumulator -= self.step;
+        self.steps_this_frame += 1;
+        return true;
+    }
+
+    // how far between the previous and the current simulation step the frame being drawn is, from 0 to 1
+    pub fn alpha(&self) -> f32 {
+        return self.accumulator.as_secs_f32() / self.step.as_secs_f32();
+    }
+}
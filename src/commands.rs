@@ -0,0 +1,215 @@
+use sdl2::pixels::Color;
+
+use crate::console::Console;
+use crate::controller::OrbitController;
+use crate::engine::App;
+use crate::fog::FogMode;
+use crate::math::Vec3;
+use crate::mesh::Mesh;
+use crate::overlay::PerformanceOverlay;
+use crate::post::{PostProcess, ToneMapping};
+use crate::render::RenderMode;
+use crate::scene::Scene;
+use crate::skybox::CubeMap;
+
+
+// Console commands change the scene directly, outside of the recorded input, so `--record` doesn't capture them
+pub fn register_builtin(console: &mut Console<App>) {
+    console.register("load", "load <file.obj> [x y z]", "adds a mesh to the scene", command_load);
+    console.register("tp", "tp <x> <y> <z>", "moves the camera", command_teleport);
+    console.register("set", "set [name] [value...]", "changes a setting, lists them without a name", command_set);
+    console.register("mode", "mode <outline|filled|textured|pbr>", "sets the render mode", command_mode);
+    console.register("skybox", "skybox <off|image|+x -x +y -y +z -z>", "sets the sky, from an equirectangular image or six faces", command_skybox);
+    console.register("fog", "fog <off|linear|exp|exp2> [r g b]", "sets the kind of fog and its colour", command_fog);
+    console.register("post", "post [pass] [on|off|reinhard|aces]", "toggles a post processing pass, lists them without one", command_post);
+    console.register("ssao", "ssao [on|off]", "toggles screen space ambient occlusion", command_ssao);
+    console.register("list", "list <meshes|settings>", "lists meshes or settings", command_list);
+    console.register("stats", "stats", "prints frame timing statistics", command_stats);
+}
+
+pub fn parse_floats(args: &[&str], count: usize) -> Result<Vec<f32>, String> {
+    if args.len() != count {
+        return Err(format!("expected {} numbers", count));
+    }
+    return args.iter().map(|arg| arg.parse::<f32>().map_err(|_| format!("'{}' is not a number", arg))).collect();
+}
+
+// everything `set` can change that's a single number, `list settings` goes through the same ones
+fn settings(scene: &mut Scene) -> Vec<(&'static str, &mut f32)> {
+    let camera = &mut scene.camera;
+    let mut settings = vec![
+        ("fov", &mut camera.fov),
+        ("near", &mut camera.near),
+        ("far", &mut camera.far),
+        ("ortho_size", &mut camera.ortho_size),
+        ("fog_start", &mut scene.fog.start),
+        ("fog_end", &mut scene.fog.end),
+        ("fog_density", &mut scene.fog.density),
+        ("exposure", &mut scene.post.exposure),
+        ("bloom_threshold", &mut scene.post.bloom_threshold),
+        ("bloom_strength", &mut scene.post.bloom_strength),
+        ("vignette_strength", &mut scene.post.vignette_strength),
+        ("ssao_radius", &mut scene.ssao.radius),
+        ("ssao_strength", &mut scene.ssao.strength)
+    ];
+    settings.extend(scene.controller.settings());
+    return settings;
+}
+
+fn command_load(app: &mut App, args: &[&str]) -> Result<String, String> {
+    let Some((filename, position)) = args.split_first() else {
+        return Err(String::from("expected a file name"));
+    };
+    let mut mesh = Mesh::load(filename)?;
+    if !position.is_empty() {
+        let p = parse_floats(position, 3)?;
+        mesh.translate(p[0], p[1], p[2]);
+    }
+
+    let count = mesh.triangle_count();
+    app.scene.meshes.push(mesh);
+    return Ok(format!("loaded {} triangles as mesh {}", count, app.scene.meshes.len() - 1));
+}
+
+fn command_teleport(app: &mut App, args: &[&str]) -> Result<String, String> {
+    let p = parse_floats(args, 3)?;
+    let scene = &mut app.scene;
+    scene.camera.pos = Vec3::new(p[0], p[1], p[2]);
+    scene.previous_camera = scene.camera;
+
+    // the orbit controller would pull the camera straight back to its target
+    if scene.controller.name() == "orbit" {
+        let distance = scene.controller.settings().into_iter().find(|(name, _)| *name == "distance").map_or(5.0, |(_, value)| *value);
+        scene.controller = Box::new(OrbitController::from_camera(&scene.camera, distance));
+    }
+    return Ok(format!("camera at {}", scene.camera.pos));
+}
+
+fn command_set(app: &mut App, args: &[&str]) -> Result<String, String> {
+    let scene = &mut app.scene;
+    let Some((name, values)) = args.split_first() else {
+        return command_list(app, &["settings"]);
+    };
+
+    if *name == "light" {
+        let d = parse_floats(values, 3)?;
+        scene.light_direction = Vec3::new(d[0], d[1], d[2]);
+        return Ok(format!("light {} {} {}", d[0], d[1], d[2]));
+    }
+
+    let value = parse_floats(values, 1)?[0];
+    if *name == "near" && (value <= 0.0 || value >= scene.camera.far) {
+        return Err(format!("near has to be above 0 and below far ({})", scene.camera.far));
+    }
+    if *name == "far" && value <= scene.camera.near {
+        return Err(format!("far has to be beyond near ({})", scene.camera.near));
+    }
+
+    let Some((_, setting)) = settings(scene).into_iter().find(|(setting, _)| setting == name) else {
+        return Err(format!("unknown setting '{}'", name));
+    };
+    *setting = value;
+
+    scene.camera.set_fov(scene.camera.fov);
+    scene.previous_camera = scene.camera;
+    return Ok(format!("{} = {}", name, value));
+}
+
+fn command_mode(app: &mut App, args: &[&str]) -> Result<String, String> {
+    app.scene.render_mode = match args {
+        ["outline"] => RenderMode::Outline,
+        ["filled"] => RenderMode::Filled,
+        ["textured"] => RenderMode::Textured,
+        ["pbr"] => RenderMode::Pbr,
+        _ => { return Err(String::from("expected outline, filled, textured or pbr")); }
+    };
+    return Ok(format!("mode {}", app.scene.render_mode));
+}
+
+fn command_fog(app: &mut App, args: &[&str]) -> Result<String, String> {
+    let Some((mode, color)) = args.split_first() else {
+        return Err(String::from("expected off, linear, exp or exp2"));
+    };
+    let fog = &mut app.scene.fog;
+    fog.mode = mode.parse::<FogMode>()?;
+    if !color.is_empty() {
+        let c = parse_floats(color, 3)?;
+        fog.color = Color::RGB(c[0] as u8, c[1] as u8, c[2] as u8);
+    }
+    return Ok(format!("fog {} ({}, {}, {})", fog.mode, fog.color.r, fog.color.g, fog.color.b));
+}
+
+fn command_post(app: &mut App, args: &[&str]) -> Result<String, String> {
+    let post = &mut app.scene.post;
+    let Some((name, value)) = args.split_first() else {
+        let lines: Vec<String> = PostProcess::PASSES.iter().map(|name| {
+            let on = if *post.pass_mut(name).unwrap() { "on" } else { "off" };
+            if *name == "tonemap" { format!("{} {} ({})", name, on, post.tone_mapping) } else { format!("{} {}", name, on) }
+        }).collect();
+        return Ok(lines.join("\n"));
+    };
+
+    let tone_mapping = if *name == "tonemap" { value.first().and_then(|value| value.parse::<ToneMapping>().ok()) } else { None };
+    let Some(pass) = post.pass_mut(name) else {
+        return Err(format!("no pass called '{}', expected one of {}", name, PostProcess::PASSES.join(", ")));
+    };
+    *pass = match value {
+        [] => !*pass,
+        ["on"] => true,
+        ["off"] => false,
+        [_] if tone_mapping.is_some() => true,
+        _ => { return Err(String::from("expected on or off, or reinhard or aces for tonemap")); }
+    };
+
+    let on = *pass;
+    if let Some(tone_mapping) = tone_mapping {
+        post.tone_mapping = tone_mapping;
+    }
+    return Ok(format!("{} {}", name, if on { "on" } else { "off" }));
+}
+
+fn command_ssao(app: &mut App, args: &[&str]) -> Result<String, String> {
+    let ssao = &mut app.scene.ssao;
+    ssao.enabled = match args {
+        [] => !ssao.enabled,
+        ["on"] => true,
+        ["off"] => false,
+        _ => { return Err(String::from("expected on or off")); }
+    };
+    return Ok(format!("ssao {}", if ssao.enabled { "on" } else { "off" }));
+}
+
+fn command_skybox(app: &mut App, args: &[&str]) -> Result<String, String> {
+    if args == ["off"] {
+        app.scene.skybox = None;
+        return Ok(String::from("no sky"));
+    }
+    let filenames: Vec<String> = args.iter().map(|arg| arg.to_string()).collect();
+    let skybox = CubeMap::load(&filenames)?;
+    let size = skybox.size();
+    app.scene.skybox = Some(skybox);
+    return Ok(format!("sky with {}x{} faces", size, size));
+}
+
+fn command_list(app: &mut App, args: &[&str]) -> Result<String, String> {
+    let scene = &mut app.scene;
+    let lines: Vec<String> = match args {
+        ["meshes"] => scene.meshes.iter().enumerate().map(|(i, mesh)| {
+            let bounds = mesh.bounds().map_or(String::from("empty"), |(min, max)| format!("{} - {}", min, max));
+            let selected = if i == scene.selected_mesh { "*" } else { " " };
+            format!("{}{:<3}{:>7} triangles  {}", selected, i, mesh.triangle_count(), bounds)
+        }).collect(),
+        ["settings"] => {
+            let light = scene.light_direction;
+            let mut lines: Vec<String> = settings(scene).into_iter().map(|(name, value)| format!("{} = {}", name, value)).collect();
+            lines.push(format!("light = {} {} {}", light.x, light.y, light.z));
+            lines
+        },
+        _ => { return Err(String::from("expected meshes or settings")); }
+    };
+    return Ok(lines.join("\n"));
+}
+
+fn command_stats(app: &mut App, _args: &[&str]) -> Result<String, String> {
+    return Ok(PerformanceOverlay::lines(&app.frame_stats).join("\n"));
+}
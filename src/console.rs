@@ -0,0 +1,152 @@
+use std::collections::{BTreeMap, VecDeque};
+use sdl2::event::Event;
+use sdl2::keyboard::Keycode;
+use sdl2::pixels::Color;
+use sdl2::rect::{Point, Rect};
+use sdl2::render::{Canvas, RenderTarget, TextureCreator};
+
+use crate::hud::{Align, Hud, PADDING, PANEL_COLOR};
+use crate::input::{Input, InputEvent};
+
+
+const OUTPUT_LINES: usize = 200;
+const VISIBLE_LINES: usize = 12;
+const ERROR_COLOR: Color = Color::RGB(240, 110, 100);
+
+// Runs a command with its arguments (without the command name). `Ok` text is printed, `Err` text is printed as an error.
+pub type CommandFn<T> = fn(&mut T, &[&str]) -> Result<String, String>;
+
+pub struct Command<T> {
+    pub usage: &'static str,
+    pub help: &'static str,
+    pub run: CommandFn<T>
+}
+
+
+// Typed commands that act on a `T`. Commands are registered by name, `help` and `clear` are always there.
+// While `open` is set, the console wants SDL's text input and all keyboard and mouse events.
+pub struct Console<T> {
+    commands: BTreeMap<&'static str, Command<T>>,
+    pub open: bool,
+    input: String,
+    output: VecDeque<(String, Color)>,
+    history: Vec<String>,
+    history_index: usize // `history.len()` when not browsing
+}
+impl<T> Console<T> {
+    pub fn new() -> Self {
+        return Self {
+            commands: BTreeMap::new(),
+            open: false,
+            input: String::new(),
+            output: VecDeque::new(),
+            history: Vec::new(),
+            history_index: 0
+        };
+    }
+
+    pub fn register(&mut self, name: &'static str, usage: &'static str, help: &'static str, run: CommandFn<T>) {
+        self.commands.insert(name, Command { usage, help, run });
+    }
+
+    pub fn print(&mut self, text: &str) {
+        self.print_colored(text, Color::WHITE);
+    }
+
+    fn print_colored(&mut self, text: &str, color: Color) {
+        for line in text.lines() {
+            if self.output.len() == OUTPUT_LINES {
+                self.output.pop_front();
+            }
+            self.output.push_back((line.to_string(), color));
+        }
+    }
+
+    pub fn execute(&mut self, line: &str, target: &mut T) {
+        let words: Vec<&str> = line.split_whitespace().collect();
+        let Some((name, args)) = words.split_first() else {
+            return;
+        };
+        self.print_colored(&format!("> {}", line), Color::GREY);
+
+        match *name {
+            "help" => {
+                let help: Vec<String> = self.commands.values()
+                    .map(|command| format!("{:<24}{}", command.usage, command.help))
+                    .collect();
+                self.print(&help.join("\n"));
+                self.print(&format!("{:<24}{}", "clear", "empties the console"));
+            },
+            "clear" => { self.output.clear(); },
+            _ => match self.commands.get(name) {
+                Some(command) => match (command.run)(target, args) {
+                    Ok(text) => { self.print(&text); },
+                    Err(e) => { self.print_colored(&format!("{}\nusage: {}", e, command.usage), ERROR_COLOR); }
+                },
+                None => { self.print_colored(&format!("unknown command '{}', try 'help'", name), ERROR_COLOR); }
+            }
+        }
+    }
+
+    // returns whether the event was used by the console, it closes on Escape or what `toggle_console` is bound to in `input`
+    pub fn handle_event(&mut self, event: &Event, input: &Input, target: &mut T) -> bool {
+        if !self.open {
+            return false;
+        }
+        if InputEvent::from_sdl(event).is_some_and(|event| input.triggers("toggle_console", &event)) {
+            self.open = false;
+            return true;
+        }
+
+        match event {
+            Event::TextInput { text, .. } => { self.input.push_str(text); },
+            Event::KeyDown { keycode: Some(keycode), .. } => match *keycode {
+                Keycode::Escape => { self.open = false; },
+                Keycode::Backspace => { self.input.pop(); },
+                Keycode::Return | Keycode::KpEnter => {
+                    let line = std::mem::take(&mut self.input);
+                    if !line.trim().is_empty() && self.history.last() != Some(&line) {
+                        self.history.push(line.clone());
+                    }
+                    self.history_index = self.history.len();
+                    self.execute(&line, target);
+                },
+                Keycode::Up if self.history_index > 0 => {
+                    self.history_index -= 1;
+                    self.input = self.history[self.history_index].clone();
+                },
+                Keycode::Down if self.history_index < self.history.len() => {
+                    self.history_index += 1;
+                    self.input = self.history.get(self.history_index).cloned().unwrap_or_default();
+                },
+                _ => {}
+            },
+            // the scene shouldn't look around or click while typing
+            Event::KeyUp { .. } | Event::MouseMotion { .. } | Event::MouseButtonDown { .. } | Event::MouseButtonUp { .. } | Event::MouseWheel { .. } => {},
+            _ => { return false; }
+        }
+        return true;
+    }
+
+    // the latest output above the line being typed, along the bottom of the screen
+    pub fn draw<R: RenderTarget, C>(&self, hud: &Hud, canvas: &mut Canvas<R>, texture_creator: &TextureCreator<C>) {
+        let (width, height) = canvas.output_size().unwrap_or((0, 0));
+        let visible = self.output.len().min(VISIBLE_LINES);
+        let output_height = hud.line_height * visible as i32 + PADDING * 2;
+        let top = height as i32 - hud.line_height - PADDING * 2 - output_height;
+
+        hud.panel(canvas, Rect::new(0, top, width, output_height as u32), PANEL_COLOR);
+        let mut y = top + PADDING;
+        for (line, color) in self.output.iter().skip(self.output.len() - visible) {
+            hud.text(canvas, texture_creator, line, Point::new(PADDING * 2, y), Align::Left, *color);
+            y += hud.line_height;
+        }
+
+        hud.console_line(canvas, texture_creator, &format!("> {}_", self.input), Color::WHITE);
+    }
+}
+impl<T> Default for Console<T> {
+    fn default() -> Self {
+        return Self::new();
+    }
+}
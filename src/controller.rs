@@ -0,0 +1,176 @@
+use std::time::Duration;
+
+use crate::camera::{Camera, Projection};
+use crate::math::{Vec3, WORLD_UP};
+use crate::input::Input;
+
+
+// Something that drives the camera from user input. `main` calls `update` on the active one every frame.
+pub trait CameraController {
+    fn name(&self) -> &'static str;
+
+    fn update(&mut self, camera: &mut Camera, input: &Input, mouse_locked: bool, dt: Duration);
+
+    // moves the camera so the box between `min` and `max` fills the view
+    fn frame(&mut self, camera: &mut Camera, min: Vec3, max: Vec3);
+
+    // whether the "zoom" action is used by the controller itself, instead of changing the field of view
+    fn uses_zoom(&self) -> bool {
+        return false;
+    }
+
+    // tunable values by name, for the debug console
+    fn settings(&mut self) -> Vec<(&'static str, &mut f32)> {
+        return Vec::new();
+    }
+}
+
+
+// distance at which a sphere of `radius` fits into the camera's view
+fn framing_distance(camera: &mut Camera, radius: f32) -> f32 {
+    if camera.projection == Projection::Orthographic {
+        camera.ortho_size = radius;
+        return radius * 2.0;
+    }
+    return radius / (camera.fov * 0.5).to_radians().sin();
+}
+
+
+pub struct FlyController {
+    pub speed: f32,
+    pub sensitivity: f32
+}
+impl FlyController {
+    pub fn new(speed: f32, sensitivity: f32) -> Self {
+        return Self { speed, sensitivity };
+    }
+}
+impl CameraController for FlyController {
+    fn name(&self) -> &'static str {
+        return "fly";
+    }
+
+    fn update(&mut self, camera: &mut Camera, input: &Input, mouse_locked: bool, dt: Duration) {
+        let speed = self.speed;
+
+        if mouse_locked {
+            camera.yaw -= input.axis("look_x") / 100.0 * self.sensitivity;
+            camera.pitch -= input.axis("look_y") / 100.0 * self.sensitivity;
+        }
+        camera.yaw -= input.axis("yaw_rate") * dt.as_secs_f32();
+        camera.pitch += input.axis("pitch_rate") * dt.as_secs_f32();
+
+        // read as axes, so analog sticks and triggers move proportionally while keys give the full speed
+        let forward = (input.axis("move_forward") - input.axis("move_back")).clamp(-1.0, 1.0);
+        let strafe = (input.axis("move_right") - input.axis("move_left")).clamp(-1.0, 1.0);
+        let lift = (input.axis("ascend") - input.axis("descend")).clamp(-1.0, 1.0);
+
+        camera.pos += camera.get_front() * forward * speed * dt.as_secs_f32();
+        camera.pos -= camera.get_right() * strafe * speed * dt.as_secs_f32();
+        camera.pos += WORLD_UP * lift * speed * dt.as_secs_f32();
+
+        camera.pitch = camera.pitch.clamp(-89.9_f32.to_radians(), 89.9_f32.to_radians());
+        camera.yaw %= 2.0*std::f32::consts::PI;
+
+        camera.look_at(camera.yaw, camera.pitch);
+    }
+
+    fn frame(&mut self, camera: &mut Camera, min: Vec3, max: Vec3) {
+        let center = (min + max) * 0.5;
+        let distance = framing_distance(camera, (max - min).length() * 0.5);
+        camera.look_at(camera.yaw, camera.pitch);
+        camera.pos = center - camera.get_forward() * distance;
+    }
+
+    fn settings(&mut self) -> Vec<(&'static str, &mut f32)> {
+        return vec![("speed", &mut self.speed), ("sensitivity", &mut self.sensitivity)];
+    }
+}
+
+
+// Rotates around `target` at a fixed `distance`.
+// "orbit_rotate" (or locked mouse) - rotate, "orbit_pan" - pan, "zoom" - dolly.
+pub struct OrbitController {
+    pub target: Vec3,
+    pub distance: f32,
+    pub sensitivity: f32,
+    pub pan_speed: f32,
+    pub dolly_speed: f32,
+    pub min_distance: f32
+}
+impl OrbitController {
+    pub fn new(target: Vec3, distance: f32) -> Self {
+        return Self {
+            target,
+            distance,
+            sensitivity: 1.0,
+            pan_speed: 1.0,
+            dolly_speed: 0.1,
+            min_distance: 0.1
+        };
+    }
+
+    // picks up the current camera placement, so switching controllers doesn't make the view jump
+    pub fn from_camera(camera: &Camera, distance: f32) -> Self {
+        return Self::new(camera.pos + camera.get_forward() * distance, distance);
+    }
+
+    fn place_camera(&self, camera: &mut Camera) {
+        camera.pitch = camera.pitch.clamp(-89.9_f32.to_radians(), 89.9_f32.to_radians());
+        camera.yaw %= 2.0*std::f32::consts::PI;
+        camera.look_at(camera.yaw, camera.pitch);
+        camera.pos = self.target - camera.get_forward() * self.distance;
+    }
+}
+impl CameraController for OrbitController {
+    fn name(&self) -> &'static str {
+        return "orbit";
+    }
+
+    fn update(&mut self, camera: &mut Camera, input: &Input, mouse_locked: bool, dt: Duration) {
+        let dx = input.axis("look_x");
+        let dy = input.axis("look_y");
+
+        if input.is_held("orbit_pan") {
+            // pan speed scales with the distance, so the target moves roughly with the cursor
+            let scale = self.distance * self.pan_speed / 500.0;
+            self.target += camera.get_right() * dx * scale;
+            self.target += camera.get_up() * dy * scale;
+        }
+        else if mouse_locked || input.is_held("orbit_rotate") {
+            camera.yaw -= dx / 100.0 * self.sensitivity;
+            camera.pitch -= dy / 100.0 * self.sensitivity;
+        }
+
+        self.distance *= (1.0 - self.dolly_speed).powf(input.axis("zoom"));
+
+        // keyboard and controller orbiting for when there is no mouse at hand
+        camera.yaw += (input.axis("move_left") - input.axis("move_right")) * dt.as_secs_f32();
+        camera.yaw -= input.axis("yaw_rate") * dt.as_secs_f32();
+        camera.pitch += (input.axis("ascend") - input.axis("descend") + input.axis("pitch_rate")) * dt.as_secs_f32();
+        self.distance -= self.distance * (input.axis("move_forward") - input.axis("move_back")) * dt.as_secs_f32();
+
+        self.distance = self.distance.max(self.min_distance);
+        self.place_camera(camera);
+    }
+
+    fn frame(&mut self, camera: &mut Camera, min: Vec3, max: Vec3) {
+        self.target = (min + max) * 0.5;
+        self.distance = framing_distance(camera, (max - min).length() * 0.5).max(self.min_distance);
+        self.place_camera(camera);
+    }
+
+    fn uses_zoom(&self) -> bool {
+        return true;
+    }
+
+    fn settings(&mut self) -> Vec<(&'static str, &mut f32)> {
+        return vec![
+            ("distance", &mut self.distance),
+            ("sensitivity", &mut self.sensitivity),
+            ("pan_speed", &mut self.pan_speed),
+            ("dolly_speed", &mut self.dolly_speed),
+            ("min_distance", &mut self.min_distance)
+        ];
+    }
+}
@@ -0,0 +1,528 @@
+use sdl2::event::Event;
+use sdl2::image::LoadTexture;
+use sdl2::pixels::{Color, PixelFormatEnum};
+use sdl2::render::{Canvas, RenderTarget, SurfaceCanvas, TextureCreator};
+use sdl2::surface::{Surface, SurfaceContext};
+
+use crate::backend::{CanvasBackend, Image, NullBackend, RenderBackend};
+use crate::camera::{Camera, Projection};
+use crate::camera_path::CameraPath;
+use crate::clock::FixedTimestep;
+use crate::commands;
+use crate::console::Console;
+use crate::fog::{Fog, FogMode};
+use crate::hud::{Align, Hud, PADDING};
+use crate::input::{self, HeldState, Input};
+use crate::material::Material;
+use crate::mesh::Mesh;
+use crate::overlay::PerformanceOverlay;
+use crate::render::{Antialiasing, Parallelism, Renderer, RESOLUTION};
+use crate::replay::{FrameInput, InputRecorder, InputReplay};
+use crate::scene::Scene;
+use crate::skybox::CubeMap;
+use crate::software::SoftwareBackend;
+use crate::stats::{FrameStats, RenderStats, Stage};
+
+
+pub const UPDATES_PER_SECOND: u32 = 120;
+pub const HUD_FONT: &str = "DejaVuSansMono.ttf";
+
+
+// What frames are drawn with. The window shows `Software` frames through a texture and treats `Null` like `Canvas`.
+#[derive(Copy, Clone, Eq, PartialEq)]
+pub enum Backend {
+    Canvas,
+    Software,
+    Null
+}
+
+
+// What `Engine::run` does
+pub enum Mode {
+    Window,
+    HeadlessPath { path_file: String, out_dir: String, fps: u32 },
+    HeadlessReplay { recording_file: String, out_dir: String }
+}
+
+
+pub struct Options {
+    pub mode: Mode,
+    pub backend: Backend,
+    pub parallelism: Parallelism,
+    pub guard_band: f32,
+    pub title: String,
+    pub uncapped: bool,
+    pub record: Option<String>,
+    pub replay: Option<String>,
+    pub texture: String,
+    pub skybox: Vec<String>, // one equirectangular image or six faces, see `CubeMap::load`
+    pub normal_map: Option<String>,
+    pub antialiasing: Antialiasing,
+    pub fog: Option<Fog>, // replaces the scene's when any of the fog options are given
+    pub files: Vec<String> // arguments that aren't options, for the program to use
+}
+impl Options {
+    pub fn new() -> Self {
+        return Self {
+            mode: Mode::Window,
+            backend: Backend::Canvas,
+            parallelism: Parallelism::available(),
+            guard_band: 1.0,
+            title: String::from("3d simulation"),
+            uncapped: false,
+            record: None,
+            replay: None,
+            texture: String::from("assets/brick.png"),
+            skybox: Vec::new(),
+            normal_map: None,
+            antialiasing: Antialiasing::Off,
+            fog: None,
+            files: Vec::new()
+        };
+    }
+
+    // Reads the command line, without the program name:
+    //   `--headless <camera path> <output directory> [fps]` renders the path to images instead of opening a window
+    //   `--headless-replay <input recording> <output directory>` replays recorded input and saves every frame
+    //   `--record <file>` saves all input, `--replay <file>` plays it back instead of reading the devices
+    //   `--uncapped` draws frames as fast as possible instead of at the display's refresh rate
+    //   `--backend <canvas|software|null>` picks what frames are drawn with
+    //   `--threads <count>` limits the threads used for rendering, 1 renders everything on the main thread
+    //   `--guard-band <factor>` only clips triangles reaching further than `factor` screens, leaving the rest to the rasterizer
+    //   `--skybox <image | +x,-x,+y,-y,+z,-z>` draws a sky from an equirectangular image or six comma separated faces
+    //   `--normal-map <image>` gives the default material a tangent-space normal map
+    //   `--antialiasing <off|msaa2|msaa4|ssaa2|ssaa4>` smooths the software rasterizer's edges, see `Antialiasing`
+    //   `--fog <off|linear|exp|exp2>`, `--fog-start <distance>`, `--fog-end <distance>` and `--fog-density <density>` set up `Fog`
+    pub fn from_args(args: &[String]) -> Result<Self, String> {
+        let mut options = Self::new();
+        let mut args = args.iter().peekable();
+
+        while let Some(arg) = args.next() {
+            let mut value = |name: &str| args.next().cloned().ok_or(format!("{} is missing {}", arg, name));
+
+            match arg.as_str() {
+                "--headless" => {
+                    let path_file = value("a camera path")?;
+                    let out_dir = value("an output directory")?;
+                    let fps = match args.next_if(|fps| !fps.starts_with("--")) {
+                        Some(fps) => fps.parse::<u32>().map_err(|_| String::from("fps must be a whole number"))?,
+                        None => 30
+                    };
+                    options.mode = Mode::HeadlessPath { path_file, out_dir, fps };
+                },
+                "--headless-replay" => {
+                    options.mode = Mode::HeadlessReplay { recording_file: value("an input recording")?, out_dir: value("an output directory")? };
+                },
+                "--record" => { options.record = Some(value("a file")?); },
+                "--replay" => { options.replay = Some(value("a file")?); },
+                "--uncapped" => { options.uncapped = true; },
+                "--threads" => {
+                    let threads = value("a thread count")?.parse::<usize>().map_err(|_| String::from("the thread count must be a whole number"))?;
+                    options.parallelism = Parallelism::new(threads);
+                },
+                "--guard-band" => {
+                    options.guard_band = value("a factor")?.parse::<f32>().map_err(|_| String::from("the guard band must be a number"))?;
+                },
+                "--skybox" => {
+                    options.skybox = value("an image")?.split(',').map(String::from).collect();
+                },
+                "--normal-map" => { options.normal_map = Some(value("an image")?); },
+                "--antialiasing" => { options.antialiasing = value("a mode")?.parse::<Antialiasing>()?; },
+                "--fog" => { options.fog.get_or_insert(Fog::off()).mode = value("a mode")?.parse::<FogMode>()?; },
+                "--fog-start" => {
+                    options.fog.get_or_insert(Fog::off()).start = value("a distance")?.parse::<f32>().map_err(|_| String::from("the fog start must be a number"))?;
+                },
+                "--fog-end" => {
+                    options.fog.get_or_insert(Fog::off()).end = value("a distance")?.parse::<f32>().map_err(|_| String::from("the fog end must be a number"))?;
+                },
+                "--fog-density" => {
+                    options.fog.get_or_insert(Fog::off()).density = value("a density")?.parse::<f32>().map_err(|_| String::from("the fog density must be a number"))?;
+                },
+                "--backend" => {
+                    options.backend = match value("a backend")?.as_str() {
+                        "canvas" => Backend::Canvas,
+                        "software" => Backend::Software,
+                        "null" => Backend::Null,
+                        other => { return Err(format!("unknown backend '{}'", other)); }
+                    };
+                },
+                _ if arg.starts_with("--") => { return Err(format!("unknown option '{}'", arg)); },
+                _ => { options.files.push(arg.clone()); }
+            }
+        }
+
+        return Ok(options);
+    }
+}
+impl Default for Options {
+    fn default() -> Self {
+        return Self::new();
+    }
+}
+
+
+// What the debug console can reach: the scene and what's drawn over it
+pub struct App {
+    pub scene: Scene,
+    pub frame_stats: FrameStats,
+    pub show_stats: bool,
+    pub show_hud: bool
+}
+
+
+// Owns the scene and runs the main loop. Add meshes and console commands, then `run`.
+pub struct Engine {
+    pub app: App,
+    pub console: Console<App>,
+    pub options: Options
+}
+impl Engine {
+    pub fn new(options: Options) -> Self {
+        let mut console = Console::new();
+        commands::register_builtin(&mut console);
+
+        return Self {
+            app: App { scene: Scene::new(), frame_stats: FrameStats::new(600), show_stats: false, show_hud: true },
+            console,
+            options
+        };
+    }
+
+    pub fn scene(&mut self) -> &mut Scene {
+        return &mut self.app.scene;
+    }
+
+    // returns the index of the mesh
+    pub fn add_mesh(&mut self, mesh: Mesh) -> usize {
+        return self.app.scene.add_mesh(mesh);
+    }
+
+    // returns the index of the material, for `Mesh::set_material`
+    pub fn add_material(&mut self, material: Material) -> usize {
+        return self.app.scene.add_material(material);
+    }
+
+    pub fn run(mut self) {
+        if !self.options.skybox.is_empty() {
+            self.app.scene.skybox = Some(CubeMap::load(&self.options.skybox).expect("Failed to load skybox."));
+        }
+        self.app.scene.antialiasing = self.options.antialiasing;
+        if let Some(fog) = self.options.fog {
+            self.app.scene.fog = fog;
+        }
+        if let Some(normal_map) = &self.options.normal_map {
+            self.app.scene.materials[0].normal_map = Some(Image::load(normal_map).expect("Failed to load normal map."));
+        }
+
+        match std::mem::replace(&mut self.options.mode, Mode::Window) {
+            Mode::Window => { self.run_window(); },
+            Mode::HeadlessPath { path_file, out_dir, fps } => {
+                let canvas = headless_canvas();
+                let texture_creator = canvas.texture_creator();
+                let mut backend = self.headless_backend(canvas, &texture_creator);
+                self.render_path_headless(backend.as_mut(), &path_file, &out_dir, fps);
+            },
+            Mode::HeadlessReplay { recording_file, out_dir } => {
+                let canvas = headless_canvas();
+                let texture_creator = canvas.texture_creator();
+                let mut backend = self.headless_backend(canvas, &texture_creator);
+                self.replay_headless(backend.as_mut(), &recording_file, &out_dir);
+            }
+        }
+    }
+
+    // `canvas` is only used by `Backend::Canvas`
+    fn headless_backend<'t>(&self, canvas: SurfaceCanvas<'static>, texture_creator: &'t TextureCreator<SurfaceContext<'static>>) -> Box<dyn RenderBackend + 't> {
+        let texture_file = &self.options.texture;
+        return match self.options.backend {
+            Backend::Canvas => {
+                let texture = texture_creator.load_texture(texture_file).expect("Failed to load texture.");
+                let background = texture_creator.create_texture_streaming(PixelFormatEnum::RGBA32, RESOLUTION[0], RESOLUTION[1])
+                        .expect("Failed to create background texture.");
+                Box::new(CanvasBackend::new(canvas, Some(texture)).with_background(background))
+            },
+            Backend::Software => Box::new(SoftwareBackend::new(RESOLUTION[0], RESOLUTION[1], Some(Image::load(texture_file).expect("Failed to load texture."))).with_parallelism(self.options.parallelism.clone())),
+            Backend::Null => Box::new(NullBackend::new(RESOLUTION[0], RESOLUTION[1]))
+        };
+    }
+
+    fn run_window(self) {
+        let Engine { mut app, mut console, options } = self;
+
+        // `record` saves all input, `replay` plays it back instead of reading the devices
+        let mut recorder: Option<InputRecorder> = options.record.as_ref().map(|file| InputRecorder::create(file).expect("Failed to create input recording."));
+        let mut replay: Option<InputReplay> = options.replay.as_ref().map(|file| InputReplay::load(file).expect("Failed to load input recording."));
+
+        let sdl_context = sdl2::init().expect("Filed to initialize SDL context.");
+        let video_subsystem = sdl_context.video().expect("Failed to initialize SDL video subsystem.");
+
+
+        let mut canvas_builder = video_subsystem.window(&options.title, RESOLUTION[0], RESOLUTION[1])
+                .position_centered()
+                .build()
+                .expect("Failed to build window.")
+                .into_canvas();
+        // frames are drawn as fast as possible with `uncapped`, otherwise at the display's refresh rate
+        if !options.uncapped {
+            canvas_builder = canvas_builder.present_vsync();
+        }
+        let window = canvas_builder
+                .build()
+                .expect("Failed to convert window surface.");
+
+        let texture_creator = window.texture_creator();
+
+        let mut events = sdl_context.event_pump().expect("Failed to build event pump.");
+
+        let mut input = load_input();
+        match sdl_context.game_controller() {
+            Ok(subsystem) => { input.enable_controllers(subsystem); },
+            Err(e) => { app.scene.log(format!("Game controllers are unavailable: {}", e)); }
+        }
+
+        let mut timestep = FixedTimestep::new(UPDATES_PER_SECOND);
+
+        let mut running = true;
+
+        let brick_texture = texture_creator.load_texture(&options.texture).unwrap();
+        let background_texture = texture_creator.create_texture_streaming(PixelFormatEnum::RGBA32, RESOLUTION[0], RESOLUTION[1])
+                .expect("Failed to create background texture.");
+        let mut backend = CanvasBackend::new(window, Some(brick_texture)).with_background(background_texture);
+        let mut software = match options.backend {
+            Backend::Software => {
+                let software_backend = SoftwareBackend::new(RESOLUTION[0], RESOLUTION[1], Some(Image::load(&options.texture).expect("Failed to load texture.")))
+                        .with_parallelism(options.parallelism.clone());
+                let frame_texture = texture_creator.create_texture_streaming(PixelFormatEnum::RGBA32, RESOLUTION[0], RESOLUTION[1])
+                        .expect("Failed to create frame texture.");
+                Some((software_backend, frame_texture))
+            },
+            _ => None
+        };
+        let mut renderer = Renderer::new(options.parallelism).with_guard_band(options.guard_band);
+        let mut unsupported: Vec<&str> = Vec::new(); // what the scene asked for last frame that the backend can't draw
+
+        let ttf_context = sdl2::ttf::init().expect("Failed to initialize SDL_ttf.");
+        let hud = Hud::new(&ttf_context, HUD_FONT, 14).expect("Failed to load HUD font.");
+
+        sdl_context.mouse().show_cursor(!app.scene.mouse_locked);
+
+        while running {
+            let frame_time = timestep.begin_frame();
+
+            // events pile up in `input` until a simulation step uses them, so nothing is lost on frames without a step
+            for event in events.poll_iter() {
+                if let Event::Quit { .. } = event {
+                    running = false;
+                }
+                if console.handle_event(&event, &input, &mut app) {
+                    continue;
+                }
+                // while replaying, the devices are only listened to for quitting
+                if replay.is_none() {
+                    input.handle_event(&event);
+                }
+            }
+            for message in input.drain_messages() {
+                app.scene.log(message);
+            }
+            if console.open {
+                // nothing is held down as far as the scene knows while typing
+                input.apply_held(HeldState::default());
+            } else if replay.is_none() {
+                input.update(&events);
+            }
+
+            while timestep.step() {
+                if let Some(frames) = replay.as_mut() {
+                    match frames.next() {
+                        Some(frame) => { frame.apply(&mut input); },
+                        None => { running = false; break; }
+                    }
+                }
+
+                if let Some(recorder) = recorder.as_mut() {
+                    recorder.record(&FrameInput::capture(timestep.step, &input)).expect("Failed to write input recording.");
+                }
+
+                if input.was_pressed("toggle_stats") {
+                    app.show_stats = !app.show_stats;
+                }
+                if input.was_pressed("toggle_hud") {
+                    app.show_hud = !app.show_hud;
+                }
+                if input.was_pressed("toggle_console") {
+                    console.open = true;
+                }
+
+                let was_locked = app.scene.mouse_locked;
+                app.scene.update(&input, timestep.step);
+                if app.scene.mouse_locked != was_locked {
+                    sdl_context.mouse().show_cursor(!app.scene.mouse_locked);
+                }
+
+                input.begin_frame();
+            }
+
+            // SDL only sends text input events while it's started, and some platforms show an on-screen keyboard for it
+            if console.open != video_subsystem.text_input().is_active() {
+                if console.open {
+                    video_subsystem.text_input().start();
+                } else {
+                    video_subsystem.text_input().stop();
+                }
+            }
+
+            // updating
+            if app.scene.mouse_locked {
+                sdl_context.mouse().warp_mouse_in_window(backend.canvas.window(), RESOLUTION[0] as i32 / 2, RESOLUTION[1] as i32 / 2);
+            }
+
+            // the canvas leaves out what only the software rasterizer can do, which is worth saying whenever it changes
+            let effects = if software.is_some() { Vec::new() } else { app.scene.pixel_effects() };
+            if effects != unsupported {
+                if !effects.is_empty() {
+                    app.scene.log(needs_software(&effects));
+                }
+                unsupported = effects;
+            }
+
+            // draw everything
+            let mut render_stats = RenderStats::new();
+            let camera = Camera::interpolate(&app.scene.previous_camera, &app.scene.camera, timestep.alpha());
+            match software.as_mut() {
+                Some((software_backend, frame_texture)) => {
+                    renderer.draw(software_backend, &app.scene, &camera, &mut render_stats);
+                    // getting the frame onto the screen counts as presenting it
+                    render_stats.time(Stage::Present, || {
+                        frame_texture.with_lock(None, |bytes, pitch| software_backend.framebuffer.write_rgba(bytes, pitch)).ok();
+                        backend.canvas.copy(frame_texture, None, None).ok();
+                    });
+                },
+                None => { renderer.draw(&mut backend, &app.scene, &camera, &mut render_stats); }
+            }
+
+            if app.show_hud {
+                let antialiasing = if software.is_some() { app.scene.antialiasing } else { Antialiasing::Off };
+                draw_hud(&hud, &mut backend.canvas, &texture_creator, &app.scene, &camera, antialiasing);
+            }
+            if app.show_stats {
+                PerformanceOverlay::draw(&hud, &mut backend.canvas, &texture_creator, &app.frame_stats);
+            }
+            if console.open {
+                console.draw(&hud, &mut backend.canvas, &texture_creator);
+            }
+
+            render_stats.time(Stage::Present, || backend.present());
+            app.frame_stats.push(frame_time, render_stats);
+        }
+
+        if let Some(recorder) = recorder {
+            recorder.finish().expect("Failed to write input recording.");
+        }
+    }
+
+
+    // Renders a camera path into numbered BMP images, without opening a window.
+    fn render_path_headless(self, backend: &mut dyn RenderBackend, path_file: &str, out_dir: &str, fps: u32) {
+        let path = CameraPath::load(path_file).expect("Failed to load camera path.");
+        std::fs::create_dir_all(out_dir).expect("Failed to create output directory.");
+
+        let scene = self.app.scene;
+        warn_unsupported(backend, &scene);
+        let mut camera = scene.camera;
+        let mut renderer = Renderer::new(self.options.parallelism).with_guard_band(self.options.guard_band);
+
+        let frame_count = (path.duration() * fps as f32).ceil() as u32 + 1;
+        for frame in 0..frame_count {
+            if let Some(keyframe) = path.sample(frame as f32 / fps as f32) {
+                keyframe.apply(&mut camera);
+            }
+
+            renderer.draw(backend, &scene, &camera, &mut RenderStats::new());
+            backend.present();
+            save_frame(backend, out_dir, frame);
+        }
+    }
+
+
+    // Feeds an input recording through the scene and saves every frame as a BMP image.
+    // The same recording always produces the same images, so they can be compared between versions.
+    fn replay_headless(self, backend: &mut dyn RenderBackend, recording_file: &str, out_dir: &str) {
+        let replay = InputReplay::load(recording_file).expect("Failed to load input recording.");
+        std::fs::create_dir_all(out_dir).expect("Failed to create output directory.");
+
+        let mut input = load_input();
+        let mut scene = self.app.scene;
+        warn_unsupported(backend, &scene);
+        let mut renderer = Renderer::new(self.options.parallelism).with_guard_band(self.options.guard_band);
+
+        for (frame_number, frame) in replay.enumerate() {
+            frame.apply(&mut input);
+            scene.update(&input, frame.dt);
+
+            renderer.draw(backend, &scene, &scene.camera, &mut RenderStats::new());
+            backend.present();
+            save_frame(backend, out_dir, frame_number as u32);
+        }
+    }
+}
+
+
+fn headless_canvas() -> SurfaceCanvas<'static> {
+    return Surface::new(RESOLUTION[0], RESOLUTION[1], PixelFormatEnum::RGBA32)
+            .expect("Failed to create surface.")
+            .into_canvas()
+            .expect("Failed to create software canvas.");
+}
+
+// `effects` from `Scene::pixel_effects` the backend leaves out
+fn needs_software(effects: &[&str]) -> String {
+    return format!("{} {} --backend software", effects.join(", "), if effects.len() == 1 { "needs" } else { "need" });
+}
+
+// headless frames have nobody watching the HUD
+fn warn_unsupported(backend: &dyn RenderBackend, scene: &Scene) {
+    let effects = scene.pixel_effects();
+    if !backend.shades_pixels() && !effects.is_empty() {
+        println!("{}", needs_software(&effects));
+    }
+}
+
+// backends without pixels don't save anything
+fn save_frame(backend: &mut dyn RenderBackend, out_dir: &str, frame: u32) {
+    if let Some(image) = backend.read_pixels() {
+        image.save_bmp(&format!("{}/frame_{:05}.bmp", out_dir, frame)).expect("Failed to save frame.");
+    }
+}
+
+
+pub fn load_input() -> Input {
+    return Input::load(input::BINDINGS_FILE).unwrap_or_else(|e| {
+        println!("Using default key bindings ({})", e);
+        input::DEFAULT_BINDINGS.parse::<Input>().expect("Default key bindings are invalid.")
+    });
+}
+
+
+// camera and render state in the top right corner, with the antialiasing the backend applies, the latest message along the bottom
+fn draw_hud<T: RenderTarget, C>(hud: &Hud, canvas: &mut Canvas<T>, texture_creator: &TextureCreator<C>, scene: &Scene, camera: &Camera, antialiasing: Antialiasing) {
+    let projection = match camera.projection {
+        Projection::Perspective => "perspective",
+        Projection::Orthographic => "orthographic"
+    };
+    let lines = [
+        format!("position {}", camera.pos),
+        format!("yaw {:.0} pitch {:.0}", camera.yaw.to_degrees(), camera.pitch.to_degrees()),
+        format!("fov {:.0} {}", camera.fov, projection),
+        format!("mode {} antialiasing {}", scene.render_mode, antialiasing),
+        format!("controller {}", scene.controller.name()),
+        format!("selected mesh {}", scene.selected_mesh)
+    ];
+    let width = canvas.output_size().map_or(RESOLUTION[0], |(width, _)| width) as i32;
+    hud.text_panel(canvas, texture_creator, &lines, width - PADDING, PADDING, Align::Right);
+
+    if scene.message_time > 0.0 {
+        hud.console_line(canvas, texture_creator, &scene.message, Color::WHITE);
+    }
+}
+
@@ -0,0 +1,147 @@
+use sdl2::pixels::Color;
+
+use crate::math::Vec3;
+use crate::shader::{mix, to_linear};
+
+
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+pub enum FogMode {
+    Off,
+    Linear,
+    Exponential,
+    ExponentialSquared
+}
+impl std::str::FromStr for FogMode {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        return match s {
+            "off" => Ok(FogMode::Off),
+            "linear" => Ok(FogMode::Linear),
+            "exp" => Ok(FogMode::Exponential),
+            "exp2" => Ok(FogMode::ExponentialSquared),
+            _ => Err(format!("unknown fog mode '{}'", s))
+        };
+    }
+}
+impl std::fmt::Display for FogMode {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        return f.pad(match self {
+            FogMode::Off => "off",
+            FogMode::Linear => "linear",
+            FogMode::Exponential => "exp",
+            FogMode::ExponentialSquared => "exp2"
+        });
+    }
+}
+
+
+// Fades things into `color` the further they are from the camera, by their depth in view space. Nothing closer than
+// `start` is fogged. Linear fog thickens evenly until it covers everything from `end` on, the exponential ones
+// thicken by `density` per unit past `start` without ever quite getting there.
+#[derive(Copy, Clone)]
+pub struct Fog {
+    pub mode: FogMode,
+    pub color: Color,
+    pub start: f32,
+    pub end: f32, // linear only
+    pub density: f32 // exponential only
+}
+impl Fog {
+    pub fn new(mode: FogMode, color: Color, start: f32, end: f32, density: f32) -> Self {
+        return Self { mode, color, start, end, density };
+    }
+
+    pub fn off() -> Self {
+        return Self::new(FogMode::Off, Color::BLACK, 0.0, 20.0, 0.05);
+    }
+
+    // how much of a point `depth` away is fog, from 0 to 1
+    pub fn amount(&self, depth: f32) -> f32 {
+        let distance = (depth - self.start).max(0.0);
+        let amount = match self.mode {
+            FogMode::Off => 0.0,
+            FogMode::Linear => distance / (self.end - self.start).max(f32::EPSILON),
+            FogMode::Exponential => 1.0 - (-self.density * distance).exp(),
+            FogMode::ExponentialSquared => 1.0 - (-(self.density * distance).powi(2)).exp()
+        };
+        return amount.clamp(0.0, 1.0);
+    }
+
+    // `color` seen from `depth` away, keeping its alpha
+    pub fn apply(&self, color: Color, depth: f32) -> Color {
+        let amount = self.amount(depth);
+        if amount == 0.0 {
+            return color;
+        }
+        return mix(color, self.color, amount);
+    }
+
+    // `apply` for linear light
+    pub fn apply_light(&self, light: Vec3, depth: f32) -> Vec3 {
+        let amount = self.amount(depth);
+        if amount == 0.0 {
+            return light;
+        }
+        return light + (to_linear(self.color) - light) * amount;
+    }
+
+    // what's behind everything, so the far plane blends into it
+    pub fn clear_color(&self) -> Color {
+        return if self.mode == FogMode::Off { Color::BLACK } else { self.color };
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::backend::RenderBackend;
+    use crate::mesh::Mesh;
+    use crate::render::{Parallelism, RenderMode, Renderer};
+    use crate::scene::Scene;
+    use crate::software::SoftwareBackend;
+    use crate::stats::RenderStats;
+
+    #[test]
+    fn linear_fog_goes_from_start_to_end() {
+        let grey = Color::RGB(128, 128, 128);
+        let fog = Fog::new(FogMode::Linear, grey, 5.0, 15.0, 0.0);
+        let red = Color::RGB(255, 0, 0);
+        assert_eq!(fog.apply(red, 2.0), red);
+        assert_eq!(fog.apply(red, 5.0), red);
+        assert_eq!(fog.amount(10.0), 0.5);
+        assert_eq!(fog.apply(red, 15.0), grey);
+        assert_eq!(fog.apply(red, 50.0), grey);
+    }
+
+    #[test]
+    fn exponential_fog_never_quite_covers_everything() {
+        let fog = Fog::new(FogMode::Exponential, Color::WHITE, 5.0, 0.0, 0.1);
+        let squared = Fog { mode: FogMode::ExponentialSquared, ..fog };
+        assert_eq!(fog.amount(5.0), 0.0);
+        assert!(fog.amount(10.0) < fog.amount(20.0) && fog.amount(20.0) < 1.0);
+        // which starts slower and catches up
+        assert!(squared.amount(6.0) < fog.amount(6.0));
+        assert!(squared.amount(30.0) > fog.amount(30.0));
+    }
+
+    #[test]
+    fn things_past_the_end_disappear_into_the_fog() {
+        let mut scene = Scene::new();
+        let mut teapot = Mesh::load("assets/teapot.obj").expect("Failed to load the teapot");
+        teapot.translate(0.0, -1.5, 8.0);
+        scene.add_mesh(teapot);
+        scene.render_mode = RenderMode::Filled;
+        let grey = Color::RGB(128, 128, 128);
+
+        let mut draw = |fog: Fog| {
+            scene.fog = fog;
+            let mut backend = SoftwareBackend::new(80, 50, None);
+            Renderer::new(Parallelism::serial()).draw(&mut backend, &scene, &scene.camera, &mut RenderStats::new());
+            return backend.read_pixels().expect("Failed to read the frame");
+        };
+        assert!(draw(Fog::new(FogMode::Linear, grey, 20.0, 30.0, 0.0)).pixels.iter().any(|color| *color != grey));
+        assert!(draw(Fog::new(FogMode::Linear, grey, 1.0, 2.0, 0.0)).pixels.iter().all(|color| *color == grey));
+    }
+}
@@ -0,0 +1,112 @@
+use sdl2::pixels::Color;
+use sdl2::rect::{Point, Rect};
+use sdl2::render::{BlendMode, Canvas, RenderTarget, TextureCreator};
+use sdl2::ttf::{Font, Sdl2TtfContext};
+
+
+pub const FONT_DIRECTORY: &str = "assets";
+pub const PANEL_COLOR: Color = Color::RGBA(0, 0, 0, 170);
+pub const PADDING: i32 = 5;
+
+
+// Horizontal alignment of text relative to the x coordinate it's drawn at
+#[derive(Copy, Clone, Eq, PartialEq)]
+pub enum Align {
+    Left,
+    Center,
+    Right
+}
+
+
+// 2D text and panels drawn on top of the finished 3D frame
+pub struct Hud<'ttf> {
+    font: Font<'ttf, 'static>,
+    pub line_height: i32
+}
+impl<'ttf> Hud<'ttf> {
+    // `font_name` is looked up in `FONT_DIRECTORY`
+    pub fn new(ttf_context: &'ttf Sdl2TtfContext, font_name: &str, size: u16) -> Result<Self, String> {
+        let path = format!("{}/{}", FONT_DIRECTORY, font_name);
+        let font = ttf_context.load_font(&path, size).map_err(|e| format!("{}: {}", path, e))?;
+        let line_height = font.recommended_line_spacing();
+        return Ok(Self { font, line_height });
+    }
+
+    // width and height the text takes up on screen
+    pub fn measure(&self, text: &str) -> (u32, u32) {
+        if text.is_empty() {
+            return (0, self.line_height as u32);
+        }
+        return self.font.size_of(text).unwrap_or((0, 0));
+    }
+
+    // size of a block of lines, without padding
+    pub fn measure_lines<S: AsRef<str>>(&self, lines: &[S]) -> (u32, u32) {
+        let width = lines.iter().map(|line| self.measure(line.as_ref()).0).max().unwrap_or(0);
+        return (width, self.line_height as u32 * lines.len() as u32);
+    }
+
+    pub fn panel<T: RenderTarget>(&self, canvas: &mut Canvas<T>, rect: Rect, color: Color) {
+        canvas.set_blend_mode(BlendMode::Blend);
+        canvas.set_draw_color(color);
+        canvas.fill_rect(rect).ok();
+        canvas.set_blend_mode(BlendMode::None);
+    }
+
+    // `position` is the top of the text, on the side of `align`
+    pub fn text<T: RenderTarget, C>(&self, canvas: &mut Canvas<T>, texture_creator: &TextureCreator<C>, text: &str, position: Point, align: Align, color: Color) {
+        if text.is_empty() {
+            return;
+        }
+        let Ok(surface) = self.font.render(text).blended(color) else {
+            return;
+        };
+        let Ok(texture) = texture_creator.create_texture_from_surface(&surface) else {
+            return;
+        };
+
+        let x = match align {
+            Align::Left => position.x,
+            Align::Center => position.x - surface.width() as i32 / 2,
+            Align::Right => position.x - surface.width() as i32
+        };
+        canvas.copy(&texture, None, Rect::new(x, position.y, surface.width(), surface.height())).ok();
+    }
+
+    // returns the y coordinate below the last line
+    pub fn lines<T: RenderTarget, C, S: AsRef<str>>(&self, canvas: &mut Canvas<T>, texture_creator: &TextureCreator<C>, lines: &[S], position: Point, align: Align, color: Color) -> i32 {
+        let mut y = position.y;
+        for line in lines {
+            self.text(canvas, texture_creator, line.as_ref(), Point::new(position.x, y), align, color);
+            y += self.line_height;
+        }
+        return y;
+    }
+
+    // lines on a panel that fits them, `x` and `y` being the panel's corner on the side of `align`
+    pub fn text_panel<T: RenderTarget, C, S: AsRef<str>>(&self, canvas: &mut Canvas<T>, texture_creator: &TextureCreator<C>, lines: &[S], x: i32, y: i32, align: Align) -> Rect {
+        let (width, height) = self.measure_lines(lines);
+        let width = width + PADDING as u32 * 2;
+        let height = height + PADDING as u32 * 2;
+        let left = match align {
+            Align::Left => x,
+            Align::Center => x - width as i32 / 2,
+            Align::Right => x - width as i32
+        };
+
+        let rect = Rect::new(left, y, width, height);
+        self.panel(canvas, rect, PANEL_COLOR);
+        self.lines(canvas, texture_creator, lines, Point::new(left + PADDING, y + PADDING), Align::Left, Color::WHITE);
+        return rect;
+    }
+
+    // single line strip along the bottom of the screen, for messages and typed commands
+    pub fn console_line<T: RenderTarget, C>(&self, canvas: &mut Canvas<T>, texture_creator: &TextureCreator<C>, text: &str, color: Color) {
+        let (width, height) = canvas.output_size().unwrap_or((0, 0));
+        let strip_height = self.line_height + PADDING * 2;
+        let top = height as i32 - strip_height;
+
+        self.panel(canvas, Rect::new(0, top, width, strip_height as u32), PANEL_COLOR);
+        self.text(canvas, texture_creator, text, Point::new(PADDING * 2, top + PADDING), Align::Left, color);
+    }
+}
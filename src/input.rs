@@ -0,0 +1,534 @@
+use std::collections::{HashMap, HashSet};
+use sdl2::event::Event;
+use sdl2::keyboard::{Keycode, Mod, Scancode};
+use sdl2::mouse::MouseButton;
+use sdl2::controller::{Axis as PadAxis, Button as PadButton, GameController};
+use sdl2::{EventPump, GameControllerSubsystem};
+
+
+pub const BINDINGS_FILE: &str = "assets/bindings.cfg";
+
+// what `BINDINGS_FILE` holds as shipped, used when it's missing
+pub const DEFAULT_BINDINGS: &str = include_str!("../assets/bindings.cfg");
+
+const PAD_BUTTONS: [PadButton; 15] = [
+    PadButton::A, PadButton::B, PadButton::X, PadButton::Y,
+    PadButton::Back, PadButton::Guide, PadButton::Start,
+    PadButton::LeftStick, PadButton::RightStick, PadButton::LeftShoulder, PadButton::RightShoulder,
+    PadButton::DPadUp, PadButton::DPadDown, PadButton::DPadLeft, PadButton::DPadRight
+];
+
+// names of the settings that can appear in a bindings file next to the actions
+const SETTINGS: [&str; 3] = ["stick_dead_zone", "trigger_dead_zone", "response_curve"];
+
+
+// splits at every `separator` that isn't between double quotes, so `"+"` and `"#"` can be bound
+fn split_unquoted(text: &str, separator: char) -> Vec<&str> {
+    let mut parts = Vec::new();
+    let (mut start, mut quoted) = (0, false);
+    for (i, c) in text.char_indices() {
+        if c == '"' {
+            quoted = !quoted;
+        } else if c == separator && !quoted {
+            parts.push(&text[start..i]);
+            start = i + c.len_utf8();
+        }
+    }
+    parts.push(&text[start..]);
+    return parts;
+}
+
+
+#[derive(Copy, Clone, Default, Eq, PartialEq)]
+pub struct Modifiers {
+    pub ctrl: bool,
+    pub shift: bool,
+    pub alt: bool
+}
+impl Modifiers {
+    pub fn from_keymod(keymod: Mod) -> Self {
+        return Self {
+            ctrl: keymod.intersects(Mod::LCTRLMOD | Mod::RCTRLMOD),
+            shift: keymod.intersects(Mod::LSHIFTMOD | Mod::RSHIFTMOD),
+            alt: keymod.intersects(Mod::LALTMOD | Mod::RALTMOD)
+        };
+    }
+
+    // modifiers that aren't required are ignored, so `Left Shift` can descend while moving forward with `W`
+    pub fn satisfied_by(&self, held: Modifiers) -> bool {
+        return (!self.ctrl || held.ctrl) && (!self.shift || held.shift) && (!self.alt || held.alt);
+    }
+}
+
+
+#[derive(Copy, Clone, Eq, PartialEq, Hash)]
+pub enum Axis {
+    MouseX,
+    MouseY,
+    Wheel
+}
+
+
+#[derive(Copy, Clone, PartialEq)]
+pub enum Source {
+    Key(Keycode),
+    Scancode(Scancode), // physical key position, independent of the keyboard layout
+    Mouse(MouseButton),
+    Axis(Axis),
+    PadButton(PadButton),
+    PadAxis(PadAxis)
+}
+
+
+#[derive(Copy, Clone, PartialEq)]
+pub struct Binding {
+    pub source: Source,
+    pub modifiers: Modifiers,
+    pub scale: f32 // multiplier when the action is read as an axis
+}
+impl Binding {
+    // `[Ctrl+][Shift+][Alt+]<source>[*scale]`, where source is an SDL key name, `Scan:<name>`, `Mouse:<button>`, `MouseX`, `MouseY`, `Wheel`,
+    // `Pad:<button>` or `PadAxis:<axis>` (buttons and axes use SDL's game controller names, like `a` or `leftx`).
+    // A key name in double quotes is always a key, for keys like `"+"`, `"*"`, `","` and `"#"` that would be read otherwise.
+    pub fn parse(text: &str) -> Result<Self, String> {
+        let mut parts = split_unquoted(text, '*');
+        let scale = if parts.len() > 1 { parts.pop() } else { None };
+        let (text, scale) = match scale {
+            Some(scale) => (text[..text.len() - scale.len() - 1].trim(), scale.trim().parse::<f32>().map_err(|e| format!("bad scale '{}': {}", scale, e))?),
+            None => (text.trim(), 1.0)
+        };
+
+        let mut modifiers = Modifiers::default();
+        let mut parts: Vec<&str> = split_unquoted(text, '+').into_iter().map(|part| part.trim()).collect();
+        let name = parts.pop().unwrap_or("");
+
+        for part in parts {
+            match part.to_lowercase().as_str() {
+                "ctrl" => { modifiers.ctrl = true; },
+                "shift" => { modifiers.shift = true; },
+                "alt" => { modifiers.alt = true; },
+                _ => { return Err(format!("unknown modifier '{}'", part)); }
+            }
+        }
+
+        let lower = name.to_lowercase();
+        let quoted = name.strip_prefix('"').and_then(|name| name.strip_suffix('"'));
+        let source = if let Some(key) = quoted {
+            Source::Key(Keycode::from_name(key).ok_or(format!("unknown key '{}'", key))?)
+        } else if lower == "mousex" {
+            Source::Axis(Axis::MouseX)
+        } else if lower == "mousey" {
+            Source::Axis(Axis::MouseY)
+        } else if lower == "wheel" {
+            Source::Axis(Axis::Wheel)
+        } else if let Some(button) = lower.strip_prefix("mouse:") {
+            Source::Mouse(match button {
+                "left" => MouseButton::Left,
+                "middle" => MouseButton::Middle,
+                "right" => MouseButton::Right,
+                "x1" => MouseButton::X1,
+                "x2" => MouseButton::X2,
+                _ => { return Err(format!("unknown mouse button '{}'", button)); }
+            })
+        } else if let Some(button) = lower.strip_prefix("pad:") {
+            Source::PadButton(PadButton::from_string(button).ok_or(format!("unknown controller button '{}'", button))?)
+        } else if let Some(axis) = lower.strip_prefix("padaxis:") {
+            Source::PadAxis(PadAxis::from_string(axis).ok_or(format!("unknown controller axis '{}'", axis))?)
+        } else if lower.starts_with("scan:") {
+            Source::Scancode(Scancode::from_name(&name[5..]).ok_or(format!("unknown scancode '{}'", &name[5..]))?)
+        } else {
+            Source::Key(Keycode::from_name(name).ok_or(format!("unknown key '{}'", name))?)
+        };
+
+        return Ok(Self { source, modifiers, scale });
+    }
+}
+
+
+// Remaps a raw axis value so that everything inside `dead_zone` is 0 and the rest covers 0..1 again,
+// then bends it with `curve` (1 is linear, higher gives finer control near the center).
+pub fn apply_response(value: f32, dead_zone: f32, curve: f32) -> f32 {
+    let magnitude = value.abs();
+    if magnitude <= dead_zone {
+        return 0.0;
+    }
+    let scaled = ((magnitude - dead_zone) / (1.0 - dead_zone)).min(1.0);
+    return scaled.powf(curve) * value.signum();
+}
+
+
+// with several controllers connected, the one pushed furthest wins
+fn merge_pad_axis(pad_axes: &mut HashMap<PadAxis, f32>, axis: PadAxis, value: f32) {
+    let current = pad_axes.entry(axis).or_insert(0.0);
+    if value.abs() > current.abs() {
+        *current = value;
+    }
+}
+
+
+// The input events that actions react to, stripped of everything SDL-specific so they can be recorded and replayed.
+#[derive(Copy, Clone, PartialEq)]
+pub enum InputEvent {
+    KeyDown { keycode: Option<Keycode>, scancode: Option<Scancode>, modifiers: Modifiers },
+    MouseButtonDown(MouseButton),
+    MouseMotion(i32, i32),
+    MouseWheel(i32),
+    PadButtonDown(PadButton)
+}
+impl InputEvent {
+    pub fn from_sdl(event: &Event) -> Option<Self> {
+        return match event {
+            Event::KeyDown { keycode, scancode, keymod, repeat: false, .. } => {
+                Some(InputEvent::KeyDown { keycode: *keycode, scancode: *scancode, modifiers: Modifiers::from_keymod(*keymod) })
+            },
+            Event::MouseButtonDown { mouse_btn, .. } => Some(InputEvent::MouseButtonDown(*mouse_btn)),
+            Event::MouseMotion { xrel, yrel, .. } => Some(InputEvent::MouseMotion(*xrel, *yrel)),
+            Event::MouseWheel { y, .. } => Some(InputEvent::MouseWheel(*y)),
+            Event::ControllerButtonDown { button, .. } => Some(InputEvent::PadButtonDown(*button)),
+            _ => None
+        };
+    }
+}
+
+
+// whether `event` triggers `binding`, events other than key presses don't carry their modifiers so `held` is used for them
+fn triggered_by(binding: &Binding, event: &InputEvent, held: Modifiers) -> bool {
+    let (matches, modifiers) = match *event {
+        InputEvent::KeyDown { keycode, scancode, modifiers } => (match binding.source {
+            Source::Key(key) => Some(key) == keycode,
+            Source::Scancode(code) => Some(code) == scancode,
+            _ => false
+        }, modifiers),
+        InputEvent::MouseButtonDown(button) => (binding.source == Source::Mouse(button), held),
+        InputEvent::PadButtonDown(button) => (binding.source == Source::PadButton(button), held),
+        InputEvent::MouseMotion(..) | InputEvent::MouseWheel(_) => (false, held)
+    };
+    return matches && binding.modifiers.satisfied_by(modifiers);
+}
+
+
+// Everything that is held down at the end of a frame. Like `InputEvent`, it can be recorded and replayed.
+#[derive(Clone, Default, PartialEq)]
+pub struct HeldState {
+    pub scancodes: HashSet<Scancode>,
+    pub mouse_buttons: HashSet<MouseButton>,
+    pub pad_buttons: HashSet<PadButton>,
+    pub pad_axes: HashMap<PadAxis, f32>, // after dead zone and response curve
+    pub modifiers: Modifiers
+}
+
+
+// Maps named actions to keys, mouse buttons, axes and game controllers, and tracks their state frame to frame.
+// Call `begin_frame`, then `handle_event` for every event, then `update` once the events are drained.
+// A replay calls `apply_event` and `apply_held` instead of the last two.
+pub struct Input {
+    bindings: HashMap<String, Vec<Binding>>,
+    held: HeldState,
+    keys_held: HashSet<Keycode>, // keycodes of `held.scancodes` in the current layout
+    pressed: HashSet<String>,
+    axis_deltas: HashMap<Axis, f32>,
+    frame_events: Vec<InputEvent>,
+    messages: Vec<String>, // controllers connecting and disconnecting, for the HUD
+
+    controller_subsystem: Option<GameControllerSubsystem>,
+    controllers: HashMap<u32, GameController>, // by joystick instance id
+    pub stick_dead_zone: f32,
+    pub trigger_dead_zone: f32,
+    pub response_curve: f32
+}
+impl Input {
+    pub fn new() -> Self {
+        return Self {
+            bindings: HashMap::new(),
+            held: HeldState::default(),
+            keys_held: HashSet::new(),
+            pressed: HashSet::new(),
+            axis_deltas: HashMap::new(),
+            frame_events: Vec::new(),
+            messages: Vec::new(),
+
+            controller_subsystem: None,
+            controllers: HashMap::new(),
+            stick_dead_zone: 0.2,
+            trigger_dead_zone: 0.1,
+            response_curve: 2.0
+        };
+    }
+
+    // Controllers are opened from `ControllerDeviceAdded` events, which SDL also sends for the ones plugged in at startup.
+    pub fn enable_controllers(&mut self, subsystem: GameControllerSubsystem) {
+        self.controller_subsystem = Some(subsystem);
+    }
+
+    pub fn controller_count(&self) -> usize {
+        return self.controllers.len();
+    }
+
+    pub fn load(filename: &str) -> Result<Self, String> {
+        let content = std::fs::read_to_string(filename).map_err(|e| format!("{}: {}", filename, e))?;
+        return content.parse::<Self>();
+    }
+
+    pub fn bind(&mut self, action: &str, binding: Binding) {
+        self.bindings.entry(action.to_string()).or_default().push(binding);
+    }
+
+    pub fn unbind(&mut self, action: &str) {
+        self.bindings.remove(action);
+    }
+
+    pub fn begin_frame(&mut self) {
+        self.pressed.clear();
+        self.axis_deltas.clear();
+        self.frame_events.clear();
+    }
+
+    pub fn handle_event(&mut self, event: &Event) {
+        if let Some(input_event) = InputEvent::from_sdl(event) {
+            self.apply_event(input_event);
+            return;
+        }
+
+        match event {
+            Event::ControllerDeviceAdded { which, .. } => {
+                if let Some(subsystem) = &self.controller_subsystem {
+                    match subsystem.open(*which) {
+                        Ok(controller) => {
+                            self.messages.push(format!("Controller connected: {}", controller.name()));
+                            self.controllers.insert(controller.instance_id(), controller);
+                        },
+                        Err(e) => { self.messages.push(format!("Failed to open controller {}: {}", which, e)); }
+                    }
+                }
+            }
+
+            Event::ControllerDeviceRemoved { which, .. } => {
+                if let Some(controller) = self.controllers.remove(which) {
+                    self.messages.push(format!("Controller disconnected: {}", controller.name()));
+                }
+            }
+
+            _ => {}
+        }
+    }
+
+    pub fn apply_event(&mut self, event: InputEvent) {
+        self.frame_events.push(event);
+
+        for (action, bindings) in &self.bindings {
+            if bindings.iter().any(|binding| triggered_by(binding, &event, self.held.modifiers)) {
+                self.pressed.insert(action.clone());
+            }
+        }
+
+        match event {
+            InputEvent::MouseMotion(xrel, yrel) => {
+                *self.axis_deltas.entry(Axis::MouseX).or_insert(0.0) += xrel as f32;
+                *self.axis_deltas.entry(Axis::MouseY).or_insert(0.0) += yrel as f32;
+            }
+
+            InputEvent::MouseWheel(y) => {
+                *self.axis_deltas.entry(Axis::Wheel).or_insert(0.0) += y as f32;
+            }
+
+            _ => {}
+        }
+    }
+
+    // whether `event` triggers `action`, without applying it
+    pub fn triggers(&self, action: &str, event: &InputEvent) -> bool {
+        return self.bindings.get(action).is_some_and(|bindings| {
+            bindings.iter().any(|binding| triggered_by(binding, event, self.held.modifiers))
+        });
+    }
+
+    // what's happened to the controllers since the last call, oldest first
+    pub fn drain_messages(&mut self) -> std::vec::Drain<'_, String> {
+        return self.messages.drain(..);
+    }
+
+    // events applied since `begin_frame`
+    pub fn frame_events(&self) -> &[InputEvent] {
+        return &self.frame_events;
+    }
+
+    pub fn held_state(&self) -> &HeldState {
+        return &self.held;
+    }
+
+    // refreshes which keys and buttons are held down
+    pub fn update(&mut self, events: &EventPump) {
+        let keyboard = events.keyboard_state();
+        let pressed = |a: Scancode, b: Scancode| keyboard.is_scancode_pressed(a) || keyboard.is_scancode_pressed(b);
+
+        let mut held = HeldState {
+            scancodes: keyboard.pressed_scancodes().collect(),
+            mouse_buttons: events.mouse_state().pressed_mouse_buttons().collect(),
+            pad_buttons: HashSet::new(),
+            pad_axes: HashMap::new(),
+            modifiers: Modifiers {
+                ctrl: pressed(Scancode::LCtrl, Scancode::RCtrl),
+                shift: pressed(Scancode::LShift, Scancode::RShift),
+                alt: pressed(Scancode::LAlt, Scancode::RAlt)
+            }
+        };
+        self.read_controllers(&mut held);
+
+        self.apply_held(held);
+    }
+
+    pub fn apply_held(&mut self, held: HeldState) {
+        self.keys_held = held.scancodes.iter().filter_map(|s| Keycode::from_scancode(*s)).collect();
+        self.held = held;
+    }
+
+    fn read_controllers(&self, held: &mut HeldState) {
+        for controller in self.controllers.values() {
+            for button in PAD_BUTTONS {
+                if controller.button(button) {
+                    held.pad_buttons.insert(button);
+                }
+            }
+
+            // sticks use a radial dead zone, so diagonals aren't cut off like they would be per axis
+            for (x_axis, y_axis) in [(PadAxis::LeftX, PadAxis::LeftY), (PadAxis::RightX, PadAxis::RightY)] {
+                let x = controller.axis(x_axis) as f32 / i16::MAX as f32;
+                let y = controller.axis(y_axis) as f32 / i16::MAX as f32;
+                let length = (x * x + y * y).sqrt();
+                if length > 0.0 {
+                    let scale = apply_response(length.min(1.0), self.stick_dead_zone, self.response_curve) / length;
+                    merge_pad_axis(&mut held.pad_axes, x_axis, x * scale);
+                    merge_pad_axis(&mut held.pad_axes, y_axis, y * scale);
+                }
+            }
+
+            for trigger in [PadAxis::TriggerLeft, PadAxis::TriggerRight] {
+                let value = controller.axis(trigger) as f32 / i16::MAX as f32;
+                merge_pad_axis(&mut held.pad_axes, trigger, apply_response(value, self.trigger_dead_zone, self.response_curve));
+            }
+        }
+    }
+
+    // axes count as held when pushed more than halfway in the direction the sign of the binding's scale points
+    fn source_held(&self, binding: &Binding) -> bool {
+        return match &binding.source {
+            Source::Key(key) => self.keys_held.contains(key),
+            Source::Scancode(code) => self.held.scancodes.contains(code),
+            Source::Mouse(button) => self.held.mouse_buttons.contains(button),
+            Source::PadButton(button) => self.held.pad_buttons.contains(button),
+            Source::PadAxis(axis) => self.held.pad_axes.get(axis).is_some_and(|v| *v * binding.scale.signum() > 0.5),
+            Source::Axis(_) => false
+        };
+    }
+
+    pub fn is_held(&self, action: &str) -> bool {
+        return self.bindings.get(action).is_some_and(|bindings| {
+            bindings.iter().any(|b| self.source_held(b) && b.modifiers.satisfied_by(self.held.modifiers))
+        });
+    }
+
+    // true only on the frame the action was triggered
+    pub fn was_pressed(&self, action: &str) -> bool {
+        return self.pressed.contains(action);
+    }
+
+    // sum of this frame's axis movement and held buttons, each multiplied by its binding's scale
+    pub fn axis(&self, action: &str) -> f32 {
+        let Some(bindings) = self.bindings.get(action) else { return 0.0; };
+
+        let mut value = 0.0;
+        for binding in bindings {
+            if !binding.modifiers.satisfied_by(self.held.modifiers) {
+                continue;
+            }
+            value += match binding.source {
+                Source::Axis(axis) => self.axis_deltas.get(&axis).copied().unwrap_or(0.0) * binding.scale,
+                Source::PadAxis(axis) => self.held.pad_axes.get(&axis).copied().unwrap_or(0.0) * binding.scale,
+                _ => if self.source_held(binding) { binding.scale } else { 0.0 }
+            };
+        }
+        return value;
+    }
+}
+impl std::str::FromStr for Input {
+    type Err = String;
+
+    // one `action = binding, binding, ...` per line, `#` starts a comment
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let mut input = Self::new();
+
+        for (n, line) in s.lines().enumerate() {
+            let line = split_unquoted(line, '#')[0].trim();
+            if line.is_empty() {
+                continue;
+            }
+
+            let (action, bindings) = line.split_once('=').ok_or(format!("line {}: expected 'action = binding'", n + 1))?;
+            let action = action.trim();
+
+            if SETTINGS.contains(&action) {
+                let value = bindings.trim().parse::<f32>().map_err(|e| format!("line {}: {}", n + 1, e))?;
+                match action {
+                    "stick_dead_zone" => { input.stick_dead_zone = value.clamp(0.0, 0.99); },
+                    "trigger_dead_zone" => { input.trigger_dead_zone = value.clamp(0.0, 0.99); },
+                    _ => { input.response_curve = value.max(0.1); }
+                }
+                continue;
+            }
+
+            for binding in split_unquoted(bindings, ',') {
+                let binding = Binding::parse(binding).map_err(|e| format!("line {}: {}", n + 1, e))?;
+                input.bind(action, binding);
+            }
+        }
+
+        return Ok(input);
+    }
+}
+impl Default for Input {
+    fn default() -> Self {
+        return Self::new();
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn quoted_separators_stay_in_their_part() {
+        assert_eq!(split_unquoted("Ctrl+\"+\"", '+'), ["Ctrl", "\"+\""]);
+        assert_eq!(split_unquoted("zoom = \"#\" # comment", '#'), ["zoom = \"#\" ", " comment"]);
+        assert_eq!(split_unquoted("A, \",\", B", ','), ["A", " \",\"", " B"]);
+    }
+
+    #[test]
+    fn negative_axis_bindings_are_held_the_negative_way() {
+        let mut input = Input::new();
+        input.bind("forward", Binding { source: Source::PadAxis(PadAxis::LeftY), modifiers: Modifiers::default(), scale: -1.0 });
+        input.bind("back", Binding { source: Source::PadAxis(PadAxis::LeftY), modifiers: Modifiers::default(), scale: 1.0 });
+
+        let mut held = HeldState::default();
+        held.pad_axes.insert(PadAxis::LeftY, -0.8);
+        input.apply_held(held.clone());
+        assert!(input.is_held("forward"));
+        assert!(!input.is_held("back"));
+
+        held.pad_axes.insert(PadAxis::LeftY, 0.8);
+        input.apply_held(held);
+        assert!(!input.is_held("forward"));
+        assert!(input.is_held("back"));
+    }
+
+    #[test]
+    fn rebound_keys_trigger_without_being_applied() {
+        let mut input = Input::new();
+        input.bind("toggle_console", Binding { source: Source::Key(Keycode::F12), modifiers: Modifiers::default(), scale: 1.0 });
+
+        let press = |keycode| InputEvent::KeyDown { keycode: Some(keycode), scancode: None, modifiers: Modifiers::default() };
+        assert!(input.triggers("toggle_console", &press(Keycode::F12)));
+        assert!(!input.triggers("toggle_console", &press(Keycode::Backquote)));
+        assert!(!input.was_pressed("toggle_console"));
+    }
+}
@@ -0,0 +1,37 @@
+// A small software 3D engine on top of SDL2. `Engine` ties everything together, the modules below can also be used on their own.
+
+pub mod math;
+pub mod camera;
+pub mod vertex;
+pub mod mesh;
+pub mod clip;
+pub mod shader;
+pub mod material;
+pub mod pbr;
+pub mod fog;
+pub mod skybox;
+pub mod pool;
+pub mod render;
+pub mod backend;
+pub mod software;
+pub mod post;
+pub mod ssao;
+pub mod clock;
+pub mod input;
+pub mod scene;
+pub mod engine;
+
+pub mod controller;
+pub mod camera_path;
+pub mod replay;
+pub mod stats;
+pub mod hud;
+pub mod overlay;
+pub mod console;
+pub mod commands;
+
+pub use camera::Camera;
+pub use engine::{Engine, Options};
+pub use math::{Vec2, Vec3};
+pub use mesh::Mesh;
+pub use scene::Scene;
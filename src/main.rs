@@ -1,1005 +1,23 @@
-use sdl2;
-use sdl2::event::Event;
-use sdl2::pixels::Color;
-use sdl2::render::{Canvas, RenderTarget};
-use sdl2::keyboard::Keycode;
-use sdl2::gfx::primitives::DrawRenderer;
-use sdl2::image::LoadTexture;
-use sdl2::render::Texture;
-// use sdl2::pixels::PixelFormatEnum;
-
-
-const WORLD_UP: Vec3 = Vec3 { x: 0.0, y: 1.0, z: 0.0 };
-const RESOLUTION: [u32; 2] = [1200, 700];
-
-#[derive(Eq, PartialEq)]
-enum RenderMode {
-    Outline,
-    Filled,
-    Textured
-}
+use third_dimension::{Engine, Mesh, Options};
 
 
+// Opens the .obj files given on the command line, or the teapot without any. See `Options::from_args` for the options.
 fn main() {
-    let sdl_context = sdl2::init().expect("Filed to initialize SDL context.");
-    let video_subsystem = sdl_context.video().expect("Failed to initialize SDL video subsystem.");
-
-
-    let mut window = video_subsystem.window("3d simulation", RESOLUTION[0], RESOLUTION[1])
-            .position_centered()
-            .build()
-            .expect("Failed to build window.")
-            .into_canvas()
-            .build()
-            .expect("Failed to convert window surface.");
-
-    let texture_creator = window.texture_creator();
-    
-    let mut events = sdl_context.event_pump().expect("Failed to build event pump.");
-
-    let mut clock = clock::Clock::new(60);
-
-    let mut running = true;
-
-    let brick_texture = texture_creator.load_texture("assets/brick.png").unwrap();
-
-    let mut render_mode: RenderMode = RenderMode::Textured;
-
-    // OTHER STUFF
-    let mut meshes: Vec<Mesh> = vec![Mesh::cube(), Mesh::cube(), Mesh::load_obj("assets/teapot.obj")];
-
-    meshes[1].translate(0.0, 2.0, 0.0);
-    meshes[2].translate(5.0, 0.0, 0.0);
-
-    let mut camera: Camera = Camera::new(Vec3::new(0.0, 0.0, 0.0), Vec3::new(0.0, 0.0, 1.0), 0.0, 0.0, 0.0);
-
-    sdl_context.mouse().show_cursor(false);
-
-    let mut mouse_locked = true;
-    let sensitivity: f32 = 1.0;
-
-    // let mut depth_buffer = Surface::new(RESOLUTION[0], RESOLUTION[1], PixelFormatEnum::RGBA32)
-    //         .unwrap()
-    //         .into_canvas()
-    //         .unwrap();
-
-    while running {
-        let dt = clock.tick();
-
-        window.window_mut().set_title(format!("{}", dt.as_micros()).as_str()).ok();
-
-        for event in events.poll_iter() {
-             match event {
-                Event::Quit { .. } => { running = false; }
-
-                Event::MouseMotion { xrel, yrel, .. } => { 
-                    if mouse_locked {
-                        camera.yaw -= xrel as f32 / 100.0 * sensitivity; 
-                        camera.pitch -= yrel as f32 / 100.0 * sensitivity;
-                    }
-                }
-
-                Event::KeyDown { keycode, .. } => {
-                    match keycode.unwrap_or(Keycode::NUM_0) {
-                        Keycode::ESCAPE => { 
-                            mouse_locked = !mouse_locked; 
-                            sdl_context.mouse().show_cursor(!mouse_locked);
-                        },
-                        Keycode::M => {
-                            if render_mode == RenderMode::Filled {
-                                render_mode = RenderMode::Textured;
-                            }
-                            else if render_mode == RenderMode::Textured {
-                                render_mode = RenderMode::Outline;
-                            }
-                            else {
-                                render_mode = RenderMode::Filled;
-                            }
-                        }
-                        _ => {}
-                    }
-                }
-
-                _ => {}
-            }
-        }
-
-        // updating
-        if mouse_locked {
-            sdl_context.mouse().warp_mouse_in_window(&window.window(), RESOLUTION[0] as i32 / 2, RESOLUTION[1] as i32 / 2);
-        }
-
-        let speed = 2.0;
-        let keys_pressed = input::get_pressed_keys(&events);
-
-        if keys_pressed.contains(&Keycode::UP) {
-            camera.pos.y += speed * dt.as_secs_f32();
-        }
-        if keys_pressed.contains(&Keycode::DOWN) {
-            camera.pos.y -= speed * dt.as_secs_f32();
-        }
-        if keys_pressed.contains(&Keycode::LEFT) {
-            camera.pitch -= 1.0 * dt.as_secs_f32();
-        }
-        if keys_pressed.contains(&Keycode::RIGHT) {
-            camera.pitch += 1.0 * dt.as_secs_f32();
-        }
-        
-        if keys_pressed.contains(&Keycode::W) {
-            camera.pos += camera.get_front() * speed * dt.as_secs_f32();
-        }
-        if keys_pressed.contains(&Keycode::S) {
-            camera.pos -= camera.get_front() * speed * dt.as_secs_f32();
-        }
-        if keys_pressed.contains(&Keycode::A) {
-            camera.pos += camera.get_right() * speed * dt.as_secs_f32();
-        }
-        if keys_pressed.contains(&Keycode::D) {
-            camera.pos -= camera.get_right() * speed * dt.as_secs_f32();
-        }
-        if keys_pressed.contains(&Keycode::SPACE) {
-            camera.pos += WORLD_UP * speed * dt.as_secs_f32();
-        }
-        if keys_pressed.contains(&Keycode::LSHIFT) {
-            camera.pos -= WORLD_UP * speed * dt.as_secs_f32();
-        }
-
-
-        camera.pitch = camera.pitch.clamp(-89.9_f32.to_radians(), 89.9_f32.to_radians());
-        camera.yaw = camera.yaw % (2.0*std::f32::consts::PI);
-
-        // update camera
-        camera.look_at(camera.yaw, camera.pitch);
-
-        // draw everything
-        window.set_draw_color(Color::RGB(0, 0, 0));
-        window.clear();
-
-        let mut triangles_to_draw: Vec<Triangle> = Vec::new();
-
-        for mesh in &meshes {
-            triangles_to_draw.append(&mut mesh.render(&camera));
-        }
+    let args: Vec<String> = std::env::args().skip(1).collect();
+    let mut options = Options::from_args(&args).unwrap_or_else(|e| panic!("{}", e));
 
-        triangles_to_draw.sort();
-        triangles_to_draw.reverse();
-
-        for triangle in triangles_to_draw {
-            let mut triangle_list: Vec<Triangle> = Vec::new();
-            triangle_list.push(triangle);
-            let mut new_triangles = 1;
-
-            for p in 0..4 {
-                while new_triangles > 0 {
-                    let test = triangle_list.remove(0);
-                    new_triangles -= 1;
-
-                    let mut new_triangles: Vec<Triangle> = match p {
-                        0 => { test.clip_against_plane(Vec3::new(0.0,                        0.0,                        0.0), Vec3::new( 0.0,  1.0, 0.0)) },
-                        1 => { test.clip_against_plane(Vec3::new(0.0,                        RESOLUTION[1] as f32 - 1.0, 0.0), Vec3::new( 0.0, -1.0, 0.0)) },
-                        2 => { test.clip_against_plane(Vec3::new(0.0,                        0.0,                        0.0), Vec3::new( 1.0,  0.0, 0.0)) },
-                        3 => { test.clip_against_plane(Vec3::new(RESOLUTION[0] as f32 - 1.0, 0.0,                        0.0), Vec3::new(-1.0,  0.0, 0.0)) },
-                        _ => {panic!("HOW DID YOU MANAGE TO BREAK THE GAME")}
-                    };
-
-                    triangle_list.append(&mut new_triangles);
-                }
-                new_triangles = triangle_list.len();
-            }
-    
-            for triangle in triangle_list {
-                match render_mode {
-                    RenderMode::Outline => { triangle.draw_outline(&mut window, Color::WHITE); },
-                    RenderMode::Textured => { triangle.draw_textured(&mut window, &brick_texture); },
-                    RenderMode::Filled => { triangle.draw(&mut window); }
-                }
-            }
-        }
-
-        window.present();
+    if options.files.is_empty() {
+        options.files.push(String::from("assets/teapot.obj"));
     }
-}
-
-
-#[cfg(any(target_os = "macos"))]
-pub fn reverse_color(color: Color) -> Color {
-    return Color::RGBA(color.a, color.b, color.g, color.r);
-}
-
+    let files = options.files.clone();
 
-pub struct Camera {
-    pub pos: Vec3,
-    pub look_direction: Vec3,
-    pub yaw: f32,
-    pub pitch: f32,
-    pub roll: f32
-}
-impl Camera {
-    pub fn new(pos: Vec3, look_direction: Vec3, yaw: f32, pitch: f32, roll: f32) -> Self {
-        return Self {
-            pos,
-            look_direction,
-            yaw,
-            pitch,
-            roll
+    let mut engine = Engine::new(options);
+    for file in &files {
+        match Mesh::load(file) {
+            Ok(mesh) => { engine.add_mesh(mesh); },
+            Err(e) => { println!("Failed to load mesh: {}", e); }
         }
     }
 
-    pub fn look_at(&mut self, yaw: f32, pitch: f32) {
-        self.look_direction.x = pitch.cos() * yaw.sin();
-        self.look_direction.y = pitch.sin();
-        self.look_direction.z = pitch.cos() * yaw.cos();
-        self.look_direction.normalize();
-    }
-
-    pub fn get_up(&self) -> Vec3 {
-        let forward = self.get_forward();
-        let right = self.get_right();
-        return forward.cross(&right);
-    }
-
-    pub fn get_right(&self) -> Vec3 {
-        let forward = self.get_forward();
-        return WORLD_UP.cross(&forward).normalized();
-    }
-
-    pub fn get_forward(&self) -> Vec3 {
-        return self.look_direction;
-    }
-
-    // in contrast with `get_forward()`, this function shows where the "front" is. If you're a nerd, imagine it like a vector tied to the XZ plane
-    pub fn get_front(&self) -> Vec3 {
-        return Vec3::new(self.yaw.sin(), 0.0, self.yaw.cos());
-    }
-}
-
-
-
-#[derive(Copy, Clone)]
-pub struct Vec4 {
-    pub x: f32,
-    pub y: f32,
-    pub z: f32,
-    pub w: f32
-}
-impl Vec4 {
-    pub fn new(x: f32, y: f32, z: f32, w: f32) -> Self {
-        return Vec4 { x, y, z, w };
-    }
-
-    pub fn from_vec3(v: Vec3, w: f32) -> Self {
-        return Self::new(v.x, v.y, v.z, w);
-    }
-
-    pub fn xyz(&self) -> Vec3 {
-        return Vec3::new(self.x, self.y, self.z);
-    }
-}
-impl std::ops::Mul<Matrix4x4> for Vec4 {
-    type Output = Vec4;
-    fn mul(self, rhs: Matrix4x4) -> Self::Output {
-        return Vec4::new(
-            self.x * rhs.mat[0][0] + self.y * rhs.mat[1][0] + self.z * rhs.mat[2][0] + rhs.mat[3][0], 
-            self.x * rhs.mat[0][1] + self.y * rhs.mat[1][1] + self.z * rhs.mat[2][1] + rhs.mat[3][1], 
-            self.x * rhs.mat[0][2] + self.y * rhs.mat[1][2] + self.z * rhs.mat[2][2] + rhs.mat[3][2],
-            self.x * rhs.mat[0][3] + self.y * rhs.mat[1][3] + self.z * rhs.mat[2][3] + rhs.mat[3][3]
-        );
-    }
-}
-
-#[derive(Copy, Clone)]
-pub struct Vec3 {
-    pub x: f32,
-    pub y: f32,
-    pub z: f32
-}
-impl Vec3 {
-    pub fn new(x: f32, y: f32, z: f32) -> Self {
-        return Self { x, y, z };
-    }
-
-    pub fn normalize(&mut self) {
-        let length = (self.x * self.x + self.y*self.y + self.z*self.z).sqrt();
-        self.x /= length;
-        self.y /= length;
-        self.z /= length;
-    }
-
-    pub fn normalized(&self) -> Vec3 {
-        let length = (self.x * self.x + self.y*self.y + self.z*self.z).sqrt();
-        return Vec3::new(self.x / length, self.y / length, self.z / length);
-    }
-
-    pub fn dot(&self, other: &Vec3) -> f32 {
-        return self.x * other.x + self.y * other.y + self.z * other.z;
-    }
-
-    pub fn cross(&self, other: &Vec3) -> Vec3 {
-        return Vec3::new(
-            self.y * other.z - self.z * other.y,
-            self.z * other.x - self.x * other.z,
-            self.x * other.y - self.y * other.x
-        );
-    }
-
-    pub fn plane_intersect(plane_p: Vec3, mut plane_n: Vec3, line_start: Vec3, line_end: Vec3) -> (Self, f32) {
-        plane_n.normalize();
-        let plane_d = -(plane_n.dot(&plane_p));
-        let ad = line_start.dot(&plane_n);
-        let bd = line_end.dot(&plane_n);
-        let t = (-plane_d - ad) / (bd - ad);
-        let line_start_to_end = line_end - line_start;
-        let line_to_intersect = line_start_to_end * t;
-        return (line_start + line_to_intersect, t);
-    }
-}
-impl std::ops::Add for Vec3 {
-    type Output = Vec3;
-    fn add(self, rhs: Self) -> Self::Output {
-        return Self::new(self.x + rhs.x, self.y + rhs.y, self.z + rhs.z);
-    }
-}
-impl std::ops::AddAssign for Vec3 {
-    fn add_assign(&mut self, rhs: Self) {
-        self.x += rhs.x;
-        self.y += rhs.y;
-        self.z += rhs.z
-    }
-}
-impl std::ops::Sub for Vec3 {
-    type Output = Vec3;
-    fn sub(self, rhs: Self) -> Self::Output {
-        return Self::new(self.x - rhs.x, self.y - rhs.y, self.z - rhs.z);
-    }
-}
-impl std::ops::SubAssign for Vec3 {
-    fn sub_assign(&mut self, rhs: Self) {
-        self.x -= rhs.x;
-        self.y -= rhs.y;
-        self.z -= rhs.z;
-    }
-}
-impl std::ops::Mul for Vec3 {
-    type Output = Vec3;
-    fn mul(self, rhs: Self) -> Self::Output {
-        return Self::new(self.x * rhs.x, self.y * rhs.y, self.z * rhs.z);
-    }
-}
-impl std::ops::Mul<f32> for Vec3 {
-    type Output = Vec3;
-    fn mul(self, rhs: f32) -> Self::Output {
-        return Vec3::new(self.x * rhs, self.y * rhs, self.z * rhs);
-    }
-}
-impl std::ops::MulAssign for Vec3 {
-    fn mul_assign(&mut self, rhs: Self) {
-        self.x *= rhs.x;
-        self.y *= rhs.y;
-        self.z *= rhs.z;
-    }
-}
-impl std::ops::Div for Vec3 {
-    type Output = Vec3;
-    fn div(self, rhs: Self) -> Self::Output {
-        return Self::new(self.x / rhs.x, self.y / rhs.y, self.z / rhs.z);
-    }
-}
-impl std::fmt::Display for Vec3 {
-    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        return f.pad(format!("({}, {}, {})", self.x as i32, self.y as i32, self.z as i32).as_str());
-    }
-}
-
-
-#[derive(Copy, Clone)]
-pub struct Vec2 {
-    pub x: f32,
-    pub y: f32
-}
-impl Vec2 {
-    pub fn new(x: f32, y: f32) -> Self {
-        return Self { x, y };
-    }
-
-    pub fn lerp(&self, v2: Vec2, mut t: f32) -> Vec2 {
-        t = t.clamp(0.0, 1.0);
-        return (v2 - *self) * t + *self;
-    }
-}
-impl std::ops::Add for Vec2 {
-    type Output = Vec2;
-    fn add(self, rhs: Self) -> Self::Output {
-        return Vec2::new(self.x + rhs.x, self.y + rhs.y);
-    }
-}
-impl std::ops::Sub for Vec2 {
-    type Output = Vec2;
-    fn sub(self, rhs: Self) -> Self::Output {
-        return Vec2::new(self.x - rhs.x, self.y - rhs.y);
-    }
-}
-impl std::ops::Mul<f32> for Vec2 {
-    type Output = Vec2;
-    fn mul(self, rhs: f32) -> Self::Output {
-        return Vec2::new(self.x * rhs, self.y * rhs);
-    }
-}
-
-
-#[derive(Copy, Clone)]
-pub struct Triangle {
-    pub points: [Vec3; 3],
-    pub texture: [Vec2; 3],
-    color: Color
-}
-impl Triangle {
-    pub fn new(points: [Vec3; 3], texture: [Vec2; 3], color: Color) -> Self {
-        return Self {
-            points,
-            texture,
-            color
-        };
-    }
-
-    pub fn set_color(&mut self, color: Color) {
-        self.color = color;
-    }
-
-    pub fn get_color(&self) -> Color {
-        return self.color;
-    }
-
-    pub fn draw<T: RenderTarget>(&self, target: &mut Canvas<T>) {
-        target.filled_trigon(self.points[0].x as i16, self.points[0].y as i16, self.points[1].x as i16, self.points[1].y as i16, self.points[2].x as i16, self.points[2].y as i16, reverse_color(self.get_color())).ok();
-    }
-
-    pub fn draw_outline<T: RenderTarget>(&self, target: &mut Canvas<T>, color: Color) {
-        target.trigon(self.points[0].x as i16, self.points[0].y as i16, self.points[1].x as i16, self.points[1].y as i16, self.points[2].x as i16, self.points[2].y as i16, reverse_color(color)).ok();
-    }
-
-    pub fn draw_textured<T: RenderTarget>(&self, target: &mut Canvas<T>, texture: &Texture) {
-        use sdl2::render::Vertex;
-        use sdl2::render::VertexIndices;
-        use sdl2::rect::FPoint;
-
-        target.render_geometry(
-            &[
-                Vertex { position: FPoint::new(self.points[0].x, self.points[0].y), color: self.color, tex_coord: FPoint::new(self.texture[0].x, self.texture[0].y) },
-                Vertex { position: FPoint::new(self.points[1].x, self.points[1].y), color: self.color, tex_coord: FPoint::new(self.texture[1].x, self.texture[1].y) },
-                Vertex { position: FPoint::new(self.points[2].x, self.points[2].y), color: self.color, tex_coord: FPoint::new(self.texture[2].x, self.texture[2].y) },
-            ],
-            Some(texture),
-            VertexIndices::Sequential
-        ).expect("no textured triangle :(");
-    }
-    
-    pub fn midpoint(&self) -> f32 {
-        return (self.points[0].z + self.points[1].z + self.points[2].z) / 3.0;
-    }
-
-    pub fn clip_against_plane(&self, plane_p: Vec3, mut plane_n: Vec3) -> Vec<Triangle> {
-        let mut results: Vec<Triangle> = Vec::new();
-
-        plane_n.normalize();
-
-        fn dist(plane_p: Vec3, plane_n: Vec3, p: Vec3) -> f32 {
-            return plane_n.x * p.x + plane_n.y * p.y + plane_n.z * p.z - plane_n.dot(&plane_p);
-        }
-
-        let mut points_inside: Vec<Vec3> = Vec::new();
-        let mut points_outside: Vec<Vec3> = Vec::new();
-        let mut points_inside_count = 0;
-        let mut texture_points_inside: Vec<Vec2> = Vec::new();
-        let mut texture_points_outside: Vec<Vec2> = Vec::new();
-
-        let d0 = dist(plane_p, plane_n, self.points[0]);
-        let d1 = dist(plane_p, plane_n, self.points[1]);
-        let d2 = dist(plane_p, plane_n, self.points[2]);
-
-
-        if d0 >= 0.0 { points_inside_count += 1; points_inside.push(self.points[0]); texture_points_inside.push(self.texture[0]); }
-        else { points_outside.push(self.points[0]); texture_points_outside.push(self.texture[0]); }
-
-        if d1 >= 0.0 { points_inside_count += 1; points_inside.push(self.points[1]); texture_points_inside.push(self.texture[1]); }
-        else { points_outside.push(self.points[1]); texture_points_outside.push(self.texture[1]); }
-
-        if d2 >= 0.0 { points_inside_count += 1; points_inside.push(self.points[2]); texture_points_inside.push(self.texture[2]); }
-        else { points_outside.push(self.points[2]); texture_points_outside.push(self.texture[2]); }
-
-        if points_inside_count == 1 {
-            let (p2, t2) = Vec3::plane_intersect(plane_p, plane_n, points_inside[0], points_outside[0]);
-            let (p3, t3) = Vec3::plane_intersect(plane_p, plane_n, points_inside[0], points_outside[1]);
-            let new_triangle = Triangle::new(
-                [
-                    points_inside[0],
-                    p2,
-                    p3
-                ],
-                [
-                    texture_points_inside[0],
-                    texture_points_inside[0].lerp(texture_points_outside[0], t2),
-                    texture_points_inside[0].lerp(texture_points_outside[1], t3)
-                ],
-                self.color
-            );
-
-            results.push(new_triangle);
-        }
-
-        if points_inside_count == 2 {
-            let (p1, t1) = Vec3::plane_intersect(plane_p, plane_n, points_inside[0], points_outside[0]);
-            let (p2, t2) = Vec3::plane_intersect(plane_p, plane_n, points_inside[1], points_outside[0]);
-
-            let new_triangle_1 = Triangle::new(
-                [
-                    points_inside[0],
-                    points_inside[1],
-                    p1
-                ],
-                [
-                    texture_points_inside[0],
-                    texture_points_inside[1],
-                    texture_points_inside[0].lerp(texture_points_outside[0], t1)
-                ],
-                self.color
-            );
-
-            let new_triangle_2 = Triangle::new(
-                [
-                    points_inside[1],
-                    p2,
-                    p1
-                ],
-                [
-                    texture_points_inside[1],
-                    texture_points_inside[1].lerp(texture_points_outside[0], t2),
-                    texture_points_inside[0].lerp(texture_points_outside[0], t1)
-                ],
-                self.color
-            );
-
-            results.push(new_triangle_1);
-            results.push(new_triangle_2);
-        }
-
-        if points_inside_count == 3 {
-            results.push(*self);
-        }
-
-        return results;
-    }
-}
-impl PartialEq for Triangle {
-    fn eq(&self, other: &Self) -> bool {
-        return self.midpoint() == other.midpoint();
-    }
-}
-impl Eq for Triangle {}
-impl PartialOrd for Triangle {
-    fn ge(&self, other: &Self) -> bool {
-        return self.midpoint() >= other.midpoint();
-    }
-
-    fn gt(&self, other: &Self) -> bool {
-        return self.midpoint() > other.midpoint();
-    }
-
-    fn le(&self, other: &Self) -> bool {
-        return self.midpoint() <= other.midpoint();
-    }
-
-    fn lt(&self, other: &Self) -> bool {
-        return self.midpoint() < other.midpoint();
-    }
-
-    fn partial_cmp(&self, other: &Self) -> Option<std::cmp::Ordering> {
-        if self.midpoint() < other.midpoint() {
-            return Some(std::cmp::Ordering::Less);
-        }
-        else if self.midpoint() > other.midpoint() {
-            return Some(std::cmp::Ordering::Greater);
-        }
-        else {
-            return Some(std::cmp::Ordering::Equal);
-        }
-    }
-}
-impl Ord for Triangle {
-    fn cmp(&self, other: &Self) -> std::cmp::Ordering {
-        if self.midpoint() < other.midpoint() {
-            return std::cmp::Ordering::Less;
-        }
-        else if self.midpoint() > other.midpoint() {
-            return std::cmp::Ordering::Greater;
-        }
-        else {
-            return std::cmp::Ordering::Equal;
-        }
-    }
-}
-
-
-pub struct Mesh {
-    triangles: Vec<Triangle>
-}
-impl Mesh {
-    pub fn new(triangles: Vec<Triangle>) -> Self {
-        return Self { triangles };
-    }
-
-    pub fn from_str(str: String) -> Self {
-        let mut triangles: Vec<Triangle> = Vec::new();
-
-        let mut vertices: Vec<Vec3> = Vec::new();
-
-        let data = str.split('\n');
-
-        for line in data {
-            let i: Vec<&str> = line.split(' ').into_iter().collect();
-            if i[0] == "v" {
-                let v: Vec3 = Vec3::new(
-                    i[1].parse::<f32>().unwrap(), 
-                    i[2].parse::<f32>().unwrap(), 
-                    i[3].parse::<f32>().unwrap()
-                );
-
-                vertices.push(v);
-            }
-            else if i[0] == "f" {
-                let triangle = Triangle::new(
-                    [
-                        vertices[i[1].parse::<usize>().unwrap() - 1], 
-                        vertices[i[2].parse::<usize>().unwrap() - 1], 
-                        vertices[i[3].parse::<usize>().unwrap() - 1]
-                    ],
-                    [Vec2::new(0.0, 0.0), Vec2::new(0.0, 0.0), Vec2::new(0.0, 0.0)],
-                    Color::BLACK
-                );
-
-                triangles.push(triangle);
-            }
-        }
-
-        return Self { triangles };
-    }
-
-    pub fn load_obj(filename: &str) -> Self {
-        let content = std::fs::read_to_string(filename).expect(&format!("This file doesn't exist: {}", filename));
-
-        return Self::from_str(content);
-    }
-
-    pub fn cube() -> Self {
-        return Self {
-            triangles: vec![
-                Triangle::new([Vec3::new(0.0, 0.0, 0.0), Vec3::new(0.0, 1.0, 0.0), Vec3::new(1.0, 1.0, 0.0)], [Vec2::new(0.0, 1.0), Vec2::new(0.0, 0.0), Vec2::new(1.0, 0.0)], Color::BLACK),
-                Triangle::new([Vec3::new(0.0, 0.0, 0.0), Vec3::new(1.0, 1.0, 0.0), Vec3::new(1.0, 0.0, 0.0)], [Vec2::new(0.0, 1.0), Vec2::new(1.0, 0.0), Vec2::new(1.0, 1.0)], Color::BLACK),
-
-                Triangle::new([Vec3::new(1.0, 0.0, 0.0), Vec3::new(1.0, 1.0, 0.0), Vec3::new(1.0, 1.0, 1.0)], [Vec2::new(0.0, 1.0), Vec2::new(0.0, 0.0), Vec2::new(1.0, 0.0)], Color::BLACK),
-                Triangle::new([Vec3::new(1.0, 0.0, 0.0), Vec3::new(1.0, 1.0, 1.0), Vec3::new(1.0, 0.0, 1.0)], [Vec2::new(0.0, 1.0), Vec2::new(1.0, 0.0), Vec2::new(1.0, 1.0)], Color::BLACK),
-
-                Triangle::new([Vec3::new(1.0, 0.0, 1.0), Vec3::new(1.0, 1.0, 1.0), Vec3::new(0.0, 1.0, 1.0)], [Vec2::new(0.0, 1.0), Vec2::new(0.0, 0.0), Vec2::new(1.0, 0.0)], Color::BLACK),
-                Triangle::new([Vec3::new(1.0, 0.0, 1.0), Vec3::new(0.0, 1.0, 1.0), Vec3::new(0.0, 0.0, 1.0)], [Vec2::new(0.0, 1.0), Vec2::new(1.0, 0.0), Vec2::new(1.0, 1.0)], Color::BLACK),
-
-                Triangle::new([Vec3::new(0.0, 0.0, 1.0), Vec3::new(0.0, 1.0, 1.0), Vec3::new(0.0, 1.0, 0.0)], [Vec2::new(0.0, 1.0), Vec2::new(0.0, 0.0), Vec2::new(1.0, 0.0)], Color::BLACK),
-                Triangle::new([Vec3::new(0.0, 0.0, 1.0), Vec3::new(0.0, 1.0, 0.0), Vec3::new(0.0, 0.0, 0.0)], [Vec2::new(0.0, 1.0), Vec2::new(1.0, 0.0), Vec2::new(1.0, 1.0)], Color::BLACK),
-
-                Triangle::new([Vec3::new(0.0, 1.0, 0.0), Vec3::new(0.0, 1.0, 1.0), Vec3::new(1.0, 1.0, 1.0)], [Vec2::new(0.0, 1.0), Vec2::new(0.0, 0.0), Vec2::new(1.0, 0.0)], Color::BLACK),
-                Triangle::new([Vec3::new(0.0, 1.0, 0.0), Vec3::new(1.0, 1.0, 1.0), Vec3::new(1.0, 1.0, 0.0)], [Vec2::new(0.0, 1.0), Vec2::new(1.0, 0.0), Vec2::new(1.0, 1.0)], Color::BLACK),
-
-                Triangle::new([Vec3::new(1.0, 0.0, 1.0), Vec3::new(0.0, 0.0, 1.0), Vec3::new(0.0, 0.0, 0.0)], [Vec2::new(0.0, 1.0), Vec2::new(0.0, 0.0), Vec2::new(1.0, 0.0)], Color::BLACK),
-                Triangle::new([Vec3::new(1.0, 0.0, 1.0), Vec3::new(0.0, 0.0, 0.0), Vec3::new(1.0, 0.0, 0.0)], [Vec2::new(0.0, 1.0), Vec2::new(1.0, 0.0), Vec2::new(1.0, 1.0)], Color::BLACK),
-            ]
-        }
-    }
-
-    pub fn render(&self, camera: &Camera) -> Vec<Triangle> {
-        let mut triangles_to_draw: Vec<Triangle> = Vec::new();
-
-        let target: Vec3 = camera.pos + camera.look_direction;
-        let view: Matrix4x4 = Matrix4x4::point_at_inverse(&Matrix4x4::point_at(camera.pos, target, camera.get_up()));
-        let world_matrix: Matrix4x4 = Matrix4x4::translation(0.0, -2.0, 4.0);
-        let projection_matrix: Matrix4x4 = Matrix4x4::projection(RESOLUTION[1] as f32 / RESOLUTION[0] as f32, 1.0 / (90.0_f32  * 0.5).to_radians().tan(), 0.1, 1000.0);
-
-        for triangle in &self.triangles {
-            let transformed_triangle = Triangle::new(
-                [
-                    (Vec4::from_vec3(triangle.points[0], 1.0) * world_matrix).xyz(),
-                    (Vec4::from_vec3(triangle.points[1], 1.0) * world_matrix).xyz(),
-                    (Vec4::from_vec3(triangle.points[2], 1.0) * world_matrix).xyz()
-                ],
-                triangle.texture,
-                triangle.color
-            );
-
-            let line1 = transformed_triangle.points[1] - transformed_triangle.points[0];
-            let line2 = transformed_triangle.points[2] - transformed_triangle.points[0];
-
-            let mut normal: Vec3 = Vec3::new(
-                line1.y * line2.z - line1.z * line2.y,
-                line1.z * line2.x - line1.x * line2.z,
-                line1.x * line2.y - line1.y * line2.x
-            );
-            normal.normalize();
-
-            let camera_ray = transformed_triangle.points[0] - camera.pos;
-
-            // Projection
-            if normal.dot(&camera_ray) < 0.0 {
-                // Lighting (very simple one)
-                let mut light_direction: Vec3 = Vec3::new(0.0, 1.0, -1.0);
-                light_direction.normalize();
-
-                let dp = light_direction.dot(&normal).max(0.1);
-
-                let color = Color::RGB((dp * 255.0) as u8, (dp * 255.0) as u8, (dp * 255.0) as u8);
-
-                let viewed_triangle = Triangle::new(
-                    [
-                        (Vec4::from_vec3(transformed_triangle.points[0], 1.0) * view).xyz(),
-                        (Vec4::from_vec3(transformed_triangle.points[1], 1.0) * view).xyz(),
-                        (Vec4::from_vec3(transformed_triangle.points[2], 1.0) * view).xyz()
-                    ],
-                    transformed_triangle.texture,
-                    color
-                );
-
-                // clip the triangle against the near plane
-                let clipped_triangles = viewed_triangle.clip_against_plane(Vec3::new(0.0, 0.0, 0.1), Vec3::new(0.0, 0.0, 1.0));
-
-
-                for clipped_triangle in clipped_triangles {
-                    // Actual projection
-                    let projections: [Vec4; 3] = [
-                        Vec4::from_vec3(clipped_triangle.points[0], 1.0) * projection_matrix,
-                        Vec4::from_vec3(clipped_triangle.points[1], 1.0) * projection_matrix,
-                        Vec4::from_vec3(clipped_triangle.points[2], 1.0) * projection_matrix
-                    ];
-
-                    let mut projected_triangle: Triangle = Triangle::new(
-                        [
-                            projections[0].xyz() / Vec3::new(projections[0].w, projections[0].w, projections[0].w),
-                            projections[1].xyz() / Vec3::new(projections[1].w, projections[1].w, projections[1].w),
-                            projections[2].xyz() / Vec3::new(projections[2].w, projections[2].w, projections[2].w)
-                        ],
-                        clipped_triangle.texture,
-                        clipped_triangle.color
-                    );
-                    projected_triangle.set_color(clipped_triangle.get_color());
-
-                    projected_triangle.points[0] *= Vec3::new(-1.0, -1.0, 1.0);
-                    projected_triangle.points[1] *= Vec3::new(-1.0, -1.0, 1.0);
-                    projected_triangle.points[2] *= Vec3::new(-1.0, -1.0, 1.0);
-
-                    // don't ask what is going on over here
-                    let offset = Vec3::new(1.0, 1.0, 0.0);
-                    projected_triangle.points[0] += offset;
-                    projected_triangle.points[1] += offset;
-                    projected_triangle.points[2] += offset;
-
-                    let coef = Vec3::new(0.5 * RESOLUTION[0] as f32, 0.5 * RESOLUTION[1] as f32, 1.0);
-                    projected_triangle.points[0] *= coef;
-                    projected_triangle.points[1] *= coef;
-                    projected_triangle.points[2] *= coef;
-
-                    triangles_to_draw.push(projected_triangle);
-                }
-            }
-        };
-        return triangles_to_draw;
-    }
-
-    pub fn translate(&mut self, dx: f32, dy: f32, dz: f32) {
-        let translation_matrix = Matrix4x4::translation(dx, dy, dz);
-        for triangle in &mut self.triangles {
-            triangle.points[0] = (Vec4::from_vec3(triangle.points[0], 1.0) * translation_matrix).xyz();
-            triangle.points[1] = (Vec4::from_vec3(triangle.points[1], 1.0) * translation_matrix).xyz();
-            triangle.points[2] = (Vec4::from_vec3(triangle.points[2], 1.0) * translation_matrix).xyz();
-        }
-    }
-}
-impl<'a> IntoIterator for &'a Mesh {
-    type Item = <std::slice::Iter<'a, Triangle> as Iterator>::Item;
-    type IntoIter = std::slice::Iter<'a, Triangle>;
-
-    fn into_iter(self) -> Self::IntoIter {
-        return self.triangles.as_slice().into_iter();
-    }
-}
-
-
-#[derive(Copy, Clone)]
-pub struct Matrix4x4 {
-    pub mat: [[f32; 4]; 4]
-}
-impl Matrix4x4 {
-    pub fn new(mat: [[f32; 4]; 4]) -> Self {
-        return Self { mat };
-    }
-
-    pub fn empty() -> Self {
-        return Self {
-            mat: [
-                [0.0, 0.0, 0.0, 0.0],
-                [0.0, 0.0, 0.0, 0.0],
-                [0.0, 0.0, 0.0, 0.0],
-                [0.0, 0.0, 0.0, 0.0]
-            ]
-        }
-    }
-
-    pub fn identity() -> Self {
-        return Self {
-            mat: [
-                [1.0, 0.0, 0.0, 0.0],
-                [0.0, 1.0, 0.0, 0.0],
-                [0.0, 0.0, 1.0, 0.0],
-                [0.0, 0.0, 0.0, 1.0]
-            ]
-        }
-    }
-
-    pub fn x_rotation(theta: f32) -> Self {
-        return Self {mat: [
-            [1.0,  0.0,                   0.0,                  0.0],
-            [0.0,  (theta * 0.5).cos(),   (theta * 0.5).sin(),  0.0],
-            [0.0,  -(theta * 0.5).sin(),  (theta * 0.5).cos(),  0.0],
-            [0.0,  0.0,                   0.0,                  1.0]
-        ]};
-    }
-
-    pub fn z_rotation(theta: f32) -> Self {
-        return Self {mat: [
-            [theta.cos(),   theta.sin(),  0.0,  0.0],
-            [-theta.sin(),  theta.cos(),  0.0,  0.0],
-            [0.0,           0.0,          1.0,  0.0],
-            [0.0,           0.0,          0.0,  1.0]
-        ]};
-    }
-
-    pub fn y_rotation(thetha: f32) -> Self {
-        return Self {mat: [
-            [thetha.cos(),   0.0,  thetha.sin(),  0.0],
-            [0.0,            1.0,  0.0,           0.0],
-            [-thetha.sin(),  0.0,  thetha.cos(),  0.0],
-            [0.0,            0.0,  0.0,           1.0]
-        ]};
-    }
-
-    pub fn translation(x: f32, y: f32, z: f32) -> Self {
-        return Self {mat: [
-            [1.0, 0.0, 0.0, 0.0],
-            [0.0, 1.0, 0.0, 0.0],
-            [0.0, 0.0, 1.0, 0.0],
-            [x,   y,   z,   1.0]
-        ]};
-    }
-
-    pub fn projection(aspect_ratio: f32, fov: f32, near: f32, far: f32) -> Self {
-        return Self { mat: [
-            [aspect_ratio * fov,  0.0,  0.0,                           0.0],
-            [0.0,                 fov,  0.0,                           0.0],
-            [0.0,                 0.0,  far / (far - near),            1.0],
-            [0.0,                 0.0,  (-far * near) / (far - near),  0.0]
-        ]};
-    }
-
-    pub fn point_at(pos: Vec3, target: Vec3, up: Vec3) -> Self {
-        let mut new_forward = target - pos;
-        new_forward.normalize();
-
-        let a = new_forward * up.dot(&new_forward);
-        let mut new_up = up - a;
-        new_up.normalize();
-
-        let new_right = new_up.cross(&new_forward);
-
-        let matrix = Matrix4x4::new([
-            [new_right.x,    new_right.y,    new_right.z,    0.0],
-            [new_up.x,       new_up.y,       new_up.z,       0.0],
-            [new_forward.x,  new_forward.y,  new_forward.z,  0.0],
-            [pos.x,          pos.y,          pos.z,          1.0]
-        ]);
-
-        return matrix;
-    }
-
-    pub fn point_at_inverse(point_at: &Matrix4x4) -> Self {
-        let m = point_at;
-        return Matrix4x4::new([
-            [m.mat[0][0], m.mat[1][0], m.mat[2][0], 0.0],
-            [m.mat[0][1], m.mat[1][1], m.mat[2][1], 0.0],
-            [m.mat[0][2], m.mat[1][2], m.mat[2][2], 0.0],
-            [-(m.mat[3][0] * m.mat[0][0] + m.mat[3][1] * m.mat[0][1] + m.mat[3][2] * m.mat[0][2]), -(m.mat[3][0] * m.mat[1][0] + m.mat[3][1] * m.mat[1][1] + m.mat[3][2] * m.mat[1][2]), -(m.mat[3][0] * m.mat[2][0] + m.mat[3][1] * m.mat[2][1] + m.mat[3][2] * m.mat[2][2]), 1.0]
-        ]);
-    }
-}
-impl std::ops::Mul for Matrix4x4 {
-    type Output = Matrix4x4;
-    fn mul(self, rhs: Self) -> Self::Output {
-        let mut res = Self::empty();
-        for c in 0..4 {
-            for r in 0..4 {
-                res.mat[r][c] = self.mat[r][0] * rhs.mat[0][c] + self.mat[r][1] * rhs.mat[1][c] + self.mat[r][2] * rhs.mat[2][c] + self.mat[r][3] * rhs.mat[3][c];
-            }
-        }
-        return res;
-    }
-}
-
-pub mod clock {
-    use std::time::{Instant, Duration};
-    use std::thread::sleep;
-
-    pub struct Clock {
-        frame_start: Instant,
-        dt: Duration,
-        pub fps: u32,
-        frame_duration: Duration
-    }
-    impl Clock {
-        pub fn new(fps: u32) -> Self {
-            return Self {
-                frame_start: Instant::now(),
-                dt: Duration::new(0, 0),
-                fps,
-                frame_duration: Duration::new(0, 1_000_000_000_u32/fps)
-            };
-        }
-
-        pub fn tick(&mut self) -> Duration {
-            self.dt = self.frame_start.elapsed();
-            self.frame_start = Instant::now();
-
-            if self.dt < self.frame_duration {
-                sleep(self.frame_duration - self.dt);
-                return self.frame_duration;
-            } else {
-                return self.dt;
-            }
-        }
-    }
-
-    pub struct Timer {
-        frame_start: Instant,
-        dt: Duration,
-        pub fps: u32,
-        frame_duration: Duration
-    }
-    impl Timer {
-        pub fn new(fps: u32) -> Self {
-            return Self {
-                frame_start: Instant::now(),
-                dt: Duration::new(0, 0),
-                fps,
-                frame_duration: Duration::new(0, 1_000_000_000_u32/fps)
-            }
-        }
-
-        pub fn tick(&mut self) -> bool {
-            self.dt = self.frame_start.elapsed();
-
-            if self.dt < self.frame_duration {
-                return false;
-            } else {
-                self.frame_start = Instant::now().min(self.frame_start + self.frame_duration);
-                return true;
-            }
-        }
-    }
-
-}
-
-pub mod input {
-    use std::collections::HashSet;
-    use sdl2::keyboard::Keycode;
-    use sdl2::EventPump;
-
-    pub fn get_pressed_keys(events: &EventPump) -> HashSet<Keycode> {
-        return events
-                .keyboard_state()
-                .pressed_scancodes()
-                .filter_map(Keycode::from_scancode)
-                .collect();
-    }
+    engine.run();
 }
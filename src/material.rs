@@ -0,0 +1,133 @@
+use sdl2::pixels::Color;
+
+use crate::backend::Image;
+use crate::pbr::PbrMaterial;
+use crate::math::Vec3;
+use crate::shader::{modulate, to_linear};
+
+
+// How the pixels of a material are combined with what's already on the screen
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+pub enum BlendMode {
+    Opaque, // replaces it and hides anything behind it
+    Alpha,
+    Additive,
+    Multiply
+}
+impl BlendMode {
+    // `source` light with `alpha` on top of `destination`, as much of it as alpha says ends up on the screen
+    pub fn blend_light(self, source: Vec3, alpha: u8, destination: Vec3) -> Vec3 {
+        let a = alpha as f32 / 255.0;
+        return match self {
+            BlendMode::Opaque => source,
+            BlendMode::Alpha => source * a + destination * (1.0 - a),
+            BlendMode::Additive => destination + source * a,
+            BlendMode::Multiply => destination * (source * a + Vec3::new(1.0 - a, 1.0 - a, 1.0 - a))
+        };
+    }
+}
+impl std::str::FromStr for BlendMode {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        return match s {
+            "opaque" => Ok(BlendMode::Opaque),
+            "alpha" => Ok(BlendMode::Alpha),
+            "additive" => Ok(BlendMode::Additive),
+            "multiply" => Ok(BlendMode::Multiply),
+            _ => Err(format!("unknown blend mode '{}'", s))
+        };
+    }
+}
+impl std::fmt::Display for BlendMode {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        return f.pad(match self {
+            BlendMode::Opaque => "opaque",
+            BlendMode::Alpha => "alpha",
+            BlendMode::Additive => "additive",
+            BlendMode::Multiply => "multiply"
+        });
+    }
+}
+
+
+// What the surface of a mesh is made of, shared by every triangle pointing at it in `Scene::materials`.
+//
+// Whatever colour the fragment shader gives a pixel is tinted by `color` and has its alpha, which already includes the
+// texture's, multiplied by `opacity`. Pixels whose alpha ends up below `alpha_cutoff` aren't drawn at all, which cuts
+// leaves out of a texture while still drawing them as opaque. Everything but `BlendMode::Opaque` is drawn after the
+// opaque geometry, back to front, without hiding what's behind it. `reflectivity` is how much of the scene's sky
+// `DefaultShader` mixes in, as if the surface were a mirror. A `normal_map` bends the surface's normals in tangent
+// space, see `shader::surface_normal`. In `RenderMode::Pbr` the surface is lit physically by `pbr`, with white plastic
+// standing in when it's `None`.
+#[derive(Clone)]
+pub struct Material {
+    pub color: Color,
+    pub opacity: f32,
+    pub blend_mode: BlendMode,
+    pub alpha_cutoff: f32,
+    pub reflectivity: f32,
+    pub texture: Option<Image>, // instead of the backend's, only `SoftwareBackend` uses it
+    pub normal_map: Option<Image>,
+    pub pbr: Option<PbrMaterial>
+}
+impl Material {
+    pub fn new() -> Self {
+        return Self {
+            color: Color::WHITE,
+            opacity: 1.0,
+            blend_mode: BlendMode::Opaque,
+            alpha_cutoff: 0.0,
+            reflectivity: 0.0,
+            texture: None,
+            normal_map: None,
+            pbr: None
+        };
+    }
+
+    // see-through, like glass
+    pub fn transparent(color: Color, opacity: f32) -> Self {
+        return Self { color, opacity, blend_mode: BlendMode::Alpha, ..Self::new() };
+    }
+
+    // opaque, with the parts of `texture` that are more transparent than `alpha_cutoff` cut out, like foliage
+    pub fn cutout(texture: Image, alpha_cutoff: f32) -> Self {
+        return Self { alpha_cutoff, texture: Some(texture), ..Self::new() };
+    }
+
+    // mirrors the sky by `reflectivity`, from 0 to 1
+    pub fn reflective(color: Color, reflectivity: f32) -> Self {
+        return Self { color, reflectivity, ..Self::new() };
+    }
+
+    // lit physically in `RenderMode::Pbr`
+    pub fn physical(pbr: PbrMaterial) -> Self {
+        return Self { pbr: Some(pbr), ..Self::new() };
+    }
+
+    pub fn is_transparent(&self) -> bool {
+        return self.blend_mode != BlendMode::Opaque;
+    }
+
+    // `color` with the tint and opacity applied
+    pub fn tint(&self, color: Color) -> Color {
+        let mut color = modulate(color, self.color);
+        color.a = (color.a as f32 * self.opacity.clamp(0.0, 1.0)) as u8;
+        return color;
+    }
+
+    // the linear light and alpha from the fragment shader tinted, `None` if it's cut out
+    pub fn apply_light(&self, light: Vec3, alpha: u8) -> Option<(Vec3, u8)> {
+        let alpha = (alpha as u32 * self.color.a as u32 / 255) as u8;
+        let alpha = (alpha as f32 * self.opacity.clamp(0.0, 1.0)) as u8;
+        if (alpha as f32 / 255.0) < self.alpha_cutoff {
+            return None;
+        }
+        return Some((light * to_linear(self.color), alpha));
+    }
+}
+impl Default for Material {
+    fn default() -> Self {
+        return Self::new();
+    }
+}
@@ -0,0 +1,410 @@
+// Vectors, quaternions and the 4x4 matrix. Vectors are rows, so transforms are applied as `vector * matrix`.
+
+
+pub const WORLD_UP: Vec3 = Vec3 { x: 0.0, y: 1.0, z: 0.0 };
+
+
+#[derive(Copy, Clone)]
+pub struct Vec4 {
+    pub x: f32,
+    pub y: f32,
+    pub z: f32,
+    pub w: f32
+}
+impl Vec4 {
+    pub fn new(x: f32, y: f32, z: f32, w: f32) -> Self {
+        return Vec4 { x, y, z, w };
+    }
+
+    pub fn from_vec3(v: Vec3, w: f32) -> Self {
+        return Self::new(v.x, v.y, v.z, w);
+    }
+
+    pub fn xyz(&self) -> Vec3 {
+        return Vec3::new(self.x, self.y, self.z);
+    }
+
+    pub fn lerp(&self, other: Vec4, t: f32) -> Vec4 {
+        return Vec4::new(
+            self.x + (other.x - self.x) * t,
+            self.y + (other.y - self.y) * t,
+            self.z + (other.z - self.z) * t,
+            self.w + (other.w - self.w) * t
+        );
+    }
+}
+impl std::ops::Mul<Matrix4x4> for Vec4 {
+    type Output = Vec4;
+    fn mul(self, rhs: Matrix4x4) -> Self::Output {
+        return Vec4::new(
+            self.x * rhs.mat[0][0] + self.y * rhs.mat[1][0] + self.z * rhs.mat[2][0] + self.w * rhs.mat[3][0],
+            self.x * rhs.mat[0][1] + self.y * rhs.mat[1][1] + self.z * rhs.mat[2][1] + self.w * rhs.mat[3][1],
+            self.x * rhs.mat[0][2] + self.y * rhs.mat[1][2] + self.z * rhs.mat[2][2] + self.w * rhs.mat[3][2],
+            self.x * rhs.mat[0][3] + self.y * rhs.mat[1][3] + self.z * rhs.mat[2][3] + self.w * rhs.mat[3][3]
+        );
+    }
+}
+
+#[derive(Copy, Clone)]
+pub struct Vec3 {
+    pub x: f32,
+    pub y: f32,
+    pub z: f32
+}
+impl Vec3 {
+    pub fn new(x: f32, y: f32, z: f32) -> Self {
+        return Self { x, y, z };
+    }
+
+    pub fn normalize(&mut self) {
+        let length = (self.x * self.x + self.y*self.y + self.z*self.z).sqrt();
+        self.x /= length;
+        self.y /= length;
+        self.z /= length;
+    }
+
+    pub fn length(&self) -> f32 {
+        return (self.x * self.x + self.y*self.y + self.z*self.z).sqrt();
+    }
+
+    pub fn normalized(&self) -> Vec3 {
+        let length = (self.x * self.x + self.y*self.y + self.z*self.z).sqrt();
+        return Vec3::new(self.x / length, self.y / length, self.z / length);
+    }
+
+    pub fn dot(&self, other: &Vec3) -> f32 {
+        return self.x * other.x + self.y * other.y + self.z * other.z;
+    }
+
+    pub fn cross(&self, other: &Vec3) -> Vec3 {
+        return Vec3::new(
+            self.y * other.z - self.z * other.y,
+            self.z * other.x - self.x * other.z,
+            self.x * other.y - self.y * other.x
+        );
+    }
+
+    pub fn plane_intersect(plane_p: Vec3, mut plane_n: Vec3, line_start: Vec3, line_end: Vec3) -> (Self, f32) {
+        plane_n.normalize();
+        let plane_d = -(plane_n.dot(&plane_p));
+        let ad = line_start.dot(&plane_n);
+        let bd = line_end.dot(&plane_n);
+        let t = (-plane_d - ad) / (bd - ad);
+        let line_start_to_end = line_end - line_start;
+        let line_to_intersect = line_start_to_end * t;
+        return (line_start + line_to_intersect, t);
+    }
+}
+impl std::ops::Add for Vec3 {
+    type Output = Vec3;
+    fn add(self, rhs: Self) -> Self::Output {
+        return Self::new(self.x + rhs.x, self.y + rhs.y, self.z + rhs.z);
+    }
+}
+impl std::ops::AddAssign for Vec3 {
+    fn add_assign(&mut self, rhs: Self) {
+        self.x += rhs.x;
+        self.y += rhs.y;
+        self.z += rhs.z
+    }
+}
+impl std::ops::Sub for Vec3 {
+    type Output = Vec3;
+    fn sub(self, rhs: Self) -> Self::Output {
+        return Self::new(self.x - rhs.x, self.y - rhs.y, self.z - rhs.z);
+    }
+}
+impl std::ops::SubAssign for Vec3 {
+    fn sub_assign(&mut self, rhs: Self) {
+        self.x -= rhs.x;
+        self.y -= rhs.y;
+        self.z -= rhs.z;
+    }
+}
+impl std::ops::Mul for Vec3 {
+    type Output = Vec3;
+    fn mul(self, rhs: Self) -> Self::Output {
+        return Self::new(self.x * rhs.x, self.y * rhs.y, self.z * rhs.z);
+    }
+}
+impl std::ops::Mul<f32> for Vec3 {
+    type Output = Vec3;
+    fn mul(self, rhs: f32) -> Self::Output {
+        return Vec3::new(self.x * rhs, self.y * rhs, self.z * rhs);
+    }
+}
+impl std::ops::MulAssign for Vec3 {
+    fn mul_assign(&mut self, rhs: Self) {
+        self.x *= rhs.x;
+        self.y *= rhs.y;
+        self.z *= rhs.z;
+    }
+}
+impl std::ops::Div for Vec3 {
+    type Output = Vec3;
+    fn div(self, rhs: Self) -> Self::Output {
+        return Self::new(self.x / rhs.x, self.y / rhs.y, self.z / rhs.z);
+    }
+}
+impl std::fmt::Display for Vec3 {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        return f.pad(format!("({}, {}, {})", self.x as i32, self.y as i32, self.z as i32).as_str());
+    }
+}
+
+
+#[derive(Copy, Clone)]
+pub struct Vec2 {
+    pub x: f32,
+    pub y: f32
+}
+impl Vec2 {
+    pub fn new(x: f32, y: f32) -> Self {
+        return Self { x, y };
+    }
+
+    pub fn lerp(&self, v2: Vec2, mut t: f32) -> Vec2 {
+        t = t.clamp(0.0, 1.0);
+        return (v2 - *self) * t + *self;
+    }
+}
+impl std::ops::Add for Vec2 {
+    type Output = Vec2;
+    fn add(self, rhs: Self) -> Self::Output {
+        return Vec2::new(self.x + rhs.x, self.y + rhs.y);
+    }
+}
+impl std::ops::Sub for Vec2 {
+    type Output = Vec2;
+    fn sub(self, rhs: Self) -> Self::Output {
+        return Vec2::new(self.x - rhs.x, self.y - rhs.y);
+    }
+}
+impl std::ops::Mul<f32> for Vec2 {
+    type Output = Vec2;
+    fn mul(self, rhs: f32) -> Self::Output {
+        return Vec2::new(self.x * rhs, self.y * rhs);
+    }
+}
+
+
+#[derive(Copy, Clone)]
+pub struct Quat {
+    pub w: f32,
+    pub x: f32,
+    pub y: f32,
+    pub z: f32
+}
+impl Quat {
+    pub fn new(w: f32, x: f32, y: f32, z: f32) -> Self {
+        return Self { w, x, y, z };
+    }
+
+    pub fn identity() -> Self {
+        return Self::new(1.0, 0.0, 0.0, 0.0);
+    }
+
+    pub fn from_axis_angle(axis: Vec3, angle: f32) -> Self {
+        let axis = axis.normalized();
+        let s = (angle * 0.5).sin();
+        return Self::new((angle * 0.5).cos(), axis.x * s, axis.y * s, axis.z * s);
+    }
+
+    // same convention as `Camera::look_at`: yaw around the Y axis, then pitch up from the XZ plane
+    pub fn from_yaw_pitch(yaw: f32, pitch: f32) -> Self {
+        return Self::from_axis_angle(Vec3::new(0.0, 1.0, 0.0), yaw) * Self::from_axis_angle(Vec3::new(1.0, 0.0, 0.0), -pitch);
+    }
+
+    pub fn to_yaw_pitch(&self) -> (f32, f32) {
+        let forward = self.rotate(Vec3::new(0.0, 0.0, 1.0));
+        return (forward.x.atan2(forward.z), forward.y.clamp(-1.0, 1.0).asin());
+    }
+
+    pub fn dot(&self, other: &Quat) -> f32 {
+        return self.w * other.w + self.x * other.x + self.y * other.y + self.z * other.z;
+    }
+
+    pub fn normalized(&self) -> Quat {
+        let length = self.dot(self).sqrt();
+        return Quat::new(self.w / length, self.x / length, self.y / length, self.z / length);
+    }
+
+    pub fn conjugate(&self) -> Quat {
+        return Quat::new(self.w, -self.x, -self.y, -self.z);
+    }
+
+    pub fn rotate(&self, v: Vec3) -> Vec3 {
+        let p = *self * Quat::new(0.0, v.x, v.y, v.z) * self.conjugate();
+        return Vec3::new(p.x, p.y, p.z);
+    }
+
+    // spherical interpolation, always along the shorter arc
+    pub fn slerp(&self, mut other: Quat, t: f32) -> Quat {
+        let mut cos_theta = self.dot(&other);
+        if cos_theta < 0.0 {
+            other = Quat::new(-other.w, -other.x, -other.y, -other.z);
+            cos_theta = -cos_theta;
+        }
+
+        // nearly the same rotation, fall back to a normalized lerp to avoid dividing by ~0
+        if cos_theta > 0.9995 {
+            return Quat::new(
+                self.w + (other.w - self.w) * t,
+                self.x + (other.x - self.x) * t,
+                self.y + (other.y - self.y) * t,
+                self.z + (other.z - self.z) * t
+            ).normalized();
+        }
+
+        let theta = cos_theta.acos();
+        let a = ((1.0 - t) * theta).sin() / theta.sin();
+        let b = (t * theta).sin() / theta.sin();
+        return Quat::new(
+            self.w * a + other.w * b,
+            self.x * a + other.x * b,
+            self.y * a + other.y * b,
+            self.z * a + other.z * b
+        );
+    }
+}
+impl std::ops::Mul for Quat {
+    type Output = Quat;
+    fn mul(self, rhs: Self) -> Self::Output {
+        return Quat::new(
+            self.w * rhs.w - self.x * rhs.x - self.y * rhs.y - self.z * rhs.z,
+            self.w * rhs.x + self.x * rhs.w + self.y * rhs.z - self.z * rhs.y,
+            self.w * rhs.y - self.x * rhs.z + self.y * rhs.w + self.z * rhs.x,
+            self.w * rhs.z + self.x * rhs.y - self.y * rhs.x + self.z * rhs.w
+        );
+    }
+}
+
+
+#[derive(Copy, Clone)]
+pub struct Matrix4x4 {
+    pub mat: [[f32; 4]; 4]
+}
+impl Matrix4x4 {
+    pub fn new(mat: [[f32; 4]; 4]) -> Self {
+        return Self { mat };
+    }
+
+    pub fn empty() -> Self {
+        return Self {
+            mat: [
+                [0.0, 0.0, 0.0, 0.0],
+                [0.0, 0.0, 0.0, 0.0],
+                [0.0, 0.0, 0.0, 0.0],
+                [0.0, 0.0, 0.0, 0.0]
+            ]
+        }
+    }
+
+    pub fn identity() -> Self {
+        return Self {
+            mat: [
+                [1.0, 0.0, 0.0, 0.0],
+                [0.0, 1.0, 0.0, 0.0],
+                [0.0, 0.0, 1.0, 0.0],
+                [0.0, 0.0, 0.0, 1.0]
+            ]
+        }
+    }
+
+    pub fn x_rotation(theta: f32) -> Self {
+        return Self {mat: [
+            [1.0,  0.0,                   0.0,                  0.0],
+            [0.0,  (theta * 0.5).cos(),   (theta * 0.5).sin(),  0.0],
+            [0.0,  -(theta * 0.5).sin(),  (theta * 0.5).cos(),  0.0],
+            [0.0,  0.0,                   0.0,                  1.0]
+        ]};
+    }
+
+    pub fn z_rotation(theta: f32) -> Self {
+        return Self {mat: [
+            [theta.cos(),   theta.sin(),  0.0,  0.0],
+            [-theta.sin(),  theta.cos(),  0.0,  0.0],
+            [0.0,           0.0,          1.0,  0.0],
+            [0.0,           0.0,          0.0,  1.0]
+        ]};
+    }
+
+    pub fn y_rotation(thetha: f32) -> Self {
+        return Self {mat: [
+            [thetha.cos(),   0.0,  thetha.sin(),  0.0],
+            [0.0,            1.0,  0.0,           0.0],
+            [-thetha.sin(),  0.0,  thetha.cos(),  0.0],
+            [0.0,            0.0,  0.0,           1.0]
+        ]};
+    }
+
+    pub fn translation(x: f32, y: f32, z: f32) -> Self {
+        return Self {mat: [
+            [1.0, 0.0, 0.0, 0.0],
+            [0.0, 1.0, 0.0, 0.0],
+            [0.0, 0.0, 1.0, 0.0],
+            [x,   y,   z,   1.0]
+        ]};
+    }
+
+    pub fn projection(aspect_ratio: f32, fov: f32, near: f32, far: f32) -> Self {
+        return Self { mat: [
+            [aspect_ratio * fov,  0.0,  0.0,                           0.0],
+            [0.0,                 fov,  0.0,                           0.0],
+            [0.0,                 0.0,  far / (far - near),            1.0],
+            [0.0,                 0.0,  (-far * near) / (far - near),  0.0]
+        ]};
+    }
+
+    // keeps w = 1, so the perspective divide after clipping leaves the points as they are
+    pub fn orthographic(aspect_ratio: f32, size: f32, near: f32, far: f32) -> Self {
+        return Self { mat: [
+            [aspect_ratio / size,  0.0,         0.0,                   0.0],
+            [0.0,                  1.0 / size,  0.0,                   0.0],
+            [0.0,                  0.0,         1.0 / (far - near),    0.0],
+            [0.0,                  0.0,         -near / (far - near),  1.0]
+        ]};
+    }
+
+    pub fn point_at(pos: Vec3, target: Vec3, up: Vec3) -> Self {
+        let mut new_forward = target - pos;
+        new_forward.normalize();
+
+        let a = new_forward * up.dot(&new_forward);
+        let mut new_up = up - a;
+        new_up.normalize();
+
+        let new_right = new_up.cross(&new_forward);
+
+        let matrix = Matrix4x4::new([
+            [new_right.x,    new_right.y,    new_right.z,    0.0],
+            [new_up.x,       new_up.y,       new_up.z,       0.0],
+            [new_forward.x,  new_forward.y,  new_forward.z,  0.0],
+            [pos.x,          pos.y,          pos.z,          1.0]
+        ]);
+
+        return matrix;
+    }
+
+    pub fn point_at_inverse(point_at: &Matrix4x4) -> Self {
+        let m = point_at;
+        return Matrix4x4::new([
+            [m.mat[0][0], m.mat[1][0], m.mat[2][0], 0.0],
+            [m.mat[0][1], m.mat[1][1], m.mat[2][1], 0.0],
+            [m.mat[0][2], m.mat[1][2], m.mat[2][2], 0.0],
+            [-(m.mat[3][0] * m.mat[0][0] + m.mat[3][1] * m.mat[0][1] + m.mat[3][2] * m.mat[0][2]), -(m.mat[3][0] * m.mat[1][0] + m.mat[3][1] * m.mat[1][1] + m.mat[3][2] * m.mat[1][2]), -(m.mat[3][0] * m.mat[2][0] + m.mat[3][1] * m.mat[2][1] + m.mat[3][2] * m.mat[2][2]), 1.0]
+        ]);
+    }
+}
+impl std::ops::Mul for Matrix4x4 {
+    type Output = Matrix4x4;
+    fn mul(self, rhs: Self) -> Self::Output {
+        let mut res = Self::empty();
+        for c in 0..4 {
+            for r in 0..4 {
+                res.mat[r][c] = self.mat[r][0] * rhs.mat[0][c] + self.mat[r][1] * rhs.mat[1][c] + self.mat[r][2] * rhs.mat[2][c] + self.mat[r][3] * rhs.mat[3][c];
+            }
+        }
+        return res;
+    }
+}
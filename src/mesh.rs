@@ -0,0 +1,445 @@
+use std::collections::HashMap;
+use std::time::Instant;
+use sdl2::pixels::Color;
+
+use crate::clip::Clipper;
+use crate::math::{Matrix4x4, Vec2, Vec3, Vec4};
+use crate::shader::{Shader, Uniforms};
+use crate::stats::{RenderStats, Stage};
+use crate::vertex::{Attribute, Vertex};
+
+
+#[derive(Copy, Clone)]
+pub struct Triangle {
+    pub vertices: [Vertex; 3],
+    pub material: u32 // index into `Scene::materials`
+}
+impl Triangle {
+    // corners with texture coordinates and one colour, the other attributes start out as zero
+    pub fn new(points: [Vec3; 3], texture: [Vec2; 3], color: Color) -> Self {
+        let mut triangle = Self::from_vertices(points.map(Vertex::new));
+        for (vertex, uv) in triangle.vertices.iter_mut().zip(texture) {
+            vertex.attributes.set_uv(uv);
+        }
+        triangle.set_color(color);
+        return triangle;
+    }
+
+    // with the scene's first material
+    pub fn from_vertices(vertices: [Vertex; 3]) -> Self {
+        return Self { vertices, material: 0 };
+    }
+
+    pub fn points(&self) -> [Vec3; 3] {
+        return self.vertices.map(|vertex| vertex.point());
+    }
+
+    // every corner
+    pub fn set_color(&mut self, color: Color) {
+        for vertex in &mut self.vertices {
+            vertex.attributes.set_color(color);
+        }
+    }
+
+    // the first corner's, which is the whole triangle's unless the corners were given their own
+    pub fn get_color(&self) -> Color {
+        return self.vertices[0].attributes.color();
+    }
+
+    // perpendicular to the triangle, on the side its corners go around the other way from
+    pub fn face_normal(&self) -> Vec3 {
+        let points = self.points();
+        let line1 = points[1] - points[0];
+        let line2 = points[2] - points[0];
+
+        let mut normal: Vec3 = Vec3::new(
+            line1.y * line2.z - line1.z * line2.y,
+            line1.z * line2.x - line1.x * line2.z,
+            line1.x * line2.y - line1.y * line2.x
+        );
+        normal.normalize();
+        return normal;
+    }
+
+    // Where the texture's u and v grow along the triangle, for normal mapping. Zero if its texture coordinates
+    // don't span an area.
+    pub fn texture_tangents(&self) -> (Vec3, Vec3) {
+        let points = self.points();
+        let uv = self.vertices.map(|vertex| vertex.attributes.uv());
+        let (edge1, edge2) = (points[1] - points[0], points[2] - points[0]);
+        let (duv1, duv2) = (uv[1] - uv[0], uv[2] - uv[0]);
+
+        let determinant = duv1.x * duv2.y - duv2.x * duv1.y;
+        if determinant == 0.0 {
+            return (Vec3::new(0.0, 0.0, 0.0), Vec3::new(0.0, 0.0, 0.0));
+        }
+        let tangent = (edge1 * duv2.y - edge2 * duv1.y) * (1.0 / determinant);
+        let bitangent = (edge2 * duv1.x - edge1 * duv2.x) * (1.0 / determinant);
+        return (tangent, bitangent);
+    }
+
+    pub fn midpoint(&self) -> f32 {
+        return (self.vertices[0].position.z + self.vertices[1].position.z + self.vertices[2].position.z) / 3.0;
+    }
+}
+impl PartialEq for Triangle {
+    fn eq(&self, other: &Self) -> bool {
+        return self.midpoint() == other.midpoint();
+    }
+}
+impl Eq for Triangle {}
+impl PartialOrd for Triangle {
+    fn partial_cmp(&self, other: &Self) -> Option<std::cmp::Ordering> {
+        return Some(self.cmp(other));
+    }
+}
+impl Ord for Triangle {
+    fn cmp(&self, other: &Self) -> std::cmp::Ordering {
+        if self.midpoint() < other.midpoint() {
+            return std::cmp::Ordering::Less;
+        }
+        else if self.midpoint() > other.midpoint() {
+            return std::cmp::Ordering::Greater;
+        }
+        else {
+            return std::cmp::Ordering::Equal;
+        }
+    }
+}
+
+
+// What `Mesh::render_triangles` keeps between its steps, owned by the caller so it's only allocated once
+#[derive(Default)]
+pub struct PipelineBuffers {
+    triangles: Vec<Triangle>
+}
+
+
+pub struct Mesh {
+    triangles: Vec<Triangle>
+}
+impl Mesh {
+    // triangles whose corners don't have normals get the normal of their face, corners without tangents get them
+    // from `generate_tangents`
+    pub fn new(mut triangles: Vec<Triangle>) -> Self {
+        for triangle in &mut triangles {
+            if triangle.vertices.iter().all(|vertex| vertex.attributes.normal().length() == 0.0) {
+                let normal = triangle.face_normal();
+                for vertex in &mut triangle.vertices {
+                    vertex.attributes.set_normal(normal);
+                }
+            }
+        }
+        Self::generate_tangents(&mut triangles);
+        return Self { triangles };
+    }
+
+    // Every corner without a tangent gets the tangents and bitangents of all triangles sharing its position, normal and
+    // texture coordinates added up, made perpendicular to its normal. The bitangent is only kept as the sign in the
+    // tangent's w, shaders get it back as the cross product of the normal and the tangent times w. Corners whose
+    // texture doesn't say which way to go get any tangent that's perpendicular to the normal.
+    fn generate_tangents(triangles: &mut [Triangle]) {
+        let needs_tangent = |vertex: &Vertex| vertex.attributes.vec3(Attribute::Tangent).length() == 0.0;
+        let key = |vertex: &Vertex| -> [u32; 8] {
+            let (p, n, uv) = (vertex.point(), vertex.attributes.normal(), vertex.attributes.uv());
+            return [p.x, p.y, p.z, n.x, n.y, n.z, uv.x, uv.y].map(f32::to_bits);
+        };
+
+        let mut sums: HashMap<[u32; 8], (Vec3, Vec3)> = HashMap::new();
+        for triangle in triangles.iter().filter(|triangle| triangle.vertices.iter().any(needs_tangent)) {
+            let (tangent, bitangent) = triangle.texture_tangents();
+            for vertex in triangle.vertices.iter().filter(|vertex| needs_tangent(vertex)) {
+                let sum = sums.entry(key(vertex)).or_insert((Vec3::new(0.0, 0.0, 0.0), Vec3::new(0.0, 0.0, 0.0)));
+                sum.0 += tangent;
+                sum.1 += bitangent;
+            }
+        }
+
+        for vertex in triangles.iter_mut().flat_map(|triangle| &mut triangle.vertices) {
+            let Some((tangent, bitangent)) = sums.get(&key(vertex)) else {
+                continue;
+            };
+            let normal = vertex.attributes.normal().normalized();
+
+            let mut tangent = *tangent - normal * normal.dot(tangent);
+            if tangent.length() < 1e-6 || tangent.length().is_nan() {
+                let axis = if normal.x.abs() < 0.9 { Vec3::new(1.0, 0.0, 0.0) } else { Vec3::new(0.0, 1.0, 0.0) };
+                tangent = axis - normal * normal.dot(&axis);
+            }
+            tangent.normalize();
+            let handedness = if normal.cross(&tangent).dot(bitangent) < 0.0 { -1.0 } else { 1.0 };
+            vertex.attributes.set_tangent(tangent, handedness);
+        }
+    }
+
+    // Positions, texture coordinates, normals and faces are read. Faces with more than three corners are cut into a
+    // fan of triangles, corners without a normal get the normal of their face.
+    pub fn parse_obj(str: &str) -> Result<Self, String> {
+        let mut triangles: Vec<Triangle> = Vec::new();
+
+        let mut vertices: Vec<Vec3> = Vec::new();
+        let mut texture_coordinates: Vec<Vec2> = Vec::new();
+        let mut normals: Vec<Vec3> = Vec::new();
+
+        for (n, line) in str.lines().enumerate() {
+            let i: Vec<&str> = line.split_whitespace().collect();
+            let error = |e: &str| format!("line {}: {}", n + 1, e);
+            let floats = |count: usize| -> Result<Vec<f32>, String> {
+                if i.len() < count + 1 {
+                    return Err(error(&format!("expected {} coordinates", count)));
+                }
+                return i[1..count + 1].iter().map(|value| value.parse::<f32>().map_err(|_| error("bad coordinate"))).collect();
+            };
+
+            if i.first() == Some(&"v") {
+                let v = floats(3)?;
+                vertices.push(Vec3::new(v[0], v[1], v[2]));
+            }
+            else if i.first() == Some(&"vt") {
+                // obj textures start at the bottom, images at the top
+                let vt = floats(2)?;
+                texture_coordinates.push(Vec2::new(vt[0], 1.0 - vt[1]));
+            }
+            else if i.first() == Some(&"vn") {
+                let vn = floats(3)?;
+                normals.push(Vec3::new(vn[0], vn[1], vn[2]));
+            }
+            else if i.first() == Some(&"f") {
+                if i.len() < 4 {
+                    return Err(error("expected 3 vertices"));
+                }
+
+                // `v`, `v/vt`, `v//vn` or `v/vt/vn`, counting from 1
+                let mut corners: Vec<Vertex> = Vec::with_capacity(i.len() - 1);
+                for corner in &i[1..] {
+                    let mut indices = corner.split('/');
+                    let mut index = |name: &str| -> Result<Option<usize>, String> {
+                        return match indices.next() {
+                            None | Some("") => Ok(None),
+                            Some(index) => index.parse::<usize>().map(|index| Some(index.wrapping_sub(1))).map_err(|_| error(&format!("bad {} index", name)))
+                        };
+                    };
+                    let (position, uv, normal) = (index("vertex")?, index("texture")?, index("normal")?);
+
+                    let position = position.ok_or(error("missing vertex index"))?;
+                    let mut vertex = Vertex::new(*vertices.get(position).ok_or(error("vertex index out of range"))?);
+                    vertex.attributes.set_color(Color::BLACK);
+                    if let Some(uv) = uv {
+                        vertex.attributes.set_uv(*texture_coordinates.get(uv).ok_or(error("texture index out of range"))?);
+                    }
+                    if let Some(normal) = normal {
+                        vertex.attributes.set_normal(*normals.get(normal).ok_or(error("normal index out of range"))?);
+                    }
+                    corners.push(vertex);
+                }
+
+                for corner in 1..corners.len() - 1 {
+                    triangles.push(Triangle::from_vertices([corners[0], corners[corner], corners[corner + 1]]));
+                }
+            }
+        }
+
+        return Ok(Self::new(triangles));
+    }
+
+    pub fn load(filename: &str) -> Result<Self, String> {
+        let content = std::fs::read_to_string(filename).map_err(|e| format!("{}: {}", filename, e))?;
+        return Self::parse_obj(&content).map_err(|e| format!("{}: {}", filename, e));
+    }
+
+    pub fn triangle_count(&self) -> usize {
+        return self.triangles.len();
+    }
+
+    pub fn cube() -> Self {
+        return Self::new(vec![
+                Triangle::new([Vec3::new(0.0, 0.0, 0.0), Vec3::new(0.0, 1.0, 0.0), Vec3::new(1.0, 1.0, 0.0)], [Vec2::new(0.0, 1.0), Vec2::new(0.0, 0.0), Vec2::new(1.0, 0.0)], Color::BLACK),
+                Triangle::new([Vec3::new(0.0, 0.0, 0.0), Vec3::new(1.0, 1.0, 0.0), Vec3::new(1.0, 0.0, 0.0)], [Vec2::new(0.0, 1.0), Vec2::new(1.0, 0.0), Vec2::new(1.0, 1.0)], Color::BLACK),
+
+                Triangle::new([Vec3::new(1.0, 0.0, 0.0), Vec3::new(1.0, 1.0, 0.0), Vec3::new(1.0, 1.0, 1.0)], [Vec2::new(0.0, 1.0), Vec2::new(0.0, 0.0), Vec2::new(1.0, 0.0)], Color::BLACK),
+                Triangle::new([Vec3::new(1.0, 0.0, 0.0), Vec3::new(1.0, 1.0, 1.0), Vec3::new(1.0, 0.0, 1.0)], [Vec2::new(0.0, 1.0), Vec2::new(1.0, 0.0), Vec2::new(1.0, 1.0)], Color::BLACK),
+
+                Triangle::new([Vec3::new(1.0, 0.0, 1.0), Vec3::new(1.0, 1.0, 1.0), Vec3::new(0.0, 1.0, 1.0)], [Vec2::new(0.0, 1.0), Vec2::new(0.0, 0.0), Vec2::new(1.0, 0.0)], Color::BLACK),
+                Triangle::new([Vec3::new(1.0, 0.0, 1.0), Vec3::new(0.0, 1.0, 1.0), Vec3::new(0.0, 0.0, 1.0)], [Vec2::new(0.0, 1.0), Vec2::new(1.0, 0.0), Vec2::new(1.0, 1.0)], Color::BLACK),
+
+                Triangle::new([Vec3::new(0.0, 0.0, 1.0), Vec3::new(0.0, 1.0, 1.0), Vec3::new(0.0, 1.0, 0.0)], [Vec2::new(0.0, 1.0), Vec2::new(0.0, 0.0), Vec2::new(1.0, 0.0)], Color::BLACK),
+                Triangle::new([Vec3::new(0.0, 0.0, 1.0), Vec3::new(0.0, 1.0, 0.0), Vec3::new(0.0, 0.0, 0.0)], [Vec2::new(0.0, 1.0), Vec2::new(1.0, 0.0), Vec2::new(1.0, 1.0)], Color::BLACK),
+
+                Triangle::new([Vec3::new(0.0, 1.0, 0.0), Vec3::new(0.0, 1.0, 1.0), Vec3::new(1.0, 1.0, 1.0)], [Vec2::new(0.0, 1.0), Vec2::new(0.0, 0.0), Vec2::new(1.0, 0.0)], Color::BLACK),
+                Triangle::new([Vec3::new(0.0, 1.0, 0.0), Vec3::new(1.0, 1.0, 1.0), Vec3::new(1.0, 1.0, 0.0)], [Vec2::new(0.0, 1.0), Vec2::new(1.0, 0.0), Vec2::new(1.0, 1.0)], Color::BLACK),
+
+                Triangle::new([Vec3::new(1.0, 0.0, 1.0), Vec3::new(0.0, 0.0, 1.0), Vec3::new(0.0, 0.0, 0.0)], [Vec2::new(0.0, 1.0), Vec2::new(0.0, 0.0), Vec2::new(1.0, 0.0)], Color::BLACK),
+                Triangle::new([Vec3::new(1.0, 0.0, 1.0), Vec3::new(0.0, 0.0, 0.0), Vec3::new(1.0, 0.0, 0.0)], [Vec2::new(0.0, 1.0), Vec2::new(1.0, 0.0), Vec2::new(1.0, 1.0)], Color::BLACK),
+            ]);
+    }
+
+    pub fn world_matrix() -> Matrix4x4 {
+        return Matrix4x4::translation(0.0, -2.0, 4.0);
+    }
+
+    // axis aligned bounding box in world space, as (min, max), `None` without any triangles
+    pub fn bounds(&self) -> Option<(Vec3, Vec3)> {
+        if self.triangles.is_empty() {
+            return None;
+        }
+
+        let world_matrix = Self::world_matrix();
+        let mut min = Vec3::new(f32::MAX, f32::MAX, f32::MAX);
+        let mut max = Vec3::new(f32::MIN, f32::MIN, f32::MIN);
+
+        for triangle in &self.triangles {
+            for point in triangle.points() {
+                let p = (Vec4::from_vec3(point, 1.0) * world_matrix).xyz();
+                min = Vec3::new(min.x.min(p.x), min.y.min(p.y), min.z.min(p.z));
+                max = Vec3::new(max.x.max(p.x), max.y.max(p.y), max.z.max(p.z));
+            }
+        }
+
+        return Some((min, max));
+    }
+
+    pub fn triangles(&self) -> &[Triangle] {
+        return &self.triangles;
+    }
+
+    // every triangle gets `material`, an index into `Scene::materials`
+    pub fn set_material(&mut self, material: usize) {
+        for triangle in &mut self.triangles {
+            triangle.material = material as u32;
+        }
+    }
+
+    // Runs `triangles` through the pipeline and appends the screen space results to `out`. `buffers` hold the steps in
+    // between. They and `out` keep their capacity from call to call, so once they're big enough nothing gets allocated.
+    // The steps are done one after another over all of `triangles`, so `stats` can time each of them separately
+    pub fn render_triangles(triangles: &[Triangle], shader: &dyn Shader, uniforms: &Uniforms, clipper: &Clipper, buffers: &mut PipelineBuffers, out: &mut Vec<Triangle>, stats: &mut RenderStats) {
+        stats.triangles_submitted += triangles.len();
+
+        // the vertex shader takes every corner into clip space
+        let start = Instant::now();
+        let clip_space = &mut buffers.triangles;
+        clip_space.clear();
+        clip_space.extend(triangles.iter().map(|triangle| Triangle {
+            vertices: triangle.vertices.each_ref().map(|vertex| shader.vertex(vertex, uniforms)),
+            material: triangle.material
+        }));
+        stats.add(Stage::Transform, start.elapsed());
+
+        // Triangles facing away from the camera end up wound the other way round on the screen. The winding is worked
+        // out before the perspective divide, which also works for corners behind the camera.
+        let start = Instant::now();
+        clip_space.retain(|triangle| {
+            let [a, b, c] = triangle.vertices.map(|vertex| vertex.position);
+            let determinant = a.x * (b.y * c.w - c.y * b.w) - a.y * (b.x * c.w - c.x * b.w) + a.w * (b.x * c.y - c.x * b.y);
+            return determinant < 0.0;
+        });
+        stats.triangles_after_culling += clip_space.len();
+        stats.add(Stage::Cull, start.elapsed());
+
+        // clip against the whole frustum and project what's left, straight into `out`
+        let start = Instant::now();
+        let first = out.len();
+        for triangle in clip_space.iter() {
+            for clipped in clipper.clip(triangle).triangles() {
+                out.push(clipper.project(clipped));
+            }
+        }
+        stats.triangles_after_clipping += out.len() - first;
+        stats.add(Stage::Clip, start.elapsed());
+    }
+
+    pub fn translate(&mut self, dx: f32, dy: f32, dz: f32) {
+        let translation_matrix = Matrix4x4::translation(dx, dy, dz);
+        for vertex in self.triangles.iter_mut().flat_map(|triangle| &mut triangle.vertices) {
+            vertex.position = vertex.position * translation_matrix;
+        }
+    }
+}
+impl<'a> IntoIterator for &'a Mesh {
+    type Item = <std::slice::Iter<'a, Triangle> as Iterator>::Item;
+    type IntoIter = std::slice::Iter<'a, Triangle>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        return self.triangles.iter();
+    }
+}
+impl std::str::FromStr for Mesh {
+    type Err = String;
+
+    // the contents of an `.obj` file, see `parse_obj`
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        return Self::parse_obj(s);
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SQUARE: &str = "v 0 0 0\nv 1 0 0\nv 1 1 0\nv 0 1 0\nvt 0 0\nvt 1 0\nvt 1 1\nvt 0 1\nvn 0 0 -1\n";
+
+    fn parse(faces: &str) -> Mesh {
+        return format!("{}{}", SQUARE, faces).parse::<Mesh>().unwrap();
+    }
+
+    #[test]
+    fn faces_with_only_positions_get_the_face_normal() {
+        let mesh = parse("f 1 2 3\n");
+        assert_eq!(mesh.triangle_count(), 1);
+        let triangle = mesh.triangles()[0];
+        assert_eq!(triangle.points().map(|p| (p.x, p.y, p.z)), [(0.0, 0.0, 0.0), (1.0, 0.0, 0.0), (1.0, 1.0, 0.0)]);
+        let normal = triangle.face_normal();
+        for vertex in &triangle.vertices {
+            let n = vertex.attributes.normal();
+            assert_eq!((n.x, n.y, n.z), (normal.x, normal.y, normal.z));
+            assert_eq!(vertex.attributes.uv().x, 0.0);
+        }
+    }
+
+    #[test]
+    fn faces_with_texture_coordinates() {
+        let triangle = parse("f 1/1 2/2 3/3\n").triangles()[0];
+        // the v axis is flipped, images start at the top
+        let uv = triangle.vertices.map(|vertex| (vertex.attributes.uv().x, vertex.attributes.uv().y));
+        assert_eq!(uv, [(0.0, 1.0), (1.0, 1.0), (1.0, 0.0)]);
+    }
+
+    #[test]
+    fn faces_with_normals() {
+        for faces in ["f 1//1 2//1 3//1\n", "f 1/1/1 2/2/1 3/3/1\n"] {
+            let triangle = parse(faces).triangles()[0];
+            for vertex in &triangle.vertices {
+                let n = vertex.attributes.normal();
+                assert_eq!((n.x, n.y, n.z), (0.0, 0.0, -1.0));
+            }
+        }
+        let triangle = parse("f 1/1/1 2/2/1 3/3/1\n").triangles()[0];
+        assert_eq!(triangle.vertices[1].attributes.uv().x, 1.0);
+    }
+
+    #[test]
+    fn polygons_are_cut_into_a_fan() {
+        let mesh = parse("f 1 2 3 4\n");
+        assert_eq!(mesh.triangle_count(), 2);
+        let corners = |i: usize| mesh.triangles()[i].points().map(|p| (p.x, p.y));
+        assert_eq!(corners(0), [(0.0, 0.0), (1.0, 0.0), (1.0, 1.0)]);
+        assert_eq!(corners(1), [(0.0, 0.0), (1.0, 1.0), (0.0, 1.0)]);
+    }
+
+    #[test]
+    fn malformed_data_is_an_error() {
+        for faces in ["f 1 2\n", "f 1 2 9\n", "f 1/9 2 3\n", "f 1//9 2 3\n", "f a 2 3\n", "v 1 2\n"] {
+            let result = format!("{}{}", SQUARE, faces).parse::<Mesh>();
+            assert!(result.is_err(), "{:?} should not parse", faces);
+        }
+    }
+
+    #[test]
+    fn empty_meshes_have_no_bounds() {
+        assert!("v 0 0 0\nv 1 0 0\n".parse::<Mesh>().unwrap().bounds().is_none());
+        // in world space, `world_matrix` moves everything down and away
+        let (min, max) = parse("f 1 2 3\n").bounds().unwrap();
+        assert_eq!(((min.x, min.y, min.z), (max.x, max.y, max.z)), ((0.0, -2.0, 4.0), (1.0, -1.0, 4.0)));
+    }
+}
@@ -0,0 +1,64 @@
+use std::time::Duration;
+use sdl2::pixels::Color;
+use sdl2::rect::Rect;
+use sdl2::render::{Canvas, RenderTarget, TextureCreator};
+
+use crate::hud::{Align, Hud, PADDING, PANEL_COLOR};
+use crate::stats::{fps, FrameStats, Stage};
+
+
+const HISTOGRAM_BUCKETS: usize = 40;
+const HISTOGRAM_BUCKET_WIDTH: Duration = Duration::from_millis(1);
+const HISTOGRAM_HEIGHT: u32 = 60;
+
+
+fn ms(duration: Duration) -> f32 {
+    return duration.as_secs_f32() * 1000.0;
+}
+
+
+// Frame time statistics, per stage timings and triangle counts in the top left corner
+pub struct PerformanceOverlay;
+impl PerformanceOverlay {
+    pub fn lines(stats: &FrameStats) -> Vec<String> {
+        let render = &stats.last;
+        let mut lines = vec![
+            format!("frame {:6.2} ms {:6.1} fps", ms(stats.frame_times().back().copied().unwrap_or_default()), fps(stats.average())),
+            format!("avg {:.2}  min {:.2}  max {:.2} ms", ms(stats.average()), ms(stats.min()), ms(stats.max())),
+            format!("1% low {:.1} fps", fps(stats.one_percent_low())),
+            format!(
+                "triangles {} -> {} culled -> {} clipped",
+                render.triangles_submitted, render.triangles_after_culling, render.triangles_after_clipping
+            )
+        ];
+        for stage in Stage::ALL {
+            lines.push(format!("{:<10}{:6.2} ms", stage.name(), ms(stats.stage_average(stage))));
+        }
+        return lines;
+    }
+
+    pub fn draw<T: RenderTarget, C>(hud: &Hud, canvas: &mut Canvas<T>, texture_creator: &TextureCreator<C>, stats: &FrameStats) {
+        let lines = Self::lines(stats);
+        let panel = hud.text_panel(canvas, texture_creator, &lines, PADDING, PADDING, Align::Left);
+
+        // frame time histogram below the text, one bar per millisecond, the last one collects everything slower
+        let width = panel.width().max(HISTOGRAM_BUCKETS as u32 * 4);
+        hud.panel(canvas, Rect::new(panel.x(), panel.bottom(), width, HISTOGRAM_HEIGHT + PADDING as u32 * 2), PANEL_COLOR);
+
+        let histogram = stats.histogram(HISTOGRAM_BUCKETS, HISTOGRAM_BUCKET_WIDTH);
+        let highest = histogram.iter().copied().max().unwrap_or(0).max(1);
+        let bar_width = (width - PADDING as u32 * 2) / HISTOGRAM_BUCKETS as u32;
+        let bottom = panel.bottom() + PADDING + HISTOGRAM_HEIGHT as i32;
+
+        for (i, count) in histogram.iter().enumerate() {
+            let bar_height = (*count as u32 * HISTOGRAM_HEIGHT / highest as u32).max(if *count > 0 { 1 } else { 0 });
+            if bar_height == 0 {
+                continue;
+            }
+            // green up to 60 fps, yellow up to 30 fps, red after that
+            let color = if i < 17 { Color::RGB(80, 200, 80) } else if i < 34 { Color::RGB(220, 200, 60) } else { Color::RGB(220, 70, 60) };
+            canvas.set_draw_color(color);
+            canvas.fill_rect(Rect::new(panel.x() + PADDING + i as i32 * bar_width as i32, bottom - bar_height as i32, bar_width - 1, bar_height)).ok();
+        }
+    }
+}
@@ -0,0 +1,246 @@
+use sdl2::pixels::Color;
+
+use crate::backend::Image;
+use crate::material::Material;
+use crate::math::{Vec2, Vec3};
+use crate::shader::{self, to_linear, to_srgb, Shader, Uniforms};
+use crate::vertex::{Attributes, Vertex};
+
+
+// how bright the light from `Uniforms::light_direction` is, a white surface facing it ends up white
+const LIGHT_INTENSITY: f32 = std::f32::consts::PI;
+// what's lighting everything from everywhere without a sky
+const AMBIENT: f32 = 0.03;
+// how much light non-metals reflect head on
+const DIELECTRIC_REFLECTANCE: f32 = 0.04;
+
+
+// A physically based surface in the metallic-roughness workflow, drawn in `RenderMode::Pbr`. Every parameter is a
+// constant, multiplied by its texture where there is one, like glTF does it:
+//   `base_color_map` is sRGB with the alpha, `metallic_roughness_map` has roughness in green and metallic in blue,
+//   `occlusion_map` has it in red and `emissive_map` is sRGB.
+// Without a base colour map the texture the backend draws with is used, so bricks can be physically based too.
+#[derive(Clone)]
+pub struct PbrMaterial {
+    pub base_color: Color,
+    pub metallic: f32, // 0 for dielectrics like plastic, 1 for metals, which tint their reflections
+    pub roughness: f32, // 0 is a mirror, 1 spreads the highlight all over
+    pub occlusion: f32, // how much of the ambient light reaches the surface
+    pub emissive: Color, // light of its own, black for none
+    pub base_color_map: Option<Image>,
+    pub metallic_roughness_map: Option<Image>,
+    pub occlusion_map: Option<Image>,
+    pub emissive_map: Option<Image>
+}
+impl PbrMaterial {
+    pub fn new(base_color: Color, metallic: f32, roughness: f32) -> Self {
+        return Self {
+            base_color,
+            metallic,
+            roughness,
+            occlusion: 1.0,
+            emissive: Color::BLACK,
+            base_color_map: None,
+            metallic_roughness_map: None,
+            occlusion_map: None,
+            emissive_map: None
+        };
+    }
+
+    // the parameters at `uv`, `texture` standing in for a missing base colour map
+    pub fn surface(&self, uv: Vec2, texture: Option<&Image>) -> Surface {
+        let sample = |map: &Option<Image>| map.as_ref().map(|map| map.sample(uv.x, uv.y));
+        let base = sample(&self.base_color_map).or(texture.map(|texture| texture.sample(uv.x, uv.y))).unwrap_or(Color::WHITE);
+        let metallic_roughness = sample(&self.metallic_roughness_map).unwrap_or(Color::WHITE);
+        let occlusion = sample(&self.occlusion_map).unwrap_or(Color::WHITE);
+        let emissive = sample(&self.emissive_map).unwrap_or(Color::WHITE);
+        let unit = |c: u8| c as f32 / 255.0;
+
+        return Surface {
+            base_color: to_linear(self.base_color) * to_linear(base),
+            alpha: (base.a as u32 * self.base_color.a as u32 / 255) as u8,
+            metallic: (self.metallic * unit(metallic_roughness.b)).clamp(0.0, 1.0),
+            // perfectly smooth surfaces have highlights too small to ever hit a pixel
+            roughness: (self.roughness * unit(metallic_roughness.g)).clamp(0.04, 1.0),
+            occlusion: (self.occlusion * unit(occlusion.r)).clamp(0.0, 1.0),
+            emissive: to_linear(self.emissive) * to_linear(emissive)
+        };
+    }
+}
+impl Default for PbrMaterial {
+    // plain white plastic
+    fn default() -> Self {
+        return Self::new(Color::WHITE, 0.0, 0.5);
+    }
+}
+
+
+// What `PbrMaterial` comes to at one point, colours in linear light
+pub struct Surface {
+    pub base_color: Vec3,
+    pub alpha: u8,
+    pub metallic: f32,
+    pub roughness: f32,
+    pub occlusion: f32,
+    pub emissive: Vec3
+}
+impl Surface {
+    // The light leaving the surface towards `to_camera`, both normalized. The light from `light_direction` goes through
+    // a Cook-Torrance BRDF: GGX for the microfacets, Smith-Schlick for their shadowing and Schlick's Fresnel. The sky,
+    // if there is one, lights it from everywhere, diffusely through its irradiance and specularly through what it
+    // reflects, blurred towards the irradiance as the surface gets rougher.
+    pub fn shade(&self, normal: Vec3, to_camera: Vec3, uniforms: &Uniforms) -> Vec3 {
+        let f0 = lerp(Vec3::new(DIELECTRIC_REFLECTANCE, DIELECTRIC_REFLECTANCE, DIELECTRIC_REFLECTANCE), self.base_color, self.metallic);
+        let diffuse_color = self.base_color * (1.0 - self.metallic);
+        let n_dot_v = normal.dot(&to_camera).max(1e-4);
+
+        let mut light = self.emissive;
+
+        let to_light = uniforms.light_direction;
+        let n_dot_l = normal.dot(&to_light);
+        if n_dot_l > 0.0 {
+            let halfway = (to_light + to_camera).normalized();
+            let n_dot_h = normal.dot(&halfway).max(0.0);
+            let v_dot_h = to_camera.dot(&halfway).max(0.0);
+
+            let alpha = self.roughness * self.roughness;
+            let d = alpha * alpha / (std::f32::consts::PI * (n_dot_h * n_dot_h * (alpha * alpha - 1.0) + 1.0).powi(2));
+            let k = (self.roughness + 1.0).powi(2) / 8.0;
+            let g = n_dot_v / (n_dot_v * (1.0 - k) + k) * n_dot_l / (n_dot_l * (1.0 - k) + k);
+            let fresnel = f0 + (Vec3::new(1.0, 1.0, 1.0) - f0) * (1.0 - v_dot_h).powi(5);
+
+            let specular = fresnel * (d * g / (4.0 * n_dot_v * n_dot_l));
+            let diffuse = (Vec3::new(1.0, 1.0, 1.0) - fresnel) * diffuse_color * (1.0 / std::f32::consts::PI);
+            light += (diffuse + specular) * (LIGHT_INTENSITY * n_dot_l);
+        }
+
+        let (irradiance, reflection) = match uniforms.environment {
+            Some(environment) => {
+                let irradiance = to_linear(environment.irradiance(normal));
+                let reflected = normal * (2.0 * normal.dot(&to_camera)) - to_camera;
+                let sharp = to_linear(environment.sample(reflected));
+                let blurred = to_linear(environment.irradiance(reflected));
+                (irradiance, lerp(sharp, blurred, self.roughness))
+            },
+            None => (Vec3::new(AMBIENT, AMBIENT, AMBIENT), Vec3::new(AMBIENT, AMBIENT, AMBIENT))
+        };
+        let (scale, bias) = environment_brdf(self.roughness, n_dot_v);
+        let ambient = diffuse_color * irradiance + reflection * (f0 * scale + Vec3::new(bias, bias, bias));
+        light += ambient * self.occlusion;
+
+        return light;
+    }
+}
+
+
+// How the whole hemisphere's reflection adds up for a roughness and angle, as a scale of F0 and a bias. Usually that's
+// a lookup table, this is Karis' curve fitted to it for mobile.
+fn environment_brdf(roughness: f32, n_dot_v: f32) -> (f32, f32) {
+    let r = [1.0 - roughness, roughness * -0.0275 + 0.0425, roughness * -0.572 + 1.04, roughness * 0.022 - 0.04];
+    let a004 = (r[0] * r[0]).min((-9.28 * n_dot_v).exp2()) * r[0] + r[1];
+    return (a004 * -1.04 + r[2], a004 * 1.04 + r[3]);
+}
+
+fn lerp(a: Vec3, b: Vec3, t: f32) -> Vec3 {
+    return a + (b - a) * t;
+}
+
+
+// Shades `Material::pbr` per pixel, or plain white plastic for materials without one. The normal map applies as with
+// every other shader and the fog goes on top. Highlights brighter than white stay that way in the HDR buffer, for
+// tone mapping and bloom. Corners are shaded as white plastic for SDL's renderer, which tints them.
+pub struct PbrShader;
+impl PbrShader {
+    fn shade(varyings: &Attributes, normal: Vec3, uniforms: &Uniforms, pbr: &PbrMaterial, texture: Option<&Image>) -> (Vec3, u8) {
+        let mut surface = pbr.surface(varyings.uv(), texture);
+        surface.occlusion *= varyings.occlusion();
+        let to_camera = (uniforms.camera_position - varyings.position()).normalized();
+        return (surface.shade(normal, to_camera, uniforms), surface.alpha);
+    }
+}
+impl Shader for PbrShader {
+    fn vertex(&self, vertex: &Vertex, uniforms: &Uniforms) -> Vertex {
+        let mut out = shader::transform(vertex, uniforms);
+        let normal = out.attributes.normal().normalized();
+        let (light, _) = Self::shade(&out.attributes, normal, uniforms, &PbrMaterial::default(), None);
+        let mut color = to_srgb(light);
+        color.a = vertex.attributes.color().a;
+        out.attributes.set_color(color);
+        return out;
+    }
+
+    fn fragment(&self, varyings: &Attributes, uniforms: &Uniforms, material: &Material, texture: Option<&Image>) -> Option<Color> {
+        let (light, alpha) = self.fragment_hdr(varyings, uniforms, material, texture)?;
+        let mut color = to_srgb(light);
+        color.a = alpha;
+        return Some(color);
+    }
+
+    fn fragment_hdr(&self, varyings: &Attributes, uniforms: &Uniforms, material: &Material, texture: Option<&Image>) -> Option<(Vec3, u8)> {
+        let normal = shader::surface_normal(varyings, material);
+        let (light, alpha) = match &material.pbr {
+            Some(pbr) => Self::shade(varyings, normal, uniforms, pbr, texture),
+            None => Self::shade(varyings, normal, uniforms, &PbrMaterial::default(), texture)
+        };
+        return Some((uniforms.fog.apply_light(light, varyings.depth()), alpha));
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::backend::RenderBackend;
+    use crate::mesh::Triangle;
+    use crate::render::{RenderMode, Renderer};
+    use crate::scene::Scene;
+    use crate::software::SoftwareBackend;
+    use crate::vertex::Attribute;
+
+    #[test]
+    fn only_the_ambient_light_reaches_surfaces_facing_away() {
+        let scene = Scene::new();
+        let uniforms = Renderer::uniforms(&scene, &scene.camera, 16, 16);
+        let away = uniforms.light_direction * -1.0;
+        let mut surface = PbrMaterial::default().surface(Vec2::new(0.0, 0.0), None);
+        assert!(surface.shade(away, away, &uniforms).x > 0.0);
+
+        surface.occlusion = 0.0;
+        assert_eq!(surface.shade(away, away, &uniforms).x, 0.0);
+    }
+
+    #[test]
+    fn metals_tint_their_highlights() {
+        let scene = Scene::new();
+        let uniforms = Renderer::uniforms(&scene, &scene.camera, 16, 16);
+        let head_on = uniforms.light_direction;
+        let highlight = |metallic: f32| PbrMaterial::new(Color::RGB(255, 180, 50), metallic, 0.2).surface(Vec2::new(0.0, 0.0), None).shade(head_on, head_on, &uniforms);
+
+        // plastic has a white highlight on top of its colour, metal only its colour
+        let (plastic, metal) = (highlight(0.0), highlight(1.0));
+        assert!(plastic.z > 1.0);
+        assert!(metal.z < metal.x * 0.1);
+    }
+
+    #[test]
+    fn emissive_materials_glow_in_the_dark() {
+        let scene = Scene::new();
+        let uniforms = Renderer::uniforms(&scene, &scene.camera, 16, 16);
+        let glow = Color::RGB(255, 128, 0);
+        let pbr = PbrMaterial { emissive: glow, occlusion: 0.0, ..PbrMaterial::new(Color::BLACK, 0.0, 1.0) };
+
+        // facing away from the light, with none of the ambient light reaching it
+        let mut triangle = Triangle::new([Vec3::new(0.0, 0.0, 0.5), Vec3::new(16.0, 0.0, 0.5), Vec3::new(0.0, 16.0, 0.5)], [Vec2::new(0.0, 0.0); 3], Color::WHITE);
+        for vertex in &mut triangle.vertices {
+            vertex.attributes.set_normal(uniforms.light_direction * -1.0);
+            vertex.attributes.set(Attribute::Position, &[0.0, 0.0, 5.0]);
+        }
+
+        let mut backend = SoftwareBackend::new(16, 16, None);
+        backend.begin_frame(Color::BLACK);
+        backend.draw_triangles(&[triangle], RenderMode::Pbr, &PbrShader, &uniforms, &[Material::physical(pbr)]);
+        let light = backend.hdr[4 * 16 + 4];
+        let expected = to_linear(glow);
+        assert!((light.x - expected.x).abs() < 1e-4 && (light.y - expected.y).abs() < 1e-4 && light.z == 0.0);
+    }
+}
@@ -0,0 +1,208 @@
+use std::any::Any;
+use std::panic::{catch_unwind, resume_unwind, AssertUnwindSafe};
+use std::sync::{Arc, Condvar, Mutex, MutexGuard, OnceLock};
+use std::thread::JoinHandle;
+
+
+// A job's `Fn(chunk)` with its lifetime taken off, so the workers can hold on to it. `WorkerPool::run` doesn't return
+// before every chunk is done, which is what keeps the closure alive for as long as it's used.
+#[derive(Copy, Clone)]
+struct Job(*const (dyn Fn(usize) + Sync));
+unsafe impl Send for Job {}
+
+struct State {
+    job: Option<Job>, // `None` while the pool is free
+    next: usize, // the first chunk nobody has taken yet
+    chunks: usize,
+    unfinished: usize,
+    panic: Option<Box<dyn Any + Send>>, // from the first chunk that panicked
+    shutdown: bool
+}
+
+struct Shared {
+    state: Mutex<State>,
+    work: Condvar, // a job was posted or the pool is shutting down
+    done: Condvar // the last chunk of a job finished
+}
+impl Shared {
+    fn lock(&self) -> MutexGuard<'_, State> {
+        return self.state.lock().expect("Failed to lock the worker pool");
+    }
+
+    // takes chunks of the current job until there are none left, the lock is let go while one runs
+    fn work_on<'a>(&'a self, mut state: MutexGuard<'a, State>) -> MutexGuard<'a, State> {
+        while let Some(job) = state.job.filter(|_| state.next < state.chunks) {
+            let chunk = state.next;
+            state.next += 1;
+            drop(state);
+
+            let result = catch_unwind(AssertUnwindSafe(|| unsafe { (*job.0)(chunk) }));
+
+            state = self.lock();
+            if let Err(panic) = result {
+                state.panic.get_or_insert(panic);
+            }
+            state.unfinished -= 1;
+            if state.unfinished == 0 {
+                self.done.notify_all();
+            }
+        }
+        return state;
+    }
+}
+
+
+// Threads that stay around to run the chunks of one job at a time, so a frame doesn't start and join threads for
+// every pass. They're started the first time there's more than one chunk to run, and the thread that posts a job
+// runs chunks of it too. A job posted while another is running, from one of its chunks or from another thread, runs
+// on the thread that posted it instead of waiting.
+pub struct WorkerPool {
+    threads: usize, // counting the one that posts the jobs
+    shared: Arc<Shared>,
+    workers: OnceLock<Vec<JoinHandle<()>>>
+}
+impl WorkerPool {
+    pub fn new(threads: usize) -> Self {
+        let state = State { job: None, next: 0, chunks: 0, unfinished: 0, panic: None, shutdown: false };
+        return Self {
+            threads: threads.max(1),
+            shared: Arc::new(Shared { state: Mutex::new(state), work: Condvar::new(), done: Condvar::new() }),
+            workers: OnceLock::new()
+        };
+    }
+
+    pub fn threads(&self) -> usize {
+        return self.threads;
+    }
+
+    // `f` for each consecutive `chunk_size` long chunk of `items` with the chunk's number, changing them in place
+    pub fn for_each_chunk_mut<T: Send, F: Fn(usize, &mut [T]) + Sync>(&self, items: &mut [T], chunk_size: usize, f: F) {
+        let chunk_size = chunk_size.max(1);
+        let (len, items) = (items.len(), ItemsPtr(items.as_mut_ptr()));
+        self.run(len.div_ceil(chunk_size), |chunk| {
+            let start = chunk * chunk_size;
+            // every chunk is run once, so no two of them get the same items
+            f(chunk, unsafe { std::slice::from_raw_parts_mut(items.add(start), chunk_size.min(len - start)) });
+        });
+    }
+
+    // `f` for every chunk number below `chunks`, a panic in any of them is passed on once they're all done
+    fn run<F: Fn(usize) + Sync>(&self, chunks: usize, f: F) {
+        if chunks <= 1 || self.threads == 1 {
+            (0..chunks).for_each(f);
+            return;
+        }
+
+        self.workers.get_or_init(|| (1..self.threads).map(|i| {
+            let shared = Arc::clone(&self.shared);
+            return std::thread::Builder::new()
+                .name(format!("worker {}", i))
+                .spawn(move || work(&shared))
+                .expect("Failed to start a worker thread");
+        }).collect());
+
+        let mut state = self.shared.lock();
+        if state.job.is_some() {
+            drop(state);
+            (0..chunks).for_each(f);
+            return;
+        }
+
+        let f: &(dyn Fn(usize) + Sync) = &f;
+        // the lifetime only goes away for as long as this call waits below
+        let f: *const (dyn Fn(usize) + Sync + '_) = f;
+        state.job = Some(Job(unsafe { std::mem::transmute::<*const (dyn Fn(usize) + Sync + '_), *const (dyn Fn(usize) + Sync)>(f) }));
+        state.next = 0;
+        state.chunks = chunks;
+        state.unfinished = chunks;
+        self.shared.work.notify_all();
+
+        state = self.shared.work_on(state);
+        while state.unfinished > 0 {
+            state = self.shared.done.wait(state).expect("Failed to wait for the worker pool");
+        }
+        state.job = None;
+        let panic = state.panic.take();
+        drop(state);
+
+        if let Some(panic) = panic {
+            resume_unwind(panic);
+        }
+    }
+}
+impl Drop for WorkerPool {
+    fn drop(&mut self) {
+        let Some(workers) = self.workers.take() else {
+            return;
+        };
+        self.shared.lock().shutdown = true;
+        self.shared.work.notify_all();
+        for worker in workers {
+            worker.join().ok();
+        }
+    }
+}
+
+
+// what a worker thread does until the pool is dropped
+fn work(shared: &Shared) {
+    let mut state = shared.lock();
+    while !state.shutdown {
+        state = shared.work_on(state);
+        if !state.shutdown {
+            state = shared.work.wait(state).expect("Failed to wait for the worker pool");
+        }
+    }
+}
+
+
+// The start of the items `for_each_chunk_mut` hands out in chunks. It's only read through `add`, so closures take
+// all of it rather than just the pointer, which isn't `Sync`.
+struct ItemsPtr<T>(*mut T);
+unsafe impl<T: Send> Sync for ItemsPtr<T> {}
+impl<T> ItemsPtr<T> {
+    unsafe fn add(&self, offset: usize) -> *mut T {
+        return unsafe { self.0.add(offset) };
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn every_chunk_runs_once() {
+        let pool = WorkerPool::new(4);
+        let mut items = vec![0; 1000];
+        for _ in 0..3 {
+            pool.for_each_chunk_mut(&mut items, 64, |chunk, items| {
+                for (i, item) in items.iter_mut().enumerate() {
+                    *item += chunk * 64 + i;
+                }
+            });
+        }
+        assert!(items.iter().enumerate().all(|(i, item)| *item == i * 3));
+    }
+
+    #[test]
+    fn nested_jobs_run_on_the_calling_thread() {
+        let pool = WorkerPool::new(4);
+        let mut rows = vec![vec![0; 100]; 8];
+        pool.for_each_chunk_mut(&mut rows, 1, |_, row| {
+            pool.for_each_chunk_mut(&mut row[0], 10, |_, items| items.fill(1));
+        });
+        assert!(rows.iter().flatten().all(|item| *item == 1));
+    }
+
+    #[test]
+    fn panics_reach_the_caller() {
+        let pool = WorkerPool::new(4);
+        let mut items = vec![0; 100];
+        let result = catch_unwind(AssertUnwindSafe(|| pool.for_each_chunk_mut(&mut items, 10, |chunk, _| assert!(chunk != 3))));
+        assert!(result.is_err());
+        // and the pool is still there for the next job
+        pool.for_each_chunk_mut(&mut items, 10, |_, items| items.fill(1));
+        assert!(items.iter().all(|item| *item == 1));
+    }
+}
@@ -0,0 +1,403 @@
+use sdl2::pixels::Color;
+
+use crate::backend::Image;
+use crate::math::Vec3;
+use crate::render::Parallelism;
+use crate::shader::to_srgb;
+
+
+// pixels per thread at least for the full screen passes
+const MIN_PIXELS_PER_THREAD: usize = 16 * 1024;
+// bloom is blurred at a quarter of the resolution, which spreads it four times as far for the same work
+const BLOOM_SCALE: u32 = 4;
+// a gaussian, from the middle out
+const BLOOM_WEIGHTS: [f32; 5] = [0.227027, 0.1945946, 0.1216216, 0.054054, 0.016216];
+// how many pixels FXAA follows an edge each way to find where it ends
+const FXAA_SEARCH_STEPS: i32 = 12;
+
+
+// How tone mapping squeezes light of any brightness into 0 to 1
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+pub enum ToneMapping {
+    Reinhard,
+    Aces // Narkowicz' fit of the ACES filmic curve, with more contrast
+}
+impl ToneMapping {
+    pub fn apply(self, light: Vec3) -> Vec3 {
+        let channel = |c: f32| -> f32 {
+            let c = c.max(0.0);
+            return match self {
+                ToneMapping::Reinhard => c / (1.0 + c),
+                ToneMapping::Aces => (c * (2.51 * c + 0.03) / (c * (2.43 * c + 0.59) + 0.14)).clamp(0.0, 1.0)
+            };
+        };
+        return Vec3::new(channel(light.x), channel(light.y), channel(light.z));
+    }
+}
+impl std::str::FromStr for ToneMapping {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        return match s {
+            "reinhard" => Ok(ToneMapping::Reinhard),
+            "aces" => Ok(ToneMapping::Aces),
+            _ => Err(format!("unknown tone mapping '{}'", s))
+        };
+    }
+}
+impl std::fmt::Display for ToneMapping {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        return f.pad(match self {
+            ToneMapping::Reinhard => "reinhard",
+            ToneMapping::Aces => "aces"
+        });
+    }
+}
+
+
+// The full screen passes that turn the HDR buffer into the image on screen, each switched on and off on its own. They
+// run in this order: bloom spreads light brighter than `bloom_threshold` around it, `tonemap` scales the light by
+// `exposure` and brings it into 0 to 1, `gamma` encodes it as sRGB, then `fxaa` smooths jagged edges and `vignette`
+// darkens the corners of the finished image. Only `gamma` is on to begin with, which leaves colours as shaders gave them.
+#[derive(Copy, Clone)]
+pub struct PostProcess {
+    pub bloom: bool,
+    pub bloom_threshold: f32,
+    pub bloom_strength: f32,
+    pub tonemap: bool,
+    pub tone_mapping: ToneMapping,
+    pub exposure: f32,
+    pub gamma: bool,
+    pub fxaa: bool,
+    pub vignette: bool,
+    pub vignette_strength: f32 // how dark the corners get, from 0 to 1
+}
+impl PostProcess {
+    pub const PASSES: [&'static str; 5] = ["bloom", "tonemap", "gamma", "fxaa", "vignette"];
+
+    pub fn new() -> Self {
+        return Self {
+            bloom: false,
+            bloom_threshold: 1.0,
+            bloom_strength: 0.5,
+            tonemap: false,
+            tone_mapping: ToneMapping::Aces,
+            exposure: 1.0,
+            gamma: true,
+            fxaa: false,
+            vignette: false,
+            vignette_strength: 0.5
+        };
+    }
+
+    // whether the pass called `name`, one of `PASSES`, is on
+    pub fn pass_mut(&mut self, name: &str) -> Option<&mut bool> {
+        return match name {
+            "bloom" => Some(&mut self.bloom),
+            "tonemap" => Some(&mut self.tonemap),
+            "gamma" => Some(&mut self.gamma),
+            "fxaa" => Some(&mut self.fxaa),
+            "vignette" => Some(&mut self.vignette),
+            _ => None
+        };
+    }
+}
+impl Default for PostProcess {
+    fn default() -> Self {
+        return Self::new();
+    }
+}
+
+
+// Runs `PostProcess` for `SoftwareBackend`. The buffers in between are kept from frame to frame.
+pub struct PostProcessor {
+    bloom: Vec<Vec3>, // `BLOOM_SCALE` times smaller than the screen
+    blur: Vec<Vec3>,
+    pixels: Vec<Color>, // what FXAA reads while it writes the image
+    luma: Vec<f32>
+}
+impl PostProcessor {
+    pub fn new() -> Self {
+        return Self { bloom: Vec::new(), blur: Vec::new(), pixels: Vec::new(), luma: Vec::new() };
+    }
+
+    // `hdr` is linear light for every pixel of `image`, which gets the finished frame
+    pub fn run(&mut self, post: &PostProcess, hdr: &[Vec3], image: &mut Image, parallelism: &Parallelism) {
+        let (width, height) = (image.width, image.height);
+        if post.bloom {
+            self.bloom(post, hdr, width, height);
+        }
+
+        let bloom = if post.bloom { Some(self.bloom.as_slice()) } else { None };
+        parallelism.for_each_chunk_indexed_mut(&mut image.pixels, MIN_PIXELS_PER_THREAD, |start, pixels| {
+            for (i, pixel) in pixels.iter_mut().enumerate() {
+                let i = start + i;
+                let mut light = hdr[i];
+                if let Some(bloom) = bloom {
+                    let (x, y) = ((i as u32 % width) as f32, (i as u32 / width) as f32);
+                    let (bloom_width, bloom_height) = (width.div_ceil(BLOOM_SCALE), height.div_ceil(BLOOM_SCALE));
+                    let scale = BLOOM_SCALE as f32;
+                    light += sample(bloom, bloom_width, bloom_height, (x + 0.5) / scale - 0.5, (y + 0.5) / scale - 0.5) * post.bloom_strength;
+                }
+                if post.tonemap {
+                    light = post.tone_mapping.apply(light * post.exposure);
+                }
+                *pixel = if post.gamma { to_srgb(light) } else { unit_color(light) };
+            }
+        });
+
+        if post.fxaa {
+            self.fxaa(image, parallelism);
+        }
+        if post.vignette {
+            vignette(image, post.vignette_strength, parallelism);
+        }
+    }
+
+    // what's brighter than the threshold, made smaller and blurred both ways
+    fn bloom(&mut self, post: &PostProcess, hdr: &[Vec3], width: u32, height: u32) {
+        let (bloom_width, bloom_height) = (width.div_ceil(BLOOM_SCALE), height.div_ceil(BLOOM_SCALE));
+        self.bloom.clear();
+        for y in 0..bloom_height {
+            for x in 0..bloom_width {
+                let mut sum = Vec3::new(0.0, 0.0, 0.0);
+                let (xs, ys) = (x * BLOOM_SCALE..((x + 1) * BLOOM_SCALE).min(width), y * BLOOM_SCALE..((y + 1) * BLOOM_SCALE).min(height));
+                let count = (xs.len() * ys.len()) as f32;
+                for sy in ys {
+                    for sx in xs.clone() {
+                        let light = hdr[(sy * width + sx) as usize];
+                        let over = |c: f32| (c - post.bloom_threshold).max(0.0);
+                        sum += Vec3::new(over(light.x), over(light.y), over(light.z));
+                    }
+                }
+                self.bloom.push(sum * (1.0 / count));
+            }
+        }
+
+        self.blur.clear();
+        self.blur.resize(self.bloom.len(), Vec3::new(0.0, 0.0, 0.0));
+        blur(&self.bloom, &mut self.blur, bloom_width, bloom_height, (1, 0));
+        blur(&self.blur, &mut self.bloom, bloom_width, bloom_height, (0, 1));
+    }
+
+    // FXAA 3.11 the simple way: finds which way an edge runs through a pixel, follows it both ways to where it ends and
+    // blends the pixel with its neighbour across the edge by how close it is to the nearer end
+    fn fxaa(&mut self, image: &mut Image, parallelism: &Parallelism) {
+        let (width, height) = (image.width as i64, image.height as i64);
+        self.pixels.clear();
+        self.pixels.extend_from_slice(&image.pixels);
+        self.luma.clear();
+        self.luma.extend(image.pixels.iter().map(|c| (c.r as f32 * 0.299 + c.g as f32 * 0.587 + c.b as f32 * 0.114) / 255.0));
+        let (colors, luma) = (&self.pixels, &self.luma);
+
+        let at = |x: i64, y: i64| (y.clamp(0, height - 1) * width + x.clamp(0, width - 1)) as usize;
+        let l = |x: i64, y: i64| luma[at(x, y)];
+        // between pixel centres, with whole numbers at the centres
+        let luma_between = |x: f32, y: f32| -> f32 {
+            let (x0, y0) = (x.floor(), y.floor());
+            let (fx, fy) = (x - x0, y - y0);
+            let (x0, y0) = (x0 as i64, y0 as i64);
+            let top = l(x0, y0) + (l(x0 + 1, y0) - l(x0, y0)) * fx;
+            let bottom = l(x0, y0 + 1) + (l(x0 + 1, y0 + 1) - l(x0, y0 + 1)) * fx;
+            return top + (bottom - top) * fy;
+        };
+
+        parallelism.for_each_chunk_indexed_mut(&mut image.pixels, MIN_PIXELS_PER_THREAD, |start, pixels| {
+            for (i, pixel) in pixels.iter_mut().enumerate() {
+                let (x, y) = ((start + i) as i64 % width, (start + i) as i64 / width);
+                let (m, n, s, e, w) = (l(x, y), l(x, y - 1), l(x, y + 1), l(x + 1, y), l(x - 1, y));
+                let max = m.max(n).max(s).max(e).max(w);
+                let range = max - m.min(n).min(s).min(e).min(w);
+                if range < (max * 0.125).max(0.0312) {
+                    continue;
+                }
+
+                let (nw, ne, sw, se) = (l(x - 1, y - 1), l(x + 1, y - 1), l(x - 1, y + 1), l(x + 1, y + 1));
+                let horizontal = (n + s - 2.0 * m).abs() * 2.0 + (ne + se - 2.0 * e).abs() + (nw + sw - 2.0 * w).abs()
+                    >= (e + w - 2.0 * m).abs() * 2.0 + (ne + nw - 2.0 * n).abs() + (se + sw - 2.0 * s).abs();
+
+                // the side of the edge that's more different, and halfway towards it
+                let (before, after) = if horizontal { (n, s) } else { (w, e) };
+                let towards_before = (before - m).abs() >= (after - m).abs();
+                let (across, step) = if towards_before { (before, -1.0) } else { (after, 1.0) };
+                let gradient = 0.25 * (before - m).abs().max((after - m).abs());
+                let local_average = 0.5 * (across + m);
+                let (along_x, along_y) = if horizontal { (1.0, 0.0) } else { (0.0, 1.0) };
+                let (edge_x, edge_y) = if horizontal { (x as f32, y as f32 + step * 0.5) } else { (x as f32 + step * 0.5, y as f32) };
+
+                let (mut distance_back, mut distance_on) = (FXAA_SEARCH_STEPS, FXAA_SEARCH_STEPS);
+                let (mut end_back, mut end_on) = (0.0, 0.0);
+                let (mut done_back, mut done_on) = (false, false);
+                for j in 1..=FXAA_SEARCH_STEPS {
+                    let offset = j as f32;
+                    if !done_back {
+                        end_back = luma_between(edge_x - along_x * offset, edge_y - along_y * offset) - local_average;
+                        done_back = end_back.abs() >= gradient;
+                        distance_back = j;
+                    }
+                    if !done_on {
+                        end_on = luma_between(edge_x + along_x * offset, edge_y + along_y * offset) - local_average;
+                        done_on = end_on.abs() >= gradient;
+                        distance_on = j;
+                    }
+                    if done_back && done_on {
+                        break;
+                    }
+                }
+
+                // only the end where the edge turns away from this pixel's side pulls on it
+                let (distance, end) = if distance_back < distance_on { (distance_back, end_back) } else { (distance_on, end_on) };
+                let edge_offset = if (m - local_average < 0.0) != (end < 0.0) {
+                    0.5 - distance as f32 / (distance_back + distance_on) as f32
+                } else {
+                    0.0
+                };
+
+                // single pixels that stand out get blended however the edge looks
+                let average = (2.0 * (n + s + e + w) + nw + ne + sw + se) / 12.0;
+                let subpixel = ((average - m).abs() / range).clamp(0.0, 1.0);
+                let subpixel = (-2.0 * subpixel + 3.0) * subpixel * subpixel;
+                let offset = edge_offset.max(subpixel * subpixel * 0.75) * step;
+
+                let (near, far) = (colors[at(x, y)], if horizontal { colors[at(x, y + step as i64)] } else { colors[at(x + step as i64, y)] });
+                *pixel = lerp_color(near, far, offset.abs());
+            }
+        });
+    }
+}
+impl Default for PostProcessor {
+    fn default() -> Self {
+        return Self::new();
+    }
+}
+
+
+// one way of a separable gaussian, `direction` is (1, 0) or (0, 1)
+fn blur(from: &[Vec3], to: &mut [Vec3], width: u32, height: u32, direction: (i64, i64)) {
+    let (width, height) = (width as i64, height as i64);
+    for y in 0..height {
+        for x in 0..width {
+            let mut sum = from[(y * width + x) as usize] * BLOOM_WEIGHTS[0];
+            for (k, weight) in BLOOM_WEIGHTS.iter().enumerate().skip(1) {
+                let k = k as i64;
+                for side in [-k, k] {
+                    let (sx, sy) = ((x + direction.0 * side).clamp(0, width - 1), (y + direction.1 * side).clamp(0, height - 1));
+                    sum += from[(sy * width + sx) as usize] * *weight;
+                }
+            }
+            to[(y * width + x) as usize] = sum;
+        }
+    }
+}
+
+// bilinear, with whole numbers at pixel centres and the edges repeated
+fn sample(pixels: &[Vec3], width: u32, height: u32, x: f32, y: f32) -> Vec3 {
+    let (x, y) = (x.clamp(0.0, (width - 1) as f32), y.clamp(0.0, (height - 1) as f32));
+    let (x0, y0) = (x.floor() as u32, y.floor() as u32);
+    let (x1, y1) = ((x0 + 1).min(width - 1), (y0 + 1).min(height - 1));
+    let (fx, fy) = (x - x0 as f32, y - y0 as f32);
+    let at = |x: u32, y: u32| pixels[(y * width + x) as usize];
+    let top = at(x0, y0) + (at(x1, y0) - at(x0, y0)) * fx;
+    let bottom = at(x0, y1) + (at(x1, y1) - at(x0, y1)) * fx;
+    return top + (bottom - top) * fy;
+}
+
+fn lerp_color(a: Color, b: Color, t: f32) -> Color {
+    let channel = |a: u8, b: u8| (a as f32 + (b as f32 - a as f32) * t).round() as u8;
+    return Color::RGB(channel(a.r, b.r), channel(a.g, b.g), channel(a.b, b.b));
+}
+
+// light written as it is, without gamma
+fn unit_color(light: Vec3) -> Color {
+    let channel = |c: f32| (c.clamp(0.0, 1.0) * 255.0).round() as u8;
+    return Color::RGB(channel(light.x), channel(light.y), channel(light.z));
+}
+
+// darker towards the corners, by the square of the distance from the middle
+fn vignette(image: &mut Image, strength: f32, parallelism: &Parallelism) {
+    let (width, height) = (image.width as usize, image.height as usize);
+    parallelism.for_each_chunk_indexed_mut(&mut image.pixels, MIN_PIXELS_PER_THREAD, |start, pixels| {
+        for (i, pixel) in pixels.iter_mut().enumerate() {
+            let (x, y) = ((start + i) % width, (start + i) / width);
+            let dx = (x as f32 + 0.5) / width as f32 * 2.0 - 1.0;
+            let dy = (y as f32 + 0.5) / height as f32 * 2.0 - 1.0;
+            let factor = 1.0 - strength.clamp(0.0, 1.0) * (dx * dx + dy * dy) * 0.5;
+            let channel = |c: u8| (c as f32 * factor).round() as u8;
+            *pixel = Color::RGB(channel(pixel.r), channel(pixel.g), channel(pixel.b));
+        }
+    });
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::backend::RenderBackend;
+    use crate::shader::to_linear;
+    use crate::software::SoftwareBackend;
+
+    const SIZE: u32 = 32;
+
+    // the image `post` makes of the HDR buffer `light` fills in for every pixel
+    fn finish(post: &PostProcess, light: impl Fn(u32, u32) -> Vec3) -> Image {
+        let mut backend = SoftwareBackend::new(SIZE, SIZE, None);
+        backend.begin_frame(Color::BLACK);
+        for (i, pixel) in backend.hdr.iter_mut().enumerate() {
+            *pixel = light(i as u32 % SIZE, i as u32 / SIZE);
+        }
+        backend.end_frame(post);
+        return backend.framebuffer;
+    }
+
+    fn only(pass: &str) -> PostProcess {
+        let mut post = PostProcess::new();
+        for name in PostProcess::PASSES {
+            *post.pass_mut(name).expect("Failed to find a post pass") = name == pass;
+        }
+        return post;
+    }
+
+    #[test]
+    fn gamma_gives_back_the_colours_shaders_returned() {
+        let colors = [Color::RGB(0, 0, 0), Color::RGB(12, 100, 200), Color::RGB(255, 128, 1)];
+        let image = finish(&PostProcess::new(), |x, _| to_linear(colors[x as usize % colors.len()]));
+        assert!((0..SIZE).all(|x| image.get(x, 0) == colors[x as usize % colors.len()]));
+    }
+
+    #[test]
+    fn tone_mapping_brings_any_light_below_white() {
+        assert_eq!(ToneMapping::Reinhard.apply(Vec3::new(1.0, 0.0, 3.0)).x, 0.5);
+        // without it everything brighter than white is white
+        let ramp = |x: u32, _: u32| Vec3::new(x as f32 * 0.25, 0.0, 0.0);
+        assert_eq!(finish(&only("gamma"), ramp).get(SIZE / 2, 0), finish(&only("gamma"), ramp).get(SIZE - 1, 0));
+
+        let mut post = only("tonemap");
+        post.tone_mapping = ToneMapping::Reinhard;
+        let image = finish(&post, ramp);
+        assert!((1..SIZE).all(|x| image.get(x, 0).r > image.get(x - 1, 0).r));
+    }
+
+    #[test]
+    fn bloom_spreads_bright_light_around() {
+        let spot = |x: u32, y: u32| if (x, y) == (SIZE / 2, SIZE / 2) { Vec3::new(50.0, 50.0, 50.0) } else { Vec3::new(0.0, 0.0, 0.0) };
+        assert_eq!(finish(&only("gamma"), spot).get(SIZE / 2 + 4, SIZE / 2), Color::BLACK);
+        assert!(finish(&only("bloom"), spot).get(SIZE / 2 + 4, SIZE / 2).r > 0);
+    }
+
+    #[test]
+    fn fxaa_smooths_steps_and_leaves_flat_colour_alone() {
+        let flat = finish(&only("fxaa"), |_, _| Vec3::new(0.5, 0.5, 0.5));
+        assert!(flat.pixels.iter().all(|color| *color == flat.pixels[0]));
+
+        // a staircase, two pixels per step
+        let steps = finish(&only("fxaa"), |x, y| if x / 2 > y / 2 { Vec3::new(1.0, 1.0, 1.0) } else { Vec3::new(0.0, 0.0, 0.0) });
+        assert!(steps.pixels.iter().any(|color| color.r > 0 && color.r < 255));
+    }
+
+    #[test]
+    fn vignettes_darken_the_corners() {
+        let image = finish(&only("vignette"), |_, _| Vec3::new(0.8, 0.8, 0.8));
+        assert!(image.get(0, 0).r < image.get(SIZE / 2, SIZE / 2).r);
+        assert_eq!(image.get(0, 0), image.get(SIZE - 1, SIZE - 1));
+    }
+}
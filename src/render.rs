@@ -0,0 +1,308 @@
+use std::sync::Arc;
+use sdl2::pixels::Color;
+
+use crate::backend::{Image, RenderBackend};
+use crate::camera::Camera;
+use crate::clip::Clipper;
+use crate::math::{Matrix4x4, Vec3};
+use crate::mesh::{Mesh, PipelineBuffers, Triangle};
+use crate::pbr::PbrShader;
+use crate::pool::WorkerPool;
+use crate::scene::Scene;
+use crate::shader::{Shader, Uniforms};
+use crate::stats::{RenderStats, Stage};
+
+
+pub const RESOLUTION: [u32; 2] = [1200, 700];
+pub const LIGHT_DIRECTION: Vec3 = Vec3 { x: 0.0, y: 1.0, z: -1.0 };
+// below this, splitting triangle work between threads costs more than it saves
+pub const MIN_TRIANGLES_PER_THREAD: usize = 256;
+
+// How many threads the pipeline may split its work over. Work is cut into consecutive chunks whose results are
+// put back together in order, so the output is the same for any number of threads. The chunks run on a `WorkerPool`
+// that clones share, so a renderer and its backend can use the same threads.
+#[derive(Clone)]
+pub struct Parallelism {
+    pool: Arc<WorkerPool>
+}
+impl Parallelism {
+    pub fn new(threads: usize) -> Self {
+        return Self { pool: Arc::new(WorkerPool::new(threads)) };
+    }
+
+    pub fn serial() -> Self {
+        return Self::new(1);
+    }
+
+    // one thread per core
+    pub fn available() -> Self {
+        return Self::new(std::thread::available_parallelism().map_or(1, |n| n.get()));
+    }
+
+    pub fn threads(&self) -> usize {
+        return self.pool.threads();
+    }
+
+    fn chunk_size(&self, items: usize, min_chunk: usize) -> usize {
+        return items.div_ceil(self.threads()).max(min_chunk).max(1);
+    }
+
+    // `f` for each consecutive chunk of `items` together with a buffer of its own, on the pool's threads. Chunks are
+    // at least `min_chunk` long, so small amounts of work stay on the calling thread. `buffers` grows to one per chunk
+    // and stays with the caller, so what they hold on to gets reused next time. Returns the number of chunks, whose
+    // results are in that many buffers at the start of `buffers`.
+    pub fn zip_chunks<T: Sync, B: Default + Send, F: Fn(&[T], &mut B) + Sync>(&self, items: &[T], min_chunk: usize, buffers: &mut Vec<B>, f: F) -> usize {
+        let chunk_size = self.chunk_size(items.len(), min_chunk);
+        let chunks = items.len().div_ceil(chunk_size).max(1);
+        if buffers.len() < chunks {
+            buffers.resize_with(chunks, B::default);
+        }
+
+        self.pool.for_each_chunk_mut(&mut buffers[..chunks], 1, |chunk, buffer| {
+            let start = chunk * chunk_size;
+            f(&items[start..(start + chunk_size).min(items.len())], &mut buffer[0]);
+        });
+        return chunks;
+    }
+
+    // `f` for each consecutive chunk of `items`, on the pool's threads, changing them in place
+    pub fn for_each_chunk_mut<T: Send, F: Fn(&mut [T]) + Sync>(&self, items: &mut [T], min_chunk: usize, f: F) {
+        self.for_each_chunk_indexed_mut(items, min_chunk, |_, chunk| f(chunk));
+    }
+
+    // like `for_each_chunk_mut`, `f` also gets the index of the chunk's first item
+    pub fn for_each_chunk_indexed_mut<T: Send, F: Fn(usize, &mut [T]) + Sync>(&self, items: &mut [T], min_chunk: usize, f: F) {
+        let chunk_size = self.chunk_size(items.len(), min_chunk);
+        if chunk_size >= items.len() {
+            f(0, items);
+            return;
+        }
+
+        self.pool.for_each_chunk_mut(items, chunk_size, |chunk, items| f(chunk * chunk_size, items));
+    }
+}
+
+
+#[derive(Copy, Clone, Eq, PartialEq)]
+pub enum RenderMode {
+    Outline,
+    Filled,
+    Textured,
+    Pbr // physically based, see `PbrMaterial`
+}
+impl std::fmt::Display for RenderMode {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        return f.pad(match self {
+            RenderMode::Outline => "outline",
+            RenderMode::Filled => "filled",
+            RenderMode::Textured => "textured",
+            RenderMode::Pbr => "pbr"
+        });
+    }
+}
+
+
+// How many samples `SoftwareBackend` takes of every pixel to smooth the edges of triangles. MSAA tests coverage and
+// depth for every sample but only shades each pixel once, SSAA shades every sample, which is the same as drawing at a
+// higher resolution and scaling it down: `Ssaa4` is twice the width and height.
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+pub enum Antialiasing {
+    Off,
+    Msaa2,
+    Msaa4,
+    Ssaa2,
+    Ssaa4
+}
+impl Antialiasing {
+    pub fn samples(self) -> usize {
+        return match self {
+            Antialiasing::Off => 1,
+            Antialiasing::Msaa2 | Antialiasing::Ssaa2 => 2,
+            Antialiasing::Msaa4 | Antialiasing::Ssaa4 => 4
+        };
+    }
+
+    // Where the samples are, from the top left corner of the pixel. MSAA uses the usual rotated grid, SSAA a plain one
+    // like a bigger image would have.
+    pub fn sample_positions(self) -> &'static [(f32, f32)] {
+        return match self {
+            Antialiasing::Off => &[(0.5, 0.5)],
+            Antialiasing::Msaa2 | Antialiasing::Ssaa2 => &[(0.75, 0.75), (0.25, 0.25)],
+            Antialiasing::Msaa4 => &[(0.375, 0.125), (0.875, 0.375), (0.125, 0.625), (0.625, 0.875)],
+            Antialiasing::Ssaa4 => &[(0.25, 0.25), (0.75, 0.25), (0.25, 0.75), (0.75, 0.75)]
+        };
+    }
+
+    pub fn shades_every_sample(self) -> bool {
+        return !matches!(self, Antialiasing::Msaa2 | Antialiasing::Msaa4);
+    }
+
+    // the one after this, for cycling through them with a key
+    pub fn next(self) -> Self {
+        return match self {
+            Antialiasing::Off => Antialiasing::Msaa2,
+            Antialiasing::Msaa2 => Antialiasing::Msaa4,
+            Antialiasing::Msaa4 => Antialiasing::Ssaa2,
+            Antialiasing::Ssaa2 => Antialiasing::Ssaa4,
+            Antialiasing::Ssaa4 => Antialiasing::Off
+        };
+    }
+}
+impl std::str::FromStr for Antialiasing {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        return match s {
+            "off" => Ok(Antialiasing::Off),
+            "msaa2" => Ok(Antialiasing::Msaa2),
+            "msaa4" => Ok(Antialiasing::Msaa4),
+            "ssaa2" => Ok(Antialiasing::Ssaa2),
+            "ssaa4" => Ok(Antialiasing::Ssaa4),
+            _ => Err(format!("unknown antialiasing '{}'", s))
+        };
+    }
+}
+impl std::fmt::Display for Antialiasing {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        return f.pad(match self {
+            Antialiasing::Off => "off",
+            Antialiasing::Msaa2 => "msaa2",
+            Antialiasing::Msaa4 => "msaa4",
+            Antialiasing::Ssaa2 => "ssaa2",
+            Antialiasing::Ssaa4 => "ssaa4"
+        });
+    }
+}
+
+
+// What one chunk of triangles goes through the pipeline with, kept from frame to frame
+#[derive(Default)]
+struct ChunkBuffers {
+    pipeline: PipelineBuffers,
+    out: Vec<Triangle>,
+    stats: RenderStats
+}
+
+
+// Runs the whole pipeline for a frame. Every buffer on the way is kept for the next frame, so once they've grown to
+// fit the scene, drawing doesn't allocate anything besides what the backend needs.
+pub struct Renderer {
+    pub parallelism: Parallelism,
+    pub guard_band: f32, // see `Clipper`, 1 clips right at the edges of the screen
+    chunks: Vec<ChunkBuffers>,
+    opaque: Vec<Triangle>, // projected, then sorted
+    transparent: Vec<Triangle>,
+    sky: Image
+}
+impl Renderer {
+    pub fn new(parallelism: Parallelism) -> Self {
+        return Self { parallelism, guard_band: 1.0, chunks: Vec::new(), opaque: Vec::new(), transparent: Vec::new(), sky: Image::new(0, 0, Color::BLACK) };
+    }
+
+    pub fn with_guard_band(mut self, guard_band: f32) -> Self {
+        self.guard_band = guard_band;
+        return self;
+    }
+
+    // What the shader sees of `camera` and `scene` this frame
+    pub fn uniforms<'a>(scene: &'a Scene, camera: &Camera, width: u32, height: u32) -> Uniforms<'a> {
+        let target: Vec3 = camera.pos + camera.look_direction;
+        let view: Matrix4x4 = Matrix4x4::point_at_inverse(&Matrix4x4::point_at(camera.pos, target, camera.get_up()));
+        let projection: Matrix4x4 = camera.projection_matrix(height as f32 / width as f32);
+
+        return Uniforms {
+            world: Mesh::world_matrix(),
+            view,
+            projection,
+            view_projection: view * projection,
+            camera_position: camera.pos,
+            light_direction: scene.light_direction.normalized(),
+            fog: scene.fog,
+            environment: scene.skybox.as_ref()
+        };
+    }
+
+    // Draws `scene` as seen by `camera` into `backend`, without presenting it. The ambient occlusion of the opaque
+    // triangles is worked out before anything is drawn. The sky goes first, then opaque triangles, then transparent
+    // ones, back to front so each blends with everything behind it. The scene's post processing finishes the frame.
+    pub fn draw<B: RenderBackend + ?Sized>(&mut self, backend: &mut B, scene: &Scene, camera: &Camera, stats: &mut RenderStats) {
+        backend.set_antialiasing(scene.antialiasing);
+        backend.begin_frame(scene.fog.clear_color());
+        let (width, height) = backend.size();
+        let clipper = Clipper::new(width, height, self.guard_band);
+        let uniforms = Self::uniforms(scene, camera, width, height);
+        let shader: &dyn Shader = if scene.render_mode == RenderMode::Pbr { &PbrShader } else { scene.shader.as_ref() };
+
+        // each mesh is split into chunks that go through the pipeline on their own threads, joined back in chunk order
+        self.opaque.clear();
+        self.transparent.clear();
+        for mesh in &scene.meshes {
+            let chunks = self.parallelism.zip_chunks(mesh.triangles(), MIN_TRIANGLES_PER_THREAD, &mut self.chunks, |chunk, buffers| {
+                buffers.out.clear();
+                buffers.stats = RenderStats::new();
+                Mesh::render_triangles(chunk, shader, &uniforms, &clipper, &mut buffers.pipeline, &mut buffers.out, &mut buffers.stats);
+            });
+            for triangle in self.chunks[..chunks].iter().flat_map(|buffers| &buffers.out) {
+                if scene.materials[triangle.material as usize].is_transparent() {
+                    self.transparent.push(*triangle);
+                } else {
+                    self.opaque.push(*triangle);
+                }
+            }
+            stats.merge_parallel(self.chunks[..chunks].iter().map(|buffers| &buffers.stats));
+        }
+
+        // unstable sorting works in place
+        stats.time(Stage::Sort, || {
+            for triangles in [&mut self.opaque, &mut self.transparent] {
+                triangles.sort_unstable();
+                triangles.reverse();
+            }
+        });
+
+        stats.time(Stage::Occlusion, || backend.ambient_occlusion(&self.opaque, &uniforms, &scene.materials, &scene.ssao));
+        stats.time(Stage::Raster, || {
+            if let Some(skybox) = &scene.skybox {
+                if (self.sky.width, self.sky.height) != (width, height) {
+                    self.sky = Image::new(width, height, Color::BLACK);
+                }
+                skybox.draw(&mut self.sky, camera, &self.parallelism);
+                backend.draw_background(&self.sky);
+            }
+            backend.draw_triangles(&self.opaque, scene.render_mode, shader, &uniforms, &scene.materials);
+            backend.draw_triangles(&self.transparent, scene.render_mode, shader, &uniforms, &scene.materials);
+        });
+        stats.time(Stage::Post, || backend.end_frame(&scene.post));
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::software::SoftwareBackend;
+
+    #[test]
+    fn frames_after_the_first_reuse_the_buffers() {
+        let mut scene = Scene::new();
+        let mut teapot = Mesh::load("assets/teapot.obj").expect("Failed to load the teapot");
+        teapot.translate(0.0, -1.5, 8.0);
+        scene.add_mesh(teapot);
+        let mut backend = SoftwareBackend::new(160, 100, None);
+        let mut renderer = Renderer::new(Parallelism::new(4));
+
+        // where every buffer's items are, which moves whenever one has to grow
+        let buffers = |renderer: &Renderer| {
+            let mut buffers = vec![renderer.opaque.as_ptr(), renderer.transparent.as_ptr()];
+            buffers.extend(renderer.chunks.iter().map(|chunk| chunk.out.as_ptr()));
+            return buffers;
+        };
+
+        renderer.draw(&mut backend, &scene, &scene.camera, &mut RenderStats::new());
+        let (first_frame, first_buffers) = (backend.framebuffer.pixels.clone(), buffers(&renderer));
+        assert!(renderer.chunks.len() > 1);
+        renderer.draw(&mut backend, &scene, &scene.camera, &mut RenderStats::new());
+        assert!(buffers(&renderer) == first_buffers);
+        assert!(backend.framebuffer.pixels == first_frame);
+    }
+}
@@ -0,0 +1,323 @@
+use std::fs::File;
+use std::io::{BufWriter, Write};
+use std::collections::VecDeque;
+use std::time::Duration;
+use sdl2::keyboard::{Keycode, Scancode};
+use sdl2::mouse::MouseButton;
+use sdl2::controller::{Axis as PadAxis, Button as PadButton};
+
+use crate::input::{HeldState, Input, InputEvent, Modifiers};
+
+
+// Recording format, one item per line:
+//   f <dt in nanoseconds>                  starts a frame
+//   k <keycode> <scancode> <ctrl> <shift> <alt>  key press (SDL numbers, `none` for none)
+//   b <button>                             mouse button press
+//   m <xrel> <yrel>                        mouse motion
+//   w <y>                                  mouse wheel
+//   p <button>                             controller button press
+//   h <ctrl> <shift> <alt>                 held modifiers
+//   hk <scancode>...                       held keys
+//   hb <button>...                         held mouse buttons
+//   hp <button>...                         held controller buttons
+//   ha <axis> <value>...                   controller axes
+// Controller buttons and axes use SDL's names, everything else its numbers.
+
+
+// One frame worth of input, as it was fed to `Input`. Formatting it gives its lines of the recording.
+#[derive(Clone, PartialEq)]
+pub struct FrameInput {
+    pub dt: Duration,
+    pub events: Vec<InputEvent>,
+    pub held: HeldState
+}
+impl FrameInput {
+    pub fn capture(dt: Duration, input: &Input) -> Self {
+        return Self { dt, events: input.frame_events().to_vec(), held: input.held_state().clone() };
+    }
+
+    // feeds the frame to `input` in place of `handle_event` and `update`
+    pub fn apply(&self, input: &mut Input) {
+        input.begin_frame();
+        for event in &self.events {
+            input.apply_event(*event);
+        }
+        input.apply_held(self.held.clone());
+    }
+}
+impl std::fmt::Display for FrameInput {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let mut out = format!("f {}\n", self.dt.as_nanos());
+
+        for event in &self.events {
+            out += &match event {
+                InputEvent::KeyDown { keycode, scancode, modifiers } => format!(
+                    "k {} {} {}\n",
+                    keycode.map_or(String::from("none"), |k| k.into_i32().to_string()),
+                    scancode.map_or(String::from("none"), |s| (s as i32).to_string()),
+                    modifiers_to_string(*modifiers)
+                ),
+                InputEvent::MouseButtonDown(button) => format!("b {}\n", *button as u8),
+                InputEvent::MouseMotion(x, y) => format!("m {} {}\n", x, y),
+                InputEvent::MouseWheel(y) => format!("w {}\n", y),
+                InputEvent::PadButtonDown(button) => format!("p {}\n", button.string())
+            };
+        }
+
+        // sorted, so the same state always gives the same line
+        let mut scancodes: Vec<i32> = self.held.scancodes.iter().map(|s| *s as i32).collect();
+        scancodes.sort();
+        let mut buttons: Vec<u8> = self.held.mouse_buttons.iter().map(|b| *b as u8).collect();
+        buttons.sort();
+        let mut pad_buttons: Vec<String> = self.held.pad_buttons.iter().map(|b| b.string()).collect();
+        pad_buttons.sort();
+        let mut pad_axes: Vec<String> = self.held.pad_axes.iter().map(|(axis, value)| format!("{} {}", axis.string(), value)).collect();
+        pad_axes.sort();
+
+        if !scancodes.is_empty() {
+            out += &format!("hk {}\n", scancodes.iter().map(|s| s.to_string()).collect::<Vec<String>>().join(" "));
+        }
+        if !buttons.is_empty() {
+            out += &format!("hb {}\n", buttons.iter().map(|b| b.to_string()).collect::<Vec<String>>().join(" "));
+        }
+        if !pad_buttons.is_empty() {
+            out += &format!("hp {}\n", pad_buttons.join(" "));
+        }
+        if !pad_axes.is_empty() {
+            out += &format!("ha {}\n", pad_axes.join(" "));
+        }
+        out += &format!("h {}\n", modifiers_to_string(self.held.modifiers));
+
+        return f.write_str(&out);
+    }
+}
+
+
+fn flag(value: bool) -> u8 {
+    return value as u8;
+}
+
+fn modifiers_to_string(modifiers: Modifiers) -> String {
+    return format!("{} {} {}", flag(modifiers.ctrl), flag(modifiers.shift), flag(modifiers.alt));
+}
+
+fn parse_modifiers(values: &[&str]) -> Result<Modifiers, String> {
+    if values.len() != 3 {
+        return Err(String::from("expected 3 modifier flags"));
+    }
+    return Ok(Modifiers { ctrl: values[0] == "1", shift: values[1] == "1", alt: values[2] == "1" });
+}
+
+fn parse<T: std::str::FromStr>(text: &str) -> Result<T, String> {
+    return text.parse::<T>().map_err(|_| format!("bad number '{}'", text));
+}
+
+// `none`, or an SDL number for `from_i32`
+fn parse_optional<T>(text: &str, from_i32: fn(i32) -> Option<T>) -> Result<Option<T>, String> {
+    if text == "none" {
+        return Ok(None);
+    }
+    return Ok(from_i32(parse(text)?));
+}
+
+
+pub struct InputRecorder {
+    writer: BufWriter<File>
+}
+impl InputRecorder {
+    pub fn create(filename: &str) -> Result<Self, String> {
+        let file = File::create(filename).map_err(|e| format!("{}: {}", filename, e))?;
+        return Ok(Self { writer: BufWriter::new(file) });
+    }
+
+    pub fn record(&mut self, frame: &FrameInput) -> Result<(), String> {
+        return write!(self.writer, "{}", frame).map_err(|e| e.to_string());
+    }
+
+    pub fn finish(mut self) -> Result<(), String> {
+        return self.writer.flush().map_err(|e| e.to_string());
+    }
+}
+
+
+pub struct InputReplay {
+    frames: VecDeque<FrameInput>
+}
+impl InputReplay {
+    pub fn load(filename: &str) -> Result<Self, String> {
+        let content = std::fs::read_to_string(filename).map_err(|e| format!("{}: {}", filename, e))?;
+        return content.parse::<Self>();
+    }
+
+    // frames that haven't been played yet
+    pub fn remaining(&self) -> usize {
+        return self.frames.len();
+    }
+}
+impl std::str::FromStr for InputReplay {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let mut frames: VecDeque<FrameInput> = VecDeque::new();
+
+        for (n, line) in s.lines().enumerate() {
+            let i: Vec<&str> = line.split_whitespace().collect();
+            if i.is_empty() || i[0].starts_with('#') {
+                continue;
+            }
+
+            let error = |e: String| format!("line {}: {}", n + 1, e);
+
+            if i[0] == "f" {
+                let nanos: u64 = parse(i.get(1).copied().unwrap_or("")).map_err(error)?;
+                frames.push_back(FrameInput { dt: Duration::from_nanos(nanos), events: Vec::new(), held: HeldState::default() });
+                continue;
+            }
+
+            let Some(frame) = frames.back_mut() else {
+                return Err(error(String::from("input before the first frame")));
+            };
+
+            match i[0] {
+                "k" => {
+                    if i.len() != 6 {
+                        return Err(error(String::from("expected 5 values")));
+                    }
+                    frame.events.push(InputEvent::KeyDown {
+                        keycode: parse_optional(i[1], Keycode::from_i32).map_err(error)?,
+                        scancode: parse_optional(i[2], Scancode::from_i32).map_err(error)?,
+                        modifiers: parse_modifiers(&i[3..]).map_err(error)?
+                    });
+                },
+                "b" => {
+                    let button: u8 = parse(i.get(1).copied().unwrap_or("")).map_err(error)?;
+                    frame.events.push(InputEvent::MouseButtonDown(MouseButton::from_ll(button)));
+                },
+                "m" => {
+                    if i.len() != 3 {
+                        return Err(error(String::from("expected 2 values")));
+                    }
+                    frame.events.push(InputEvent::MouseMotion(parse(i[1]).map_err(error)?, parse(i[2]).map_err(error)?));
+                },
+                "w" => {
+                    frame.events.push(InputEvent::MouseWheel(parse(i.get(1).copied().unwrap_or("")).map_err(error)?));
+                },
+                "p" => {
+                    let name = i.get(1).copied().unwrap_or("");
+                    let button = PadButton::from_string(name).ok_or(error(format!("unknown controller button '{}'", name)))?;
+                    frame.events.push(InputEvent::PadButtonDown(button));
+                },
+                "h" => {
+                    frame.held.modifiers = parse_modifiers(&i[1..]).map_err(error)?;
+                },
+                "hk" => {
+                    for text in &i[1..] {
+                        let code: i32 = parse(text).map_err(error)?;
+                        frame.held.scancodes.extend(Scancode::from_i32(code));
+                    }
+                },
+                "hb" => {
+                    for text in &i[1..] {
+                        let button: u8 = parse(text).map_err(error)?;
+                        frame.held.mouse_buttons.insert(MouseButton::from_ll(button));
+                    }
+                },
+                "hp" => {
+                    for name in &i[1..] {
+                        let button = PadButton::from_string(name).ok_or(error(format!("unknown controller button '{}'", name)))?;
+                        frame.held.pad_buttons.insert(button);
+                    }
+                },
+                "ha" => {
+                    for pair in i[1..].chunks(2) {
+                        let axis = PadAxis::from_string(pair[0]).ok_or(error(format!("unknown controller axis '{}'", pair[0])))?;
+                        let value: f32 = parse(pair.get(1).copied().unwrap_or("")).map_err(error)?;
+                        frame.held.pad_axes.insert(axis, value);
+                    }
+                },
+                _ => { return Err(error(format!("unknown entry '{}'", i[0]))); }
+            }
+        }
+
+        return Ok(Self { frames });
+    }
+}
+impl Iterator for InputReplay {
+    type Item = FrameInput;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        return self.frames.pop_front();
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::input::{Binding, Source};
+
+    fn frames() -> Vec<FrameInput> {
+        let shift = Modifiers { ctrl: false, shift: true, alt: false };
+        let mut held = HeldState::default();
+        held.scancodes.insert(Scancode::W);
+        held.mouse_buttons.insert(MouseButton::Right);
+        held.modifiers = shift;
+        return vec![
+            FrameInput {
+                dt: Duration::from_nanos(16_666_667),
+                events: vec![
+                    InputEvent::KeyDown { keycode: Some(Keycode::P), scancode: Some(Scancode::P), modifiers: Modifiers::default() },
+                    InputEvent::KeyDown { keycode: None, scancode: Some(Scancode::F13), modifiers: shift },
+                    InputEvent::KeyDown { keycode: Some(Keycode::M), scancode: None, modifiers: Modifiers::default() },
+                    InputEvent::MouseButtonDown(MouseButton::Left),
+                    InputEvent::MouseMotion(3, -4),
+                    InputEvent::MouseWheel(-1)
+                ],
+                held
+            },
+            FrameInput { dt: Duration::from_nanos(16_666_666), events: Vec::new(), held: HeldState::default() }
+        ];
+    }
+
+    #[test]
+    fn recordings_load_back_the_same() {
+        let filename = std::env::temp_dir().join(format!("replay_round_trip_{}.txt", std::process::id()));
+        let filename = filename.to_str().unwrap();
+        let mut recorder = InputRecorder::create(filename).unwrap();
+        for frame in frames() {
+            recorder.record(&frame).unwrap();
+        }
+        recorder.finish().unwrap();
+
+        let replayed: Vec<FrameInput> = InputReplay::load(filename).unwrap().collect();
+        std::fs::remove_file(filename).ok();
+        assert!(replayed == frames());
+    }
+
+    #[test]
+    fn missing_keycodes_stay_missing() {
+        let mut replay = "f 1\nk none 104 0 1 0\nh 0 0 0\n".parse::<InputReplay>().unwrap();
+        let frame = replay.next().unwrap();
+        assert!(frame.events == [InputEvent::KeyDown { keycode: None, scancode: Some(Scancode::F13), modifiers: Modifiers { ctrl: false, shift: true, alt: false } }]);
+    }
+
+    #[test]
+    fn replays_trigger_the_same_actions() {
+        let mut input = Input::new();
+        input.bind("toggle_projection", Binding { source: Source::Key(Keycode::P), modifiers: Modifiers::default(), scale: 1.0 });
+        input.bind("orbit_rotate", Binding { source: Source::Mouse(MouseButton::Left), modifiers: Modifiers::default(), scale: 1.0 });
+        input.bind("look_x", Binding { source: Source::Axis(crate::input::Axis::MouseX), modifiers: Modifiers::default(), scale: 1.0 });
+
+        let text: String = frames().iter().map(|frame| frame.to_string()).collect();
+        let replayed: Vec<FrameInput> = text.parse::<InputReplay>().unwrap().collect();
+        for (original, replayed) in frames().iter().zip(&replayed) {
+            original.apply(&mut input);
+            let expected = (input.was_pressed("toggle_projection"), input.was_pressed("orbit_rotate"), input.axis("look_x"));
+            replayed.apply(&mut input);
+            assert_eq!((input.was_pressed("toggle_projection"), input.was_pressed("orbit_rotate"), input.axis("look_x")), expected);
+        }
+        frames()[0].apply(&mut input);
+        assert!(input.was_pressed("toggle_projection") && input.was_pressed("orbit_rotate"));
+        assert_eq!(input.axis("look_x"), 3.0);
+    }
+}
@@ -0,0 +1,204 @@
+use std::time::Duration;
+
+use crate::camera::Camera;
+use crate::camera_path::{CameraPath, CameraPathPlayer};
+use crate::controller::{CameraController, FlyController, OrbitController};
+use crate::fog::Fog;
+use crate::input::Input;
+use crate::material::Material;
+use crate::math::Vec3;
+use crate::mesh::Mesh;
+use crate::post::PostProcess;
+use crate::render::{Antialiasing, RenderMode, LIGHT_DIRECTION};
+use crate::shader::{DefaultShader, Shader};
+use crate::skybox::CubeMap;
+use crate::ssao::Ssao;
+
+
+pub const CAMERA_PATH_FILE: &str = "camera_path.txt";
+pub const MESSAGE_SECONDS: f32 = 4.0;
+
+
+// Everything that changes from step to step, apart from the window. It is only ever advanced by the engine's fixed
+// step, so the same input always ends up in the same state, which is what makes input replays reproducible.
+pub struct Scene {
+    pub meshes: Vec<Mesh>,
+    pub materials: Vec<Material>, // the first one is what triangles start out with
+    pub light_direction: Vec3,
+    pub fog: Fog,
+    pub skybox: Option<CubeMap>, // drawn behind everything and reflected by materials
+    pub post: PostProcess,
+    pub ssao: Ssao,
+    pub camera: Camera,
+    pub previous_camera: Camera, // camera before the last `update`, for interpolation
+    pub controller: Box<dyn CameraController>,
+    pub render_mode: RenderMode,
+    pub antialiasing: Antialiasing,
+    pub shader: Box<dyn Shader>,
+    pub selected_mesh: usize,
+    pub mouse_locked: bool,
+    pub path_player: CameraPathPlayer,
+    pub recording_time: Option<f32>, // time since the first recorded keyframe
+    pub message: String, // shown on the console line for `MESSAGE_SECONDS`
+    pub message_time: f32
+}
+impl Scene {
+    // an empty scene with the camera at the origin, looking down +z
+    pub fn new() -> Self {
+        let camera = Camera::new(Vec3::new(0.0, 0.0, 0.0), Vec3::new(0.0, 0.0, 1.0), 0.0, 0.0, 0.0);
+        return Self {
+            meshes: Vec::new(),
+            materials: vec![Material::new()],
+            light_direction: LIGHT_DIRECTION,
+            fog: Fog::off(),
+            skybox: None,
+            post: PostProcess::new(),
+            ssao: Ssao::new(),
+            camera,
+            previous_camera: camera,
+            controller: Box::new(FlyController::new(2.0, 1.0)),
+            render_mode: RenderMode::Textured,
+            antialiasing: Antialiasing::Off,
+            shader: Box::new(DefaultShader),
+            selected_mesh: 0,
+            mouse_locked: true,
+            path_player: CameraPathPlayer::new(CameraPath::new()),
+            recording_time: None,
+            message: String::new(),
+            message_time: 0.0
+        };
+    }
+
+    // what's turned on that only a backend which `RenderBackend::shades_pixels` can draw
+    pub fn pixel_effects(&self) -> Vec<&'static str> {
+        let mut effects = Vec::new();
+        if self.antialiasing != Antialiasing::Off {
+            effects.push("antialiasing");
+        }
+        if self.ssao.enabled {
+            effects.push("ssao");
+        }
+        // gamma leaves colours as shaders gave them, which is what the others draw anyway
+        let post = &self.post;
+        for (pass, on) in [("bloom", post.bloom), ("tonemap", post.tonemap), ("fxaa", post.fxaa), ("vignette", post.vignette)] {
+            if on {
+                effects.push(pass);
+            }
+        }
+        return effects;
+    }
+
+    // returns the index of the mesh
+    pub fn add_mesh(&mut self, mesh: Mesh) -> usize {
+        self.meshes.push(mesh);
+        return self.meshes.len() - 1;
+    }
+
+    // returns the index of the material, for `Mesh::set_material`
+    pub fn add_material(&mut self, material: Material) -> usize {
+        self.materials.push(material);
+        return self.materials.len() - 1;
+    }
+
+    pub fn log(&mut self, message: String) {
+        println!("{}", message);
+        self.message = message;
+        self.message_time = MESSAGE_SECONDS;
+    }
+
+    pub fn update(&mut self, input: &Input, dt: Duration) {
+        self.previous_camera = self.camera;
+        self.message_time -= dt.as_secs_f32();
+
+        if input.was_pressed("toggle_mouse_lock") {
+            self.mouse_locked = !self.mouse_locked;
+        }
+        if input.was_pressed("toggle_render_mode") {
+            if self.render_mode == RenderMode::Filled {
+                self.render_mode = RenderMode::Textured;
+            }
+            else if self.render_mode == RenderMode::Textured {
+                self.render_mode = RenderMode::Pbr;
+            }
+            else if self.render_mode == RenderMode::Pbr {
+                self.render_mode = RenderMode::Outline;
+            }
+            else {
+                self.render_mode = RenderMode::Filled;
+            }
+        }
+        if input.was_pressed("toggle_antialiasing") {
+            self.antialiasing = self.antialiasing.next();
+            self.log(format!("Antialiasing {}", self.antialiasing));
+        }
+        if input.was_pressed("toggle_projection") {
+            self.camera.toggle_projection();
+        }
+        if input.was_pressed("switch_controller") {
+            self.controller = if self.controller.name() == "fly" {
+                Box::new(OrbitController::from_camera(&self.camera, 5.0))
+            } else {
+                Box::new(FlyController::new(2.0, 1.0))
+            };
+        }
+        if input.was_pressed("select_next") && !self.meshes.is_empty() {
+            self.selected_mesh = (self.selected_mesh + 1) % self.meshes.len();
+        }
+        if input.was_pressed("frame_selected") && !self.meshes.is_empty() {
+            match self.meshes[self.selected_mesh].bounds() {
+                Some((min, max)) => { self.controller.frame(&mut self.camera, min, max); },
+                None => { self.log(format!("Mesh {} has no triangles to frame", self.selected_mesh)); }
+            }
+        }
+        if input.was_pressed("record_keyframe") {
+            // first keyframe starts a new recording
+            let time = self.recording_time.unwrap_or_else(|| {
+                self.path_player.path = CameraPath::new();
+                0.0
+            });
+            self.path_player.path.record(&self.camera, time);
+            self.recording_time = Some(time);
+            self.log(format!("Recorded keyframe {} at {:.2}s", self.path_player.path.keyframes().len(), time));
+        }
+        if input.was_pressed("save_path") {
+            self.recording_time = None;
+            match self.path_player.path.save(CAMERA_PATH_FILE) {
+                Ok(()) => { self.log(format!("Saved camera path to {}", CAMERA_PATH_FILE)); },
+                Err(e) => { self.log(format!("Failed to save camera path: {}", e)); }
+            }
+        }
+        if input.was_pressed("play_path") {
+            if self.path_player.playing {
+                self.path_player.stop();
+            } else {
+                if self.path_player.path.is_empty() {
+                    match CameraPath::load(CAMERA_PATH_FILE) {
+                        Ok(path) => { self.path_player.path = path; },
+                        Err(e) => { self.log(format!("Failed to load camera path: {}", e)); }
+                    }
+                }
+                self.recording_time = None;
+                self.path_player.play();
+            }
+        }
+
+        if let Some(time) = self.recording_time.as_mut() {
+            *time += dt.as_secs_f32();
+        }
+
+        if self.path_player.playing {
+            self.path_player.update(&mut self.camera, dt.as_secs_f32());
+        } else {
+            self.controller.update(&mut self.camera, input, self.mouse_locked, dt);
+            if !self.controller.uses_zoom() {
+                self.camera.zoom(input.axis("zoom"));
+            }
+        }
+    }
+}
+impl Default for Scene {
+    fn default() -> Self {
+        return Self::new();
+    }
+}
+
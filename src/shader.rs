@@ -0,0 +1,301 @@
+use std::sync::OnceLock;
+
+use sdl2::pixels::Color;
+
+use crate::backend::Image;
+use crate::fog::Fog;
+use crate::material::Material;
+use crate::math::{Matrix4x4, Vec3, Vec4};
+use crate::skybox::CubeMap;
+use crate::vertex::{Attribute, Attributes, Vertex};
+
+
+// multiplies two colours channel by channel
+pub fn modulate(color: Color, tint: Color) -> Color {
+    return Color::RGBA(
+        (color.r as u32 * tint.r as u32 / 255) as u8,
+        (color.g as u32 * tint.g as u32 / 255) as u8,
+        (color.b as u32 * tint.b as u32 / 255) as u8,
+        (color.a as u32 * tint.a as u32 / 255) as u8
+    );
+}
+
+// `amount` of the way from `color` to `other`, keeping the alpha of `color`
+pub fn mix(color: Color, other: Color, amount: f32) -> Color {
+    let channel = |a: u8, b: u8| (a as f32 + (b as f32 - a as f32) * amount).round() as u8;
+    return Color::RGBA(channel(color.r, other.r), channel(color.g, other.g), channel(color.b, other.b), color.a);
+}
+
+// An sRGB colour as linear light from 0 to 1, for adding up light. Alpha is left out.
+pub fn to_linear(color: Color) -> Vec3 {
+    let (table, _) = srgb_tables();
+    return Vec3::new(table[color.r as usize], table[color.g as usize], table[color.b as usize]);
+}
+
+// Linear light back into an opaque sRGB colour, anything brighter than 1 is clipped. Colours that came from
+// `to_linear` come back exactly as they were.
+pub fn to_srgb(light: Vec3) -> Color {
+    let (_, thresholds) = srgb_tables();
+    let channel = |c: f32| thresholds.partition_point(|threshold| *threshold < c) as u8;
+    return Color::RGB(channel(light.x), channel(light.y), channel(light.z));
+}
+
+// every sRGB value as linear light, and the halfway points between neighbouring ones
+fn srgb_tables() -> &'static ([f32; 256], [f32; 255]) {
+    static TABLES: OnceLock<([f32; 256], [f32; 255])> = OnceLock::new();
+    return TABLES.get_or_init(|| {
+        let linear: [f32; 256] = std::array::from_fn(|i| {
+            let c = i as f32 / 255.0;
+            if c <= 0.04045 { c / 12.92 } else { ((c + 0.055) / 1.055).powf(2.4) }
+        });
+        (linear, std::array::from_fn(|i| (linear[i] + linear[i + 1]) * 0.5))
+    });
+}
+
+
+// What every vertex and pixel of a frame gets to see
+#[derive(Copy, Clone)]
+pub struct Uniforms<'a> {
+    pub world: Matrix4x4,
+    pub view: Matrix4x4,
+    pub projection: Matrix4x4,
+    pub view_projection: Matrix4x4,
+    pub camera_position: Vec3,
+    pub light_direction: Vec3, // normalized
+    pub fog: Fog,
+    pub environment: Option<&'a CubeMap> // the sky, for reflections
+}
+
+
+// Decides how things look. `vertex` runs on every corner of every mesh and returns it in clip space, with whatever
+// attributes `fragment` needs (the varyings). Those are interpolated across the triangle and `fragment` turns them into
+// the colour of a pixel, or `None` to leave the pixel alone.
+//
+// Only `SoftwareBackend` runs `fragment`, SDL's renderer draws the colour `vertex` gives each corner.
+pub trait Shader: Sync {
+    fn vertex(&self, vertex: &Vertex, uniforms: &Uniforms) -> Vertex;
+
+    // `material` is what the triangle is made of, `texture` is there in `RenderMode::Textured` and `RenderMode::Pbr`:
+    // the material's, or the backend's if it has none. The material's tint and opacity are applied afterwards.
+    fn fragment(&self, varyings: &Attributes, uniforms: &Uniforms, material: &Material, texture: Option<&Image>) -> Option<Color>;
+
+    // The same as linear light with its alpha, for `SoftwareBackend`'s HDR buffer. Light isn't limited to 1 here, so
+    // shaders that work out how bright things really are can leave the rest to tone mapping. By default it's
+    // `fragment`'s colour.
+    fn fragment_hdr(&self, varyings: &Attributes, uniforms: &Uniforms, material: &Material, texture: Option<&Image>) -> Option<(Vec3, u8)> {
+        return self.fragment(varyings, uniforms, material, texture).map(|color| (to_linear(color), color.a));
+    }
+}
+
+
+// Moves the vertex into clip space and turns its normal and tangent along into the world. `Attribute::Position` gets
+// its position in the world and `Attribute::Depth` its depth in view space, for `fog`. Everything else stays as it is.
+pub fn transform(vertex: &Vertex, uniforms: &Uniforms) -> Vertex {
+    let world = vertex.position * uniforms.world;
+    let view = world * uniforms.view;
+    let mut attributes = vertex.attributes;
+    let normal = (Vec4::from_vec3(attributes.normal(), 0.0) * uniforms.world).xyz();
+    let tangent = attributes.tangent();
+    attributes.set_normal(normal);
+    attributes.set_tangent((Vec4::from_vec3(tangent.xyz(), 0.0) * uniforms.world).xyz(), tangent.w);
+    attributes.set(Attribute::Position, &[world.x, world.y, world.z]);
+    attributes.set(Attribute::Depth, &[view.z]);
+    return Vertex { position: view * uniforms.projection, attributes };
+}
+
+// The normal at a pixel, bent by the material's normal map if it has one. Normal maps go the OpenGL way, with red
+// pointing along the texture's u and green up the image.
+pub fn surface_normal(varyings: &Attributes, material: &Material) -> Vec3 {
+    let normal = varyings.normal().normalized();
+    let Some(normal_map) = &material.normal_map else {
+        return normal;
+    };
+
+    // interpolating doesn't keep the tangent perpendicular to the normal
+    let tangent = varyings.tangent();
+    let t = (tangent.xyz() - normal * normal.dot(&tangent.xyz())).normalized();
+    let b = normal.cross(&t) * tangent.w;
+
+    let uv = varyings.uv();
+    let texel = normal_map.sample(uv.x, uv.y);
+    let axis = |c: u8| c as f32 / 255.0 * 2.0 - 1.0;
+    return (t * axis(texel.r) - b * axis(texel.g) + normal * axis(texel.b)).normalized();
+}
+
+// what a mirror with `normal` at a pixel shows of the sky, `None` without one
+pub fn reflection(varyings: &Attributes, normal: Vec3, uniforms: &Uniforms) -> Option<Color> {
+    let environment = uniforms.environment?;
+    let view = (varyings.position() - uniforms.camera_position).normalized();
+    return Some(environment.sample(view - normal * (2.0 * view.dot(&normal))));
+}
+
+// `color` of a pixel faded into the fog, by the depth `transform` gave its corners
+pub fn fog(color: Color, varyings: &Attributes, uniforms: &Uniforms) -> Color {
+    return uniforms.fog.apply(color, varyings.depth());
+}
+
+
+// One light from `light_direction` on top of `AMBIENT` everywhere, so nothing ends up completely black. It's worked out
+// for every corner, or for every pixel when the material has a normal map. Reflective materials mirror the sky on top.
+// Ambient occlusion only takes away the ambient part, the same as `PbrShader`.
+pub struct DefaultShader;
+impl Shader for DefaultShader {
+    fn vertex(&self, vertex: &Vertex, uniforms: &Uniforms) -> Vertex {
+        let mut out = transform(vertex, uniforms);
+        out.attributes.set_color(lambert(out.attributes.normal(), vertex.attributes.color().a, uniforms));
+        return out;
+    }
+
+    fn fragment(&self, varyings: &Attributes, uniforms: &Uniforms, material: &Material, texture: Option<&Image>) -> Option<Color> {
+        let normal = surface_normal(varyings, material);
+        let mut light = if material.normal_map.is_some() { lambert(normal, varyings.color().a, uniforms) } else { varyings.color() };
+        if varyings.occlusion() < 1.0 {
+            light = occlude(light, varyings.occlusion());
+        }
+        let mut color = match texture {
+            Some(texture) => {
+                let uv = varyings.uv();
+                modulate(texture.sample(uv.x, uv.y), light)
+            },
+            None => light
+        };
+        if material.reflectivity > 0.0 && let Some(reflection) = reflection(varyings, normal, uniforms) {
+            color = mix(color, reflection, material.reflectivity.min(1.0));
+        }
+        return Some(fog(color, varyings, uniforms));
+    }
+}
+
+
+// how bright the default shader's light is where `light_direction` doesn't reach
+const AMBIENT: f32 = 0.1;
+
+// grey as bright as the light falling on `normal`, the direct light tops the ambient up to the Lambert term
+fn lambert(normal: Vec3, alpha: u8, uniforms: &Uniforms) -> Color {
+    let dp = uniforms.light_direction.dot(&normal).max(AMBIENT);
+    return Color::RGBA((dp * 255.0) as u8, (dp * 255.0) as u8, (dp * 255.0) as u8, alpha);
+}
+
+// `light` from `lambert` with only `occlusion` of its ambient part left
+fn occlude(light: Color, occlusion: f32) -> Color {
+    let lost = (AMBIENT * (1.0 - occlusion.clamp(0.0, 1.0)) * 255.0).round() as u8;
+    return Color::RGBA(light.r.saturating_sub(lost), light.g.saturating_sub(lost), light.b.saturating_sub(lost), light.a);
+}
+
+
+// A shader made of two closures, for writing one without a type of its own
+pub struct FnShader<V, F> {
+    pub vertex: V,
+    pub fragment: F
+}
+impl<V, F> Shader for FnShader<V, F>
+where
+    V: Fn(&Vertex, &Uniforms) -> Vertex + Sync,
+    F: Fn(&Attributes, &Uniforms, &Material, Option<&Image>) -> Option<Color> + Sync
+{
+    fn vertex(&self, vertex: &Vertex, uniforms: &Uniforms) -> Vertex {
+        return (self.vertex)(vertex, uniforms);
+    }
+
+    fn fragment(&self, varyings: &Attributes, uniforms: &Uniforms, material: &Material, texture: Option<&Image>) -> Option<Color> {
+        return (self.fragment)(varyings, uniforms, material, texture);
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::backend::RenderBackend;
+    use crate::math::Vec2;
+    use crate::mesh::{Mesh, Triangle};
+    use crate::render::{Parallelism, RenderMode, Renderer};
+    use crate::scene::Scene;
+    use crate::software::SoftwareBackend;
+    use crate::stats::RenderStats;
+
+    fn uniforms() -> Uniforms<'static> {
+        return Uniforms {
+            world: Matrix4x4::identity(),
+            view: Matrix4x4::identity(),
+            projection: Matrix4x4::identity(),
+            view_projection: Matrix4x4::identity(),
+            camera_position: Vec3::new(0.0, 0.0, 0.0),
+            light_direction: Vec3::new(0.0, 0.0, -1.0),
+            fog: Fog::off(),
+            environment: None
+        };
+    }
+
+    // the default shader's light on a surface facing `normal`, with `occlusion` of the ambient light reaching it
+    fn light(normal: Vec3, occlusion: f32) -> Color {
+        let uniforms = uniforms();
+        let mut vertex = Vertex::new(Vec3::new(0.0, 0.0, 1.0));
+        vertex.attributes.set_normal(normal);
+        let mut varyings = DefaultShader.vertex(&vertex, &uniforms).attributes;
+        varyings.set(Attribute::Occlusion, &[occlusion]);
+        return DefaultShader.fragment(&varyings, &uniforms, &Material::new(), None).expect("Failed to shade a pixel");
+    }
+
+    #[test]
+    fn occlusion_leaves_the_direct_light_alone() {
+        let facing = Vec3::new(0.0, 0.0, -1.0);
+        assert_eq!(light(facing, 1.0).r, 255);
+        assert_eq!(light(facing, 0.0).r, ((1.0 - AMBIENT) * 255.0) as u8);
+
+        let away = Vec3::new(0.0, 0.0, 1.0);
+        assert_eq!(light(away, 1.0).r, (AMBIENT * 255.0) as u8);
+        assert_eq!(light(away, 0.0).r, 0);
+        assert!(light(away, 0.5).r < light(away, 1.0).r);
+    }
+
+    // the teapot drawn with a shader whose fragment stage gives every pixel `color`
+    fn draw_with(color: Option<Color>) -> Image {
+        let mut scene = Scene::new();
+        let mut teapot = Mesh::load("assets/teapot.obj").expect("Failed to load the teapot");
+        teapot.translate(0.0, -1.5, 8.0);
+        scene.add_mesh(teapot);
+        scene.render_mode = RenderMode::Filled;
+        scene.shader = Box::new(FnShader { vertex: transform, fragment: move |_: &Attributes, _: &Uniforms, _: &Material, _: Option<&Image>| color });
+
+        let mut backend = SoftwareBackend::new(80, 50, None);
+        Renderer::new(Parallelism::serial()).draw(&mut backend, &scene, &scene.camera, &mut RenderStats::new());
+        return backend.read_pixels().expect("Failed to read the frame");
+    }
+
+    #[test]
+    fn fragment_shaders_colour_every_pixel_they_cover() {
+        let red = Color::RGB(255, 0, 0);
+        let image = draw_with(Some(red));
+        assert!(image.pixels.contains(&red));
+        assert!(image.pixels.iter().all(|color| *color == red || *color == Color::BLACK));
+
+        // and leave the ones they return nothing for alone
+        assert!(draw_with(None).pixels.iter().all(|color| *color == Color::BLACK));
+    }
+
+    // how bright the middle of a triangle facing the light is drawn with `normal_map`
+    fn lit_through(normal_map: Image) -> f32 {
+        let scene = Scene::new();
+        let uniforms = Renderer::uniforms(&scene, &scene.camera, 16, 16);
+        let mut triangle = Triangle::new([Vec3::new(0.0, 0.0, 0.5), Vec3::new(16.0, 0.0, 0.5), Vec3::new(0.0, 16.0, 0.5)], [Vec2::new(0.0, 0.0); 3], Color::WHITE);
+        for vertex in &mut triangle.vertices {
+            vertex.attributes.set_normal(uniforms.light_direction);
+            vertex.attributes.set_tangent(uniforms.light_direction.cross(&Vec3::new(1.0, 0.0, 0.0)).normalized(), 1.0);
+        }
+        let material = Material { normal_map: Some(normal_map), ..Material::new() };
+
+        let mut backend = SoftwareBackend::new(16, 16, None);
+        backend.begin_frame(Color::BLACK);
+        backend.draw_triangles(&[triangle], RenderMode::Filled, &DefaultShader, &uniforms, &[material]);
+        return backend.hdr[4 * 16 + 4].x;
+    }
+
+    #[test]
+    fn normal_maps_bend_the_light_per_pixel() {
+        // straight out of the surface changes nothing
+        assert!(lit_through(Image::new(2, 2, Color::RGB(128, 128, 255))) > 0.99);
+        // along the tangent, square to the light, leaves only the ambient
+        assert!(lit_through(Image::new(2, 2, Color::RGB(255, 128, 128))) < 0.05);
+    }
+}
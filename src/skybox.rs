@@ -0,0 +1,242 @@
+use sdl2::pixels::Color;
+
+use crate::backend::Image;
+use crate::camera::Camera;
+use crate::math::{Matrix4x4, Vec3};
+use crate::render::Parallelism;
+use crate::shader::{to_linear, to_srgb};
+
+
+// pixels per thread at least when drawing the sky
+const MIN_PIXELS_PER_THREAD: usize = 16 * 1024;
+// size of the faces of the irradiance map, and of what it's averaged from
+const IRRADIANCE_SIZE: u32 = 8;
+const IRRADIANCE_SOURCE_SIZE: u32 = 16;
+
+// Which way each face looks, and which ways its images' right and down go. The camera starts out looking down +z
+// with -x to its right, the faces are laid out so the images read the right way round from the inside.
+const FACES: [(Vec3, Vec3, Vec3); 6] = [
+    (Vec3 { x: 1.0, y: 0.0, z: 0.0 }, Vec3 { x: 0.0, y: 0.0, z: 1.0 }, Vec3 { x: 0.0, y: -1.0, z: 0.0 }),
+    (Vec3 { x: -1.0, y: 0.0, z: 0.0 }, Vec3 { x: 0.0, y: 0.0, z: -1.0 }, Vec3 { x: 0.0, y: -1.0, z: 0.0 }),
+    (Vec3 { x: 0.0, y: 1.0, z: 0.0 }, Vec3 { x: -1.0, y: 0.0, z: 0.0 }, Vec3 { x: 0.0, y: 0.0, z: 1.0 }),
+    (Vec3 { x: 0.0, y: -1.0, z: 0.0 }, Vec3 { x: -1.0, y: 0.0, z: 0.0 }, Vec3 { x: 0.0, y: 0.0, z: -1.0 }),
+    (Vec3 { x: 0.0, y: 0.0, z: 1.0 }, Vec3 { x: -1.0, y: 0.0, z: 0.0 }, Vec3 { x: 0.0, y: -1.0, z: 0.0 }),
+    (Vec3 { x: 0.0, y: 0.0, z: -1.0 }, Vec3 { x: 1.0, y: 0.0, z: 0.0 }, Vec3 { x: 0.0, y: -1.0, z: 0.0 })
+];
+
+
+// What's infinitely far away in every direction, as six square images: +x, -x, +y, -y, +z and -z.
+// The scene draws it behind everything as the sky, and shaders can look up what a reflection would show, or how much
+// light falls on a surface from all of it together.
+pub struct CubeMap {
+    faces: [Image; 6],
+    irradiance: [Image; 6] // tiny, every texel is the sky's average over the hemisphere around it
+}
+impl CubeMap {
+    // faces must be square and all the same size
+    pub fn new(faces: [Image; 6]) -> Result<Self, String> {
+        let size = faces[0].width;
+        if faces.iter().any(|face| face.width != size || face.height != size) {
+            return Err(String::from("cube map faces must be square and the same size"));
+        }
+        return Ok(Self::with_irradiance(faces));
+    }
+
+    fn with_irradiance(faces: [Image; 6]) -> Self {
+        let irradiance = convolve(&faces);
+        return Self { faces, irradiance };
+    }
+
+    // six images in the order of the faces
+    pub fn load_faces(filenames: &[String; 6]) -> Result<Self, String> {
+        let mut faces = Vec::with_capacity(6);
+        for filename in filenames {
+            faces.push(Image::load(filename)?);
+        }
+        let faces: [Image; 6] = faces.try_into().map_err(|_| String::from("expected 6 faces"))?;
+        return Self::new(faces);
+    }
+
+    // One image with longitude going right and latitude going down, looking down +z in the middle. Its faces are
+    // half as wide as the image is high, which keeps about as much detail as there was.
+    pub fn from_equirectangular(image: &Image) -> Self {
+        let size = (image.height / 2).max(1);
+        let faces = FACES.map(|(forward, right, down)| {
+            let mut face = Image::new(size, size, Color::BLACK);
+            for y in 0..size {
+                for x in 0..size {
+                    let (u, v) = ((x as f32 + 0.5) / size as f32 * 2.0 - 1.0, (y as f32 + 0.5) / size as f32 * 2.0 - 1.0);
+                    let direction = (forward + right * u + down * v).normalized();
+                    let longitude = (-direction.x).atan2(direction.z);
+                    let latitude = direction.y.clamp(-1.0, 1.0).asin();
+                    face.set(x, y, image.sample(0.5 + longitude / std::f32::consts::TAU, (0.5 - latitude / std::f32::consts::PI).min(0.9999)));
+                }
+            }
+            face
+        });
+        return Self::with_irradiance(faces);
+    }
+
+    pub fn load_equirectangular(filename: &str) -> Result<Self, String> {
+        return Ok(Self::from_equirectangular(&Image::load(filename)?));
+    }
+
+    // one file is an equirectangular image, six are the faces
+    pub fn load(filenames: &[String]) -> Result<Self, String> {
+        return match filenames {
+            [filename] => Self::load_equirectangular(filename),
+            [_, _, _, _, _, _] => Self::load_faces(filenames.try_into().unwrap()),
+            _ => Err(String::from("a sky is one equirectangular image or six faces"))
+        };
+    }
+
+    pub fn size(&self) -> u32 {
+        return self.faces[0].width;
+    }
+
+    // what's seen looking along `direction`, which doesn't have to be normalized
+    pub fn sample(&self, direction: Vec3) -> Color {
+        return sample_faces(&self.faces, direction);
+    }
+
+    // how brightly the sky lights a surface facing `direction`, as the colour of a white one
+    pub fn irradiance(&self, direction: Vec3) -> Color {
+        return sample_faces(&self.irradiance, direction);
+    }
+
+    // Fills `image` with the sky as `camera` sees it. Only the camera's rotation matters, the sky is too far away for
+    // moving to change anything. Orthographic cameras see it with their field of view like perspective ones.
+    pub fn draw(&self, image: &mut Image, camera: &Camera, parallelism: &Parallelism) {
+        let (width, height) = (image.width as usize, image.height as usize);
+        let rotation = Matrix4x4::point_at(Vec3::new(0.0, 0.0, 0.0), camera.look_direction, camera.get_up());
+        let (right, up, forward) = (row(&rotation, 0), row(&rotation, 1), row(&rotation, 2));
+
+        // the inverse of the projection, pixels are flipped on both axes like the pipeline does it
+        let scale_y = (camera.fov * 0.5).to_radians().tan();
+        let scale_x = scale_y * width as f32 / height as f32;
+
+        parallelism.for_each_chunk_indexed_mut(&mut image.pixels, MIN_PIXELS_PER_THREAD, |start, pixels| {
+            for (i, pixel) in pixels.iter_mut().enumerate() {
+                let (x, y) = ((start + i) % width, (start + i) / width);
+                let ndc_x = 1.0 - (x as f32 + 0.5) / width as f32 * 2.0;
+                let ndc_y = 1.0 - (y as f32 + 0.5) / height as f32 * 2.0;
+                *pixel = self.sample(forward + right * (ndc_x * scale_x) + up * (ndc_y * scale_y));
+            }
+        });
+    }
+}
+
+fn sample_faces(faces: &[Image; 6], direction: Vec3) -> Color {
+    let (x, y, z) = (direction.x.abs(), direction.y.abs(), direction.z.abs());
+    let face = if x >= y && x >= z {
+        if direction.x >= 0.0 { 0 } else { 1 }
+    } else if y >= z {
+        if direction.y >= 0.0 { 2 } else { 3 }
+    } else if direction.z >= 0.0 {
+        4
+    } else {
+        5
+    };
+
+    let (forward, right, down) = FACES[face];
+    let distance = direction.dot(&forward);
+    if distance <= 0.0 || distance.is_nan() {
+        return Color::BLACK;
+    }
+    let size = faces[face].width;
+    let coordinate = |axis: Vec3| (((direction.dot(&axis) / distance + 1.0) * 0.5 * size as f32) as u32).min(size - 1);
+    return faces[face].get(coordinate(right), coordinate(down));
+}
+
+// Irradiance faces for `faces`: every texel adds up a coarse version of the whole sky, weighted by how squarely it
+// faces the texel's direction and by how much of the sphere it covers. The sum happens with linear light.
+fn convolve(faces: &[Image; 6]) -> [Image; 6] {
+    let texel = |size: u32, x: u32, y: u32| -> (f32, f32) {
+        return ((x as f32 + 0.5) / size as f32 * 2.0 - 1.0, (y as f32 + 0.5) / size as f32 * 2.0 - 1.0);
+    };
+
+    let mut sources = Vec::with_capacity(6 * (IRRADIANCE_SOURCE_SIZE * IRRADIANCE_SOURCE_SIZE) as usize);
+    let mut sphere = 0.0; // all the solid angles, which really add up to 4 pi
+    for (forward, right, down) in FACES {
+        for y in 0..IRRADIANCE_SOURCE_SIZE {
+            for x in 0..IRRADIANCE_SOURCE_SIZE {
+                let (u, v) = texel(IRRADIANCE_SOURCE_SIZE, x, y);
+                let direction = forward + right * u + down * v;
+                let solid_angle = 1.0 / (1.0 + u * u + v * v).powf(1.5);
+                let light = to_linear(sample_faces(faces, direction));
+                sources.push((direction.normalized(), light * solid_angle));
+                sphere += solid_angle;
+            }
+        }
+    }
+
+    return FACES.map(|(forward, right, down)| {
+        let mut face = Image::new(IRRADIANCE_SIZE, IRRADIANCE_SIZE, Color::BLACK);
+        for y in 0..IRRADIANCE_SIZE {
+            for x in 0..IRRADIANCE_SIZE {
+                let (u, v) = texel(IRRADIANCE_SIZE, x, y);
+                let normal = (forward + right * u + down * v).normalized();
+                let mut sum = Vec3::new(0.0, 0.0, 0.0);
+                for (direction, light) in &sources {
+                    let cosine = normal.dot(direction);
+                    if cosine > 0.0 {
+                        sum += *light * cosine;
+                    }
+                }
+                // divided by pi, so a sky that's the same everywhere lights a white surface to its own colour
+                face.set(x, y, to_srgb(sum * (4.0 / sphere)));
+            }
+        }
+        face
+    });
+}
+
+fn row(matrix: &Matrix4x4, i: usize) -> Vec3 {
+    return Vec3::new(matrix.mat[i][0], matrix.mat[i][1], matrix.mat[i][2]);
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::backend::RenderBackend;
+    use crate::render::Renderer;
+    use crate::scene::Scene;
+    use crate::software::SoftwareBackend;
+    use crate::stats::RenderStats;
+
+    const COLORS: [(u8, u8, u8); 6] = [(200, 60, 60), (60, 200, 60), (60, 60, 200), (200, 200, 60), (60, 200, 200), (200, 60, 200)];
+
+    // every face a colour of its own
+    fn sky() -> CubeMap {
+        return CubeMap::new(COLORS.map(|(r, g, b)| Image::new(4, 4, Color::RGB(r, g, b)))).expect("Failed to make the sky");
+    }
+
+    #[test]
+    fn directions_look_up_their_face() {
+        let sky = sky();
+        for ((forward, _, _), (r, g, b)) in FACES.iter().zip(COLORS) {
+            assert_eq!(sky.sample(*forward * 3.0), Color::RGB(r, g, b));
+        }
+    }
+
+    #[test]
+    fn an_even_sky_lights_surfaces_its_own_colour() {
+        let sky = CubeMap::new(std::array::from_fn(|_| Image::new(4, 4, Color::RGB(100, 150, 200)))).expect("Failed to make the sky");
+        let light = sky.irradiance(Vec3::new(0.3, -1.0, 0.2));
+        assert!(light.r.abs_diff(100) <= 2 && light.g.abs_diff(150) <= 2 && light.b.abs_diff(200) <= 2);
+    }
+
+    #[test]
+    fn the_sky_is_drawn_behind_an_empty_scene() {
+        let mut scene = Scene::new();
+        scene.skybox = Some(sky());
+        let mut backend = SoftwareBackend::new(40, 30, None);
+        Renderer::new(Parallelism::serial()).draw(&mut backend, &scene, &scene.camera, &mut RenderStats::new());
+        let image = backend.read_pixels().expect("Failed to read the frame");
+
+        // the camera looks down +z
+        let (r, g, b) = COLORS[4];
+        assert_eq!(image.get(20, 15), Color::RGB(r, g, b));
+    }
+}
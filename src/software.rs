@@ -0,0 +1,550 @@
+use sdl2::pixels::Color;
+
+use crate::backend::{Image, RenderBackend};
+use crate::material::{BlendMode, Material};
+use crate::math::{Vec2, Vec3};
+use crate::mesh::Triangle;
+use crate::post::{PostProcess, PostProcessor};
+use crate::render::{Antialiasing, Parallelism, RenderMode};
+use crate::shader::{to_linear, Shader, Uniforms};
+use crate::ssao::{Ssao, SsaoPass};
+use crate::vertex::{Attribute, Attributes};
+
+
+pub const TILE_SIZE: u32 = 64;
+// tiles per thread at least, fewer aren't worth a thread of their own
+const MIN_TILES_PER_THREAD: usize = 4;
+// pixels per thread at least when averaging samples
+const MIN_PIXELS_PER_THREAD: usize = 16 * 1024;
+// the most samples any `Antialiasing` takes of a pixel
+const MAX_SAMPLES: usize = 4;
+
+
+// (b - a) x (p - a), positive when `p` is to the left of the edge from `a` to `b`
+pub fn edge(a: Vec2, b: Vec2, p: Vec2) -> f32 {
+    return (b.x - a.x) * (p.y - a.y) - (b.y - a.y) * (p.x - a.x);
+}
+
+
+// The rectangle of the HDR buffer that's being drawn into: the whole of it, or a single tile.
+// `pixels` and `depth` only cover the rectangle, coordinates are still those of the whole framebuffer. Every pixel has
+// `antialiasing.samples()` of each, one after the other. `occlusion` is one per pixel of the whole framebuffer,
+// `frame_width` wide, and empty without ambient occlusion.
+struct Target<'a> {
+    pixels: &'a mut [Vec3],
+    depth: &'a mut [f32],
+    x: u32,
+    y: u32,
+    width: u32,
+    height: u32,
+    antialiasing: Antialiasing,
+    occlusion: &'a [f32],
+    frame_width: u32
+}
+impl<'a> Target<'a> {
+    fn contains(&self, x: i64, y: i64) -> bool {
+        return x >= self.x as i64 && y >= self.y as i64 && x < (self.x + self.width) as i64 && y < (self.y + self.height) as i64;
+    }
+
+    fn index(&self, x: u32, y: u32) -> usize {
+        return ((y - self.y) * self.width + x - self.x) as usize;
+    }
+
+    fn set(&mut self, x: u32, y: u32, color: Color) {
+        let samples = self.antialiasing.samples();
+        let i = self.index(x, y) * samples;
+        self.pixels[i..i + samples].fill(to_linear(color));
+    }
+
+    // Every sample inside the triangle and not behind what's already there. `shade` gets the pixel and the barycentric
+    // weights of the three corners and leaves the samples alone by returning `None`, otherwise their light is blended in by its alpha
+    // with `blend_mode`. Only opaque samples write their depth, so transparent ones never hide anything.
+    // Without antialiasing the only sample is the pixel's center. MSAA shades once per pixel, at the center if it's
+    // inside the triangle and at the first sample that is otherwise, SSAA shades every sample on its own.
+    fn fill_triangle<F: Fn(u32, u32, [f32; 3]) -> Option<(Vec3, u8)>>(&mut self, triangle: &Triangle, blend_mode: BlendMode, shade: F) {
+        let p = triangle.vertices.map(|vertex| Vec2::new(vertex.position.x, vertex.position.y));
+        let z = triangle.vertices.map(|vertex| vertex.position.z);
+        let area = edge(p[0], p[1], p[2]);
+        if area == 0.0 {
+            return;
+        }
+
+        let min_x = (p[0].x.min(p[1].x).min(p[2].x).floor().max(0.0) as u32).max(self.x);
+        let min_y = (p[0].y.min(p[1].y).min(p[2].y).floor().max(0.0) as u32).max(self.y);
+        let max_x = (p[0].x.max(p[1].x).max(p[2].x).ceil().max(0.0) as u32).min(self.x + self.width);
+        let max_y = (p[0].y.max(p[1].y).max(p[2].y).ceil().max(0.0) as u32).min(self.y + self.height);
+
+        // dividing by the area makes the weights positive inside for either winding
+        let weights_at = |point: Vec2| [edge(p[1], p[2], point) / area, edge(p[2], p[0], point) / area, edge(p[0], p[1], point) / area];
+        // A sample right on an edge belongs to the triangle only if it's a top or left edge, so two triangles sharing
+        // an edge don't both draw it. Going along the edge with the inside on the right (y points down) that's going
+        // right along a flat edge or up.
+        let owns = [(p[1], p[2]), (p[2], p[0]), (p[0], p[1])].map(|(a, b)| {
+            let (dx, dy) = ((b.x - a.x) * area.signum(), (b.y - a.y) * area.signum());
+            return dy < 0.0 || (dy == 0.0 && dx > 0.0);
+        });
+        let inside = |weights: &[f32; 3]| weights.iter().zip(owns).all(|(w, owned)| *w > 0.0 || (*w == 0.0 && owned));
+        let positions = self.antialiasing.sample_positions();
+        let per_sample = self.antialiasing.shades_every_sample();
+
+        for y in min_y..max_y {
+            for x in min_x..max_x {
+                let first = self.index(x, y) * positions.len();
+
+                // which samples are covered and in front, and their depth
+                let mut covered = [None; MAX_SAMPLES];
+                for (s, (offset_x, offset_y)) in positions.iter().enumerate() {
+                    let weights = weights_at(Vec2::new(x as f32 + offset_x, y as f32 + offset_y));
+                    if !inside(&weights) {
+                        continue;
+                    }
+                    // depth divided by w is linear on the screen
+                    let depth = weights[0] * z[0] + weights[1] * z[1] + weights[2] * z[2];
+                    if depth <= self.depth[first + s] {
+                        covered[s] = Some((weights, depth));
+                    }
+                }
+                let Some((first_weights, _)) = covered.iter().flatten().next().copied() else {
+                    continue;
+                };
+
+                let mut once = None;
+                if !per_sample {
+                    let center = weights_at(Vec2::new(x as f32 + 0.5, y as f32 + 0.5));
+                    once = shade(x, y, if center.iter().all(|w| *w >= 0.0) { center } else { first_weights });
+                }
+                for (s, sample) in covered.iter().enumerate() {
+                    let Some((weights, depth)) = sample else {
+                        continue;
+                    };
+                    let shaded = if per_sample { shade(x, y, *weights) } else { once };
+                    if let Some((light, alpha)) = shaded {
+                        self.pixels[first + s] = blend_mode.blend_light(light, alpha, self.pixels[first + s]);
+                        if blend_mode == BlendMode::Opaque {
+                            self.depth[first + s] = *depth;
+                        }
+                    }
+                }
+            }
+        }
+    }
+
+    // Bresenham, pixels outside the target are skipped
+    fn draw_line(&mut self, from: Vec2, to: Vec2, color: Color) {
+        let (mut x, mut y) = (from.x as i64, from.y as i64);
+        let (end_x, end_y) = (to.x as i64, to.y as i64);
+        let dx = (end_x - x).abs();
+        let dy = -(end_y - y).abs();
+        let step_x = if x < end_x { 1 } else { -1 };
+        let step_y = if y < end_y { 1 } else { -1 };
+        let mut error = dx + dy;
+
+        loop {
+            if self.contains(x, y) {
+                self.set(x as u32, y as u32, color);
+            }
+            if x == end_x && y == end_y {
+                break;
+            }
+            let doubled = error * 2;
+            if doubled >= dy {
+                error += dy;
+                x += step_x;
+            }
+            if doubled <= dx {
+                error += dx;
+                y += step_y;
+            }
+        }
+    }
+
+    // `texture` is the backend's, for materials without one of their own
+    fn draw_triangle(&mut self, triangle: &Triangle, render_mode: RenderMode, shader: &dyn Shader, uniforms: &Uniforms, material: &Material, texture: Option<&Image>) {
+        if render_mode == RenderMode::Outline {
+            for i in 0..3 {
+                let (a, b) = (triangle.vertices[i].position, triangle.vertices[(i + 1) % 3].position);
+                self.draw_line(Vec2::new(a.x, a.y), Vec2::new(b.x, b.y), Color::WHITE);
+            }
+            return;
+        }
+
+        let attributes = triangle.vertices.each_ref().map(|vertex| &vertex.attributes);
+        let inv_w = triangle.vertices.map(|vertex| vertex.position.w);
+        let texture = if matches!(render_mode, RenderMode::Textured | RenderMode::Pbr) { material.texture.as_ref().or(texture) } else { None };
+        let (occlusion, frame_width) = (self.occlusion, self.frame_width);
+
+        self.fill_triangle(triangle, material.blend_mode, |x, y, w| {
+            // attributes over w are linear on screen, dividing by the interpolated 1 / w undoes it
+            let w = [w[0] * inv_w[0], w[1] * inv_w[1], w[2] * inv_w[2]];
+            let sum = w[0] + w[1] + w[2];
+            let mut varyings = Attributes::blend(attributes, [w[0] / sum, w[1] / sum, w[2] / sum]);
+            varyings.set(Attribute::Occlusion, &[occlusion.get((y * frame_width + x) as usize).copied().unwrap_or(1.0)]);
+            shader.fragment_hdr(&varyings, uniforms, material, texture).and_then(|(light, alpha)| material.apply_light(light, alpha))
+        });
+    }
+}
+
+
+// A rectangle of the framebuffer, the triangles touching it and pixels of its own to draw them into on another thread.
+// Tiles are kept from frame to frame, so their buffers only get allocated once.
+struct Tile {
+    x: u32,
+    y: u32,
+    width: u32,
+    height: u32,
+    triangles: Vec<u32>, // indices, in drawing order
+    pixels: Vec<Vec3>,
+    depth: Vec<f32>
+}
+
+
+// Rasterizes on the CPU into an `Image`, without needing a window or SDL's renderer. A depth buffer keeps opaque
+// triangles from covering what's in front of them, whatever order they come in. Everything is drawn as linear light
+// into a floating point HDR buffer first, `end_frame` runs the scene's `PostProcess` on it to make the framebuffer.
+// With antialiasing every pixel has a few samples of light and depth, averaged before post processing. Ambient
+// occlusion is worked out per pixel before the opaque triangles are drawn, when the scene's `Ssao` is on.
+// With more than one thread the screen is cut into `tile_size` tiles. Every triangle is binned into the tiles it
+// touches and each tile draws its triangles in the order they came in, so the image is the same as drawing on one thread.
+pub struct SoftwareBackend {
+    pub framebuffer: Image,
+    pub hdr: Vec<Vec3>, // linear light of every sample of every pixel
+    pub depth: Vec<f32>, // z of the closest opaque sample so far, from 0 at the near plane to 1 at the far one
+    antialiasing: Antialiasing,
+    resolved: Vec<Vec3>, // the samples of each pixel averaged
+    texture: Option<Image>,
+    pub parallelism: Parallelism,
+    pub tile_size: u32,
+    tiles: Vec<Tile>,
+    tiles_x: u32,
+    tiles_layout: (u32, u32, u32), // framebuffer width and height and tile size the tiles were made for
+    post: PostProcessor,
+    ssao: SsaoPass,
+    occluded: bool // whether `ssao` has the occlusion of this frame
+}
+impl SoftwareBackend {
+    pub fn new(width: u32, height: u32, texture: Option<Image>) -> Self {
+        return Self {
+            framebuffer: Image::new(width, height, Color::BLACK),
+            hdr: vec![Vec3::new(0.0, 0.0, 0.0); (width * height) as usize],
+            depth: vec![f32::INFINITY; (width * height) as usize],
+            antialiasing: Antialiasing::Off,
+            resolved: Vec::new(),
+            texture,
+            parallelism: Parallelism::serial(),
+            tile_size: TILE_SIZE,
+            tiles: Vec::new(),
+            tiles_x: 0,
+            tiles_layout: (0, 0, 0),
+            post: PostProcessor::new(),
+            ssao: SsaoPass::new(),
+            occluded: false
+        };
+    }
+
+    pub fn with_parallelism(mut self, parallelism: Parallelism) -> Self {
+        self.parallelism = parallelism;
+        return self;
+    }
+
+    fn whole_target(&mut self) -> Target<'_> {
+        return Target {
+            pixels: &mut self.hdr,
+            depth: &mut self.depth,
+            x: 0,
+            y: 0,
+            width: self.framebuffer.width,
+            height: self.framebuffer.height,
+            antialiasing: self.antialiasing,
+            occlusion: if self.occluded { &self.ssao.occlusion } else { &[] },
+            frame_width: self.framebuffer.width
+        };
+    }
+
+    // cuts the framebuffer into tiles again if its size or the tile size changed
+    fn layout_tiles(&mut self) {
+        let (width, height, tile_size) = (self.framebuffer.width, self.framebuffer.height, self.tile_size);
+        if self.tiles_layout == (width, height, tile_size) {
+            return;
+        }
+
+        self.tiles_x = width.div_ceil(tile_size);
+        self.tiles.clear();
+        for y in (0..height).step_by(tile_size as usize) {
+            for x in (0..width).step_by(tile_size as usize) {
+                let (tile_width, tile_height) = (tile_size.min(width - x), tile_size.min(height - y));
+                self.tiles.push(Tile {
+                    x,
+                    y,
+                    width: tile_width,
+                    height: tile_height,
+                    triangles: Vec::new(),
+                    pixels: Vec::with_capacity((tile_width * tile_height) as usize),
+                    depth: Vec::with_capacity((tile_width * tile_height) as usize)
+                });
+            }
+        }
+        self.tiles_layout = (width, height, tile_size);
+    }
+
+    // puts the index of each triangle into the tiles it touches
+    fn bin(&mut self, triangles: &[Triangle]) {
+        let (tiles_x, tiles_y) = (self.tiles_x, self.tiles.len() as u32 / self.tiles_x.max(1));
+        let tile_size = self.tile_size;
+        let last = |value: f32, tiles: u32| ((value.max(0.0) as u32) / tile_size).min(tiles - 1);
+
+        for tile in &mut self.tiles {
+            tile.triangles.clear();
+        }
+        if self.tiles.is_empty() {
+            return;
+        }
+
+        for (i, triangle) in triangles.iter().enumerate() {
+            let p = triangle.points();
+            let (min_x, max_x) = (p[0].x.min(p[1].x).min(p[2].x), p[0].x.max(p[1].x).max(p[2].x));
+            let (min_y, max_y) = (p[0].y.min(p[1].y).min(p[2].y), p[0].y.max(p[1].y).max(p[2].y));
+
+            for tile_y in last(min_y, tiles_y)..=last(max_y, tiles_y) {
+                for tile_x in last(min_x, tiles_x)..=last(max_x, tiles_x) {
+                    self.tiles[(tile_y * tiles_x + tile_x) as usize].triangles.push(i as u32);
+                }
+            }
+        }
+    }
+
+    fn draw_tiled(&mut self, triangles: &[Triangle], render_mode: RenderMode, shader: &dyn Shader, uniforms: &Uniforms, materials: &[Material]) {
+        self.layout_tiles();
+        self.bin(triangles);
+
+        let (width, hdr, depth) = (self.framebuffer.width, &self.hdr, &self.depth);
+        let (texture, antialiasing, samples) = (self.texture.as_ref(), self.antialiasing, self.antialiasing.samples());
+        let occlusion: &[f32] = if self.occluded { &self.ssao.occlusion } else { &[] };
+
+        // each tile is drawn into its own pixels and depth, which are copied back afterwards
+        self.parallelism.for_each_chunk_mut(&mut self.tiles, MIN_TILES_PER_THREAD, |chunk| {
+            for tile in chunk.iter_mut().filter(|tile| !tile.triangles.is_empty()) {
+                tile.pixels.clear();
+                tile.depth.clear();
+                for row in tile.y..tile.y + tile.height {
+                    let (start, end) = ((row * width + tile.x) as usize * samples, (row * width + tile.x + tile.width) as usize * samples);
+                    tile.pixels.extend_from_slice(&hdr[start..end]);
+                    tile.depth.extend_from_slice(&depth[start..end]);
+                }
+
+                let mut target = Target {
+                    pixels: &mut tile.pixels,
+                    depth: &mut tile.depth,
+                    x: tile.x,
+                    y: tile.y,
+                    width: tile.width,
+                    height: tile.height,
+                    antialiasing,
+                    occlusion,
+                    frame_width: width
+                };
+                for i in &tile.triangles {
+                    let triangle = &triangles[*i as usize];
+                    target.draw_triangle(triangle, render_mode, shader, uniforms, &materials[triangle.material as usize], texture);
+                }
+            }
+        });
+
+        let width = self.framebuffer.width;
+        for tile in self.tiles.iter().filter(|tile| !tile.triangles.is_empty()) {
+            let row_length = tile.width as usize * samples;
+            let rows = tile.pixels.chunks(row_length).zip(tile.depth.chunks(row_length));
+            for (row, (line, depth)) in rows.enumerate() {
+                let start = ((tile.y + row as u32) * width + tile.x) as usize * samples;
+                self.hdr[start..start + row_length].copy_from_slice(line);
+                self.depth[start..start + row_length].copy_from_slice(depth);
+            }
+        }
+    }
+
+    // averages the samples of every pixel into `resolved`
+    fn resolve(&mut self) {
+        let samples = self.antialiasing.samples();
+        let hdr = &self.hdr;
+        self.resolved.resize(self.framebuffer.pixels.len(), Vec3::new(0.0, 0.0, 0.0));
+        self.parallelism.for_each_chunk_indexed_mut(&mut self.resolved, MIN_PIXELS_PER_THREAD, |start, pixels| {
+            for (i, pixel) in pixels.iter_mut().enumerate() {
+                let first = (start + i) * samples;
+                let mut sum = Vec3::new(0.0, 0.0, 0.0);
+                for light in &hdr[first..first + samples] {
+                    sum += *light;
+                }
+                *pixel = sum * (1.0 / samples as f32);
+            }
+        });
+    }
+}
+impl RenderBackend for SoftwareBackend {
+    fn size(&self) -> (u32, u32) {
+        return (self.framebuffer.width, self.framebuffer.height);
+    }
+
+    fn shades_pixels(&self) -> bool {
+        return true;
+    }
+
+    fn begin_frame(&mut self, clear_color: Color) {
+        let samples = self.framebuffer.pixels.len() * self.antialiasing.samples();
+        self.hdr.clear();
+        self.hdr.resize(samples, to_linear(clear_color));
+        self.depth.clear();
+        self.depth.resize(samples, f32::INFINITY);
+        self.occluded = false;
+    }
+
+    // starts the frame over when the number of samples changes
+    fn set_antialiasing(&mut self, antialiasing: Antialiasing) {
+        if antialiasing != self.antialiasing {
+            self.antialiasing = antialiasing;
+            self.begin_frame(Color::BLACK);
+        }
+    }
+
+    fn draw_triangles(&mut self, triangles: &[Triangle], render_mode: RenderMode, shader: &dyn Shader, uniforms: &Uniforms, materials: &[Material]) {
+        if self.parallelism.threads() > 1 {
+            self.draw_tiled(triangles, render_mode, shader, uniforms, materials);
+            return;
+        }
+
+        let texture = self.texture.take();
+        let mut target = self.whole_target();
+        for triangle in triangles {
+            target.draw_triangle(triangle, render_mode, shader, uniforms, &materials[triangle.material as usize], texture.as_ref());
+        }
+        self.texture = texture;
+    }
+
+    fn ambient_occlusion(&mut self, triangles: &[Triangle], uniforms: &Uniforms, materials: &[Material], ssao: &Ssao) {
+        self.occluded = ssao.enabled;
+        if ssao.enabled {
+            self.ssao.run(ssao, triangles, uniforms, materials, (self.framebuffer.width, self.framebuffer.height), &self.parallelism);
+        }
+    }
+
+    fn draw_background(&mut self, image: &Image) {
+        if (image.width, image.height) == (self.framebuffer.width, self.framebuffer.height) {
+            let samples = self.antialiasing.samples();
+            for (pixel, color) in self.hdr.chunks_mut(samples).zip(&image.pixels) {
+                pixel.fill(to_linear(*color));
+            }
+        }
+    }
+
+    fn draw_line(&mut self, from: Vec2, to: Vec2, color: Color) {
+        self.whole_target().draw_line(from, to, color);
+    }
+
+    fn end_frame(&mut self, post: &PostProcess) {
+        if self.antialiasing.samples() == 1 {
+            self.post.run(post, &self.hdr, &mut self.framebuffer, &self.parallelism);
+            return;
+        }
+        self.resolve();
+        self.post.run(post, &self.resolved, &mut self.framebuffer, &self.parallelism);
+    }
+
+    fn present(&mut self) {}
+
+    fn read_pixels(&mut self) -> Option<Image> {
+        return Some(self.framebuffer.clone());
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::mesh::Mesh;
+    use crate::render::Renderer;
+    use crate::scene::Scene;
+    use crate::shader::{to_srgb, transform, FnShader};
+    use crate::skybox::CubeMap;
+    use crate::stats::RenderStats;
+
+    // a couple of frames of a scene with every pass that's split between threads turned on
+    fn draw(parallelism: Parallelism) -> Image {
+        let mut scene = Scene::new();
+        let mut teapot = Mesh::load("assets/teapot.obj").expect("Failed to load the teapot");
+        teapot.translate(0.0, -1.5, 8.0);
+        scene.add_mesh(teapot);
+        scene.render_mode = RenderMode::Filled;
+        scene.antialiasing = Antialiasing::Msaa4;
+        scene.ssao.enabled = true;
+        for pass in PostProcess::PASSES {
+            *scene.post.pass_mut(pass).expect("Failed to find a post pass") = true;
+        }
+        let faces = [(200, 60, 60), (60, 200, 60), (60, 60, 200), (200, 200, 60), (60, 200, 200), (200, 60, 200)];
+        scene.skybox = Some(CubeMap::new(faces.map(|(r, g, b)| Image::new(8, 8, Color::RGB(r, g, b)))).expect("Failed to make the sky"));
+
+        let mut backend = SoftwareBackend::new(320, 200, None).with_parallelism(parallelism.clone());
+        let mut renderer = Renderer::new(parallelism);
+        for _ in 0..2 {
+            renderer.draw(&mut backend, &scene, &scene.camera, &mut RenderStats::new());
+        }
+        return backend.framebuffer;
+    }
+
+    #[test]
+    fn threads_draw_the_same_image() {
+        let serial = draw(Parallelism::serial());
+        let threaded = draw(Parallelism::new(4));
+        assert!(serial.pixels == threaded.pixels);
+    }
+
+    #[test]
+    fn shared_edges_are_drawn_once() {
+        let scene = Scene::new();
+        let uniforms = Renderer::uniforms(&scene, &scene.camera, 16, 16);
+        let white = FnShader { vertex: transform, fragment: |_: &Attributes, _: &Uniforms, _: &Material, _: Option<&Image>| Some(Color::WHITE) };
+        let glass = [Material::transparent(Color::WHITE, 0.5)];
+        let triangle = |points: [(f32, f32); 3]| Triangle::new(points.map(|(x, y)| Vec3::new(x, y, 0.5)), [Vec2::new(0.0, 0.0); 3], Color::WHITE);
+
+        // split along a diagonal and along a row, both right through the middle of pixels, and the second pair is wound
+        // different ways
+        let pairs = [
+            [triangle([(0.0, 0.0), (8.0, 0.0), (8.0, 8.0)]), triangle([(0.0, 0.0), (8.0, 8.0), (0.0, 8.0)])],
+            [triangle([(0.0, 4.5), (8.0, 4.5), (4.0, 0.0)]), triangle([(0.0, 4.5), (8.0, 4.5), (4.0, 9.0)])]
+        ];
+        for pair in pairs {
+            let mut backend = SoftwareBackend::new(16, 16, None);
+            backend.begin_frame(Color::BLACK);
+            backend.draw_triangles(&pair, RenderMode::Filled, &white, &uniforms, &glass);
+
+            let once = backend.hdr[2 * 16 + 4];
+            assert!(once.x > 0.0);
+            assert!(backend.hdr.iter().all(|light| light.x == 0.0 || light.x == once.x));
+            // and without a gap between them
+            assert!((1..7).all(|x| backend.hdr[4 * 16 + x].x == once.x));
+        }
+    }
+
+    #[test]
+    fn half_covered_pixels_resolve_to_the_average() {
+        let scene = Scene::new();
+        let uniforms = Renderer::uniforms(&scene, &scene.camera, 8, 8);
+        let white = FnShader { vertex: transform, fragment: |_: &Attributes, _: &Uniforms, _: &Material, _: Option<&Image>| Some(Color::WHITE) };
+        // everything right of a line through the first column, between the pixel centre and its right half's samples
+        let triangle = Triangle::new([Vec3::new(0.6, -10.0, 0.5), Vec3::new(0.6, 20.0, 0.5), Vec3::new(30.0, 5.0, 0.5)], [Vec2::new(0.0, 0.0); 3], Color::WHITE);
+
+        let draw = |antialiasing: Antialiasing| {
+            let mut backend = SoftwareBackend::new(8, 8, None);
+            backend.set_antialiasing(antialiasing);
+            backend.begin_frame(Color::BLACK);
+            backend.draw_triangles(&[triangle], RenderMode::Filled, &white, &uniforms, &[Material::new()]);
+            backend.end_frame(&PostProcess::new());
+            return backend.framebuffer;
+        };
+
+        assert_eq!(draw(Antialiasing::Off).get(0, 4), Color::BLACK);
+        let half = to_srgb(Vec3::new(0.5, 0.5, 0.5));
+        for antialiasing in [Antialiasing::Msaa2, Antialiasing::Msaa4, Antialiasing::Ssaa2, Antialiasing::Ssaa4] {
+            let image = draw(antialiasing);
+            assert_eq!(image.get(0, 4), half);
+            assert_eq!(image.get(1, 4), Color::WHITE);
+        }
+    }
+}
@@ -0,0 +1,273 @@
+use rand::rngs::StdRng;
+use rand::{Rng, SeedableRng};
+
+use crate::material::Material;
+use crate::math::{Vec2, Vec3, Vec4};
+use crate::mesh::Triangle;
+use crate::render::Parallelism;
+use crate::shader::Uniforms;
+use crate::software::edge;
+use crate::vertex::Attributes;
+
+
+// samples in the hemisphere around every pixel
+const KERNEL_SIZE: usize = 16;
+// the random rotations of the kernel repeat every this many pixels both ways, the blur is as wide so it evens them out
+const NOISE_SIZE: u32 = 4;
+// the kernel and its rotations are the same every run, so replays draw the same frames
+const SEED: u64 = 0x55a0;
+// how far behind the surface in view space a sample has to be to count, so flat surfaces don't occlude themselves
+const BIAS: f32 = 0.025;
+// pixels per thread at least
+const MIN_PIXELS_PER_THREAD: usize = 16 * 1024;
+
+
+// Screen space ambient occlusion: how much of the ambient light reaches each pixel, judging by how much of the
+// hemisphere over it within `radius` is inside the depth buffer. Corners, creases and where things touch get darker.
+#[derive(Copy, Clone)]
+pub struct Ssao {
+    pub enabled: bool,
+    pub radius: f32, // in world units
+    pub strength: f32 // 0 leaves everything lit, 1 takes away as much ambient light as is occluded
+}
+impl Ssao {
+    pub fn new() -> Self {
+        return Self { enabled: false, radius: 0.5, strength: 1.0 };
+    }
+}
+impl Default for Ssao {
+    fn default() -> Self {
+        return Self::new();
+    }
+}
+
+
+// the closest opaque surface at a pixel in view space, `position.z` is infinite where there's nothing
+#[derive(Copy, Clone)]
+struct Texel {
+    position: Vec3,
+    normal: Vec3
+}
+const EMPTY: Texel = Texel { position: Vec3 { x: 0.0, y: 0.0, z: f32::INFINITY }, normal: Vec3 { x: 0.0, y: 0.0, z: 0.0 } };
+
+
+// Runs `Ssao` for `SoftwareBackend` ahead of drawing the opaque triangles. They're rasterized once without shading for
+// the position and normal of every pixel, the occlusion is worked out from those and blurred into `occlusion`.
+// The buffers are kept from frame to frame.
+pub struct SsaoPass {
+    kernel: [Vec3; KERNEL_SIZE],
+    noise: [Vec3; (NOISE_SIZE * NOISE_SIZE) as usize], // directions in the screen's plane to turn the kernel to
+    geometry: Vec<Texel>,
+    raw: Vec<f32>, // the occlusion before blurring
+    pub occlusion: Vec<f32> // 1 where all the ambient light gets through, for every pixel
+}
+impl SsaoPass {
+    pub fn new() -> Self {
+        let mut rng = StdRng::seed_from_u64(SEED);
+        let mut kernel = [Vec3::new(0.0, 0.0, 0.0); KERNEL_SIZE];
+        for (i, sample) in kernel.iter_mut().enumerate() {
+            let direction = Vec3::new(rng.random_range(-1.0..1.0), rng.random_range(-1.0..1.0), rng.random_range(0.0..1.0));
+            // more samples close to the pixel, where occluders matter most
+            let scale = i as f32 / KERNEL_SIZE as f32;
+            *sample = direction.normalized() * (rng.random::<f32>() * (0.1 + 0.9 * scale * scale));
+        }
+        let noise = std::array::from_fn(|_| Vec3::new(rng.random_range(-1.0..1.0), rng.random_range(-1.0..1.0), 0.0));
+
+        return Self { kernel, noise, geometry: Vec::new(), raw: Vec::new(), occlusion: Vec::new() };
+    }
+
+    // `triangles` are on a screen `width` by `height` pixels. Cutouts are left out, their holes would occlude.
+    pub fn run(&mut self, ssao: &Ssao, triangles: &[Triangle], uniforms: &Uniforms, materials: &[Material], (width, height): (u32, u32), parallelism: &Parallelism) {
+        let pixels = (width * height) as usize;
+        self.geometry.clear();
+        self.geometry.resize(pixels, EMPTY);
+        self.raw.resize(pixels, 1.0);
+        self.occlusion.resize(pixels, 1.0);
+
+        parallelism.for_each_chunk_indexed_mut(&mut self.geometry, MIN_PIXELS_PER_THREAD, |start, texels| {
+            for triangle in triangles.iter().filter(|triangle| materials[triangle.material as usize].alpha_cutoff <= 0.0) {
+                rasterize(triangle, uniforms, width, start, texels);
+            }
+        });
+
+        let (kernel, noise, geometry) = (&self.kernel, &self.noise, &self.geometry);
+        parallelism.for_each_chunk_indexed_mut(&mut self.raw, MIN_PIXELS_PER_THREAD, |start, values| {
+            for (i, value) in values.iter_mut().enumerate() {
+                let i = start + i;
+                let (x, y) = (i as u32 % width, i as u32 / width);
+                let rotation = noise[(y % NOISE_SIZE * NOISE_SIZE + x % NOISE_SIZE) as usize];
+                *value = occlusion_at(ssao, kernel, rotation, geometry[i], geometry, uniforms, (width, height));
+            }
+        });
+
+        // a box as wide as the noise, only over pixels with something in them
+        let raw = &self.raw;
+        parallelism.for_each_chunk_indexed_mut(&mut self.occlusion, MIN_PIXELS_PER_THREAD, |start, values| {
+            let half = NOISE_SIZE as i64 / 2;
+            for (i, value) in values.iter_mut().enumerate() {
+                let i = start + i;
+                if geometry[i].position.z == f32::INFINITY {
+                    *value = 1.0;
+                    continue;
+                }
+                let (x, y) = ((i as u32 % width) as i64, (i as u32 / width) as i64);
+                let (mut sum, mut count) = (0.0, 0);
+                for sample_y in (y - half).max(0)..(y + half).min(height as i64) {
+                    for sample_x in (x - half).max(0)..(x + half).min(width as i64) {
+                        let j = (sample_y * width as i64 + sample_x) as usize;
+                        if geometry[j].position.z != f32::INFINITY {
+                            sum += raw[j];
+                            count += 1;
+                        }
+                    }
+                }
+                *value = if count > 0 { sum / count as f32 } else { 1.0 };
+            }
+        });
+    }
+}
+impl Default for SsaoPass {
+    fn default() -> Self {
+        return Self::new();
+    }
+}
+
+
+// Puts the view space position and normal of `triangle` into the pixels of `texels` it covers that it's in front
+// of. `texels` are the pixels from `start` on, of a screen `width` wide.
+fn rasterize(triangle: &Triangle, uniforms: &Uniforms, width: u32, start: usize, texels: &mut [Texel]) {
+    let p = triangle.vertices.map(|vertex| Vec2::new(vertex.position.x, vertex.position.y));
+    let area = edge(p[0], p[1], p[2]);
+    if area == 0.0 {
+        return;
+    }
+
+    let end = start + texels.len();
+    let (first_row, last_row) = ((start / width as usize) as u32, end.div_ceil(width as usize) as u32);
+    let min_x = p[0].x.min(p[1].x).min(p[2].x).floor().max(0.0) as u32;
+    let min_y = (p[0].y.min(p[1].y).min(p[2].y).floor().max(0.0) as u32).max(first_row);
+    let max_x = (p[0].x.max(p[1].x).max(p[2].x).ceil().max(0.0) as u32).min(width);
+    let max_y = (p[0].y.max(p[1].y).max(p[2].y).ceil().max(0.0) as u32).min(last_row);
+
+    let attributes = triangle.vertices.each_ref().map(|vertex| &vertex.attributes);
+    let inv_w = triangle.vertices.map(|vertex| vertex.position.w);
+    for y in min_y..max_y {
+        for x in min_x..max_x {
+            let i = (y * width + x) as usize;
+            if i < start || i >= end {
+                continue;
+            }
+            let center = Vec2::new(x as f32 + 0.5, y as f32 + 0.5);
+            let w = [edge(p[1], p[2], center) / area, edge(p[2], p[0], center) / area, edge(p[0], p[1], center) / area];
+            if w.iter().any(|w| *w < 0.0) {
+                continue;
+            }
+
+            let w = [w[0] * inv_w[0], w[1] * inv_w[1], w[2] * inv_w[2]];
+            let sum = w[0] + w[1] + w[2];
+            let varyings = Attributes::blend(attributes, [w[0] / sum, w[1] / sum, w[2] / sum]);
+            let position = (Vec4::from_vec3(varyings.position(), 1.0) * uniforms.view).xyz();
+            if position.z < texels[i - start].position.z {
+                let normal = (Vec4::from_vec3(varyings.normal(), 0.0) * uniforms.view).xyz().normalized();
+                texels[i - start] = Texel { position, normal };
+            }
+        }
+    }
+}
+
+// How much ambient light gets to `texel`: the kernel is turned around its normal by `rotation`, and every sample that
+// ends up behind what's on the screen where it lands counts as occluded, less so the further that is from the texel
+fn occlusion_at(ssao: &Ssao, kernel: &[Vec3], rotation: Vec3, texel: Texel, geometry: &[Texel], uniforms: &Uniforms, (width, height): (u32, u32)) -> f32 {
+    if texel.position.z == f32::INFINITY {
+        return 1.0;
+    }
+
+    // the camera looks down +z, so normals facing it have a negative z
+    let normal = if texel.normal.dot(&texel.position) > 0.0 { texel.normal * -1.0 } else { texel.normal };
+    let mut tangent = rotation - normal * normal.dot(&rotation);
+    if tangent.length() < 1e-4 {
+        tangent = normal.cross(&Vec3::new(1.0, 0.0, 0.0));
+    }
+    let tangent = tangent.normalized();
+    let bitangent = normal.cross(&tangent);
+
+    let mut occluded = 0.0;
+    for sample in kernel {
+        let point = texel.position + (tangent * sample.x + bitangent * sample.y + normal * sample.z) * ssao.radius;
+        let clip = Vec4::from_vec3(point, 1.0) * uniforms.projection;
+        if clip.w <= 0.0 {
+            continue;
+        }
+        let x = (1.0 - clip.x / clip.w) * 0.5 * width as f32;
+        let y = (1.0 - clip.y / clip.w) * 0.5 * height as f32;
+        if x < 0.0 || y < 0.0 || x >= width as f32 || y >= height as f32 {
+            continue;
+        }
+
+        let occluder = geometry[(y as u32 * width + x as u32) as usize].position.z;
+        if occluder <= point.z - BIAS {
+            // occluders much further away than the radius are something else in front, not a crease
+            let range = (ssao.radius / (texel.position.z - occluder).abs()).min(1.0);
+            occluded += range * range * (3.0 - 2.0 * range);
+        }
+    }
+    return (1.0 - ssao.strength * occluded / kernel.len() as f32).clamp(0.0, 1.0);
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use sdl2::pixels::Color;
+    use crate::clip::Clipper;
+    use crate::mesh::{Mesh, PipelineBuffers};
+    use crate::render::Renderer;
+    use crate::scene::Scene;
+    use crate::shader::DefaultShader;
+    use crate::stats::RenderStats;
+
+    const SIZE: (u32, u32) = (64, 48);
+
+    // two triangles, facing the camera when the corners go round anticlockwise as seen from it
+    fn quad(corners: [Vec3; 4]) -> Vec<Triangle> {
+        let uv = [Vec2::new(0.0, 0.0); 3];
+        return vec![
+            Triangle::new([corners[0], corners[1], corners[2]], uv, Color::WHITE),
+            Triangle::new([corners[0], corners[2], corners[3]], uv, Color::WHITE)
+        ];
+    }
+
+    // the occlusion of every pixel of `mesh` seen from the scene's camera
+    fn occlusion(mesh: &Mesh) -> Vec<f32> {
+        let scene = Scene::new();
+        let uniforms = Renderer::uniforms(&scene, &scene.camera, SIZE.0, SIZE.1);
+        let mut triangles = Vec::new();
+        Mesh::render_triangles(mesh.triangles(), &DefaultShader, &uniforms, &Clipper::new(SIZE.0, SIZE.1, 1.0), &mut PipelineBuffers::default(), &mut triangles, &mut RenderStats::new());
+        assert!(!triangles.is_empty());
+
+        let mut pass = SsaoPass::new();
+        let ssao = Ssao { enabled: true, ..Ssao::new() };
+        pass.run(&ssao, &triangles, &uniforms, &[Material::new()], SIZE, &Parallelism::serial());
+        return pass.occlusion;
+    }
+
+    // a wall across the whole view, 4 in front of the camera and from 2 below it up
+    fn wall() -> Vec<Triangle> {
+        return quad([Vec3::new(20.0, 0.0, 4.0), Vec3::new(-20.0, 0.0, 4.0), Vec3::new(-20.0, 20.0, 4.0), Vec3::new(20.0, 20.0, 4.0)]);
+    }
+
+    #[test]
+    fn flat_surfaces_are_not_occluded() {
+        assert!(occlusion(&Mesh::new(wall())).iter().all(|value| *value > 0.99));
+    }
+
+    #[test]
+    fn creases_are_occluded() {
+        let mut triangles = wall();
+        triangles.extend(quad([Vec3::new(-20.0, 0.0, 0.1), Vec3::new(-20.0, 0.0, 4.0), Vec3::new(20.0, 0.0, 4.0), Vec3::new(20.0, 0.0, 0.1)]));
+        let occlusion = occlusion(&Mesh::new(triangles));
+        assert!(occlusion.iter().any(|value| *value < 0.8));
+        // far from the crease the wall is still lit
+        assert!(occlusion[..SIZE.0 as usize].iter().all(|value| *value > 0.99));
+    }
+}
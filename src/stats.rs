@@ -0,0 +1,168 @@
+use std::collections::VecDeque;
+use std::time::{Duration, Instant};
+
+
+#[derive(Copy, Clone, Eq, PartialEq)]
+pub enum Stage {
+    Transform,
+    Cull,
+    Clip,
+    Sort,
+    Occlusion,
+    Raster,
+    Post,
+    Present
+}
+impl Stage {
+    pub const ALL: [Stage; 8] = [Stage::Transform, Stage::Cull, Stage::Clip, Stage::Sort, Stage::Occlusion, Stage::Raster, Stage::Post, Stage::Present];
+
+    pub fn name(&self) -> &'static str {
+        return match self {
+            Stage::Transform => "transform",
+            Stage::Cull => "cull",
+            Stage::Clip => "clip",
+            Stage::Sort => "sort",
+            Stage::Occlusion => "ssao",
+            Stage::Raster => "raster",
+            Stage::Post => "post",
+            Stage::Present => "present"
+        };
+    }
+}
+
+
+// What one frame spent its time on, and how many triangles made it through each step of the pipeline
+#[derive(Copy, Clone)]
+pub struct RenderStats {
+    pub timings: [Duration; 8], // indexed by `Stage`
+    pub triangles_submitted: usize,
+    pub triangles_after_culling: usize,
+    pub triangles_after_clipping: usize
+}
+impl Default for RenderStats {
+    fn default() -> Self {
+        return Self::new();
+    }
+}
+impl RenderStats {
+    pub fn new() -> Self {
+        return Self {
+            timings: [Duration::new(0, 0); 8],
+            triangles_submitted: 0,
+            triangles_after_culling: 0,
+            triangles_after_clipping: 0
+        };
+    }
+
+    pub fn add(&mut self, stage: Stage, duration: Duration) {
+        self.timings[stage as usize] += duration;
+    }
+
+    pub fn time<R, F: FnOnce() -> R>(&mut self, stage: Stage, f: F) -> R {
+        let start = Instant::now();
+        let result = f();
+        self.add(stage, start.elapsed());
+        return result;
+    }
+
+    // adds up work that ran side by side on several threads: counts are summed, while the time
+    // each stage took is that of the slowest thread
+    pub fn merge_parallel<'a, I: Iterator<Item = &'a RenderStats> + Clone>(&mut self, parts: I) {
+        for stage in Stage::ALL {
+            self.add(stage, parts.clone().map(|part| part.get(stage)).max().unwrap_or_default());
+        }
+        for part in parts {
+            self.triangles_submitted += part.triangles_submitted;
+            self.triangles_after_culling += part.triangles_after_culling;
+            self.triangles_after_clipping += part.triangles_after_clipping;
+        }
+    }
+
+    pub fn get(&self, stage: Stage) -> Duration {
+        return self.timings[stage as usize];
+    }
+}
+
+
+// Rolling window over the last `capacity` frames
+pub struct FrameStats {
+    frame_times: VecDeque<Duration>,
+    capacity: usize,
+    pub last: RenderStats,
+    stage_averages: [f32; 8] // seconds, smoothed so the numbers are readable
+}
+impl FrameStats {
+    pub fn new(capacity: usize) -> Self {
+        return Self {
+            frame_times: VecDeque::with_capacity(capacity),
+            capacity,
+            last: RenderStats::new(),
+            stage_averages: [0.0; 8]
+        };
+    }
+
+    pub fn push(&mut self, frame_time: Duration, render: RenderStats) {
+        if self.frame_times.len() == self.capacity {
+            self.frame_times.pop_front();
+        }
+        self.frame_times.push_back(frame_time);
+
+        for stage in Stage::ALL {
+            let average = &mut self.stage_averages[stage as usize];
+            *average += (render.get(stage).as_secs_f32() - *average) * 0.05;
+        }
+        self.last = render;
+    }
+
+    pub fn frame_times(&self) -> &VecDeque<Duration> {
+        return &self.frame_times;
+    }
+
+    pub fn average(&self) -> Duration {
+        if self.frame_times.is_empty() {
+            return Duration::new(0, 0);
+        }
+        return self.frame_times.iter().sum::<Duration>() / self.frame_times.len() as u32;
+    }
+
+    pub fn min(&self) -> Duration {
+        return self.frame_times.iter().min().copied().unwrap_or_default();
+    }
+
+    pub fn max(&self) -> Duration {
+        return self.frame_times.iter().max().copied().unwrap_or_default();
+    }
+
+    // average of the slowest 1% of frames, the usual "1% low" when turned into FPS
+    pub fn one_percent_low(&self) -> Duration {
+        if self.frame_times.is_empty() {
+            return Duration::new(0, 0);
+        }
+        let mut sorted: Vec<Duration> = self.frame_times.iter().copied().collect();
+        sorted.sort_unstable_by(|a, b| b.cmp(a));
+        let count = (sorted.len() / 100).max(1);
+        return sorted[..count].iter().sum::<Duration>() / count as u32;
+    }
+
+    pub fn stage_average(&self, stage: Stage) -> Duration {
+        return Duration::from_secs_f32(self.stage_averages[stage as usize]);
+    }
+
+    // frame counts in `bucket_count` buckets of `bucket_width`, the last one also holds everything slower
+    pub fn histogram(&self, bucket_count: usize, bucket_width: Duration) -> Vec<usize> {
+        let mut buckets = vec![0; bucket_count];
+        for frame_time in &self.frame_times {
+            let index = (frame_time.as_secs_f32() / bucket_width.as_secs_f32()) as usize;
+            buckets[index.min(bucket_count - 1)] += 1;
+        }
+        return buckets;
+    }
+}
+
+
+pub fn fps(frame_time: Duration) -> f32 {
+    if frame_time.is_zero() {
+        return 0.0;
+    }
+    return 1.0 / frame_time.as_secs_f32();
+}
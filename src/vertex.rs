@@ -0,0 +1,243 @@
+use std::ops::Range;
+use sdl2::pixels::Color;
+
+use crate::math::{Vec2, Vec3, Vec4};
+
+
+pub const MAX_ATTRIBUTES: usize = 21;
+// floats left over for anything else a shader wants to pass along, after the named attributes
+pub const CUSTOM_ATTRIBUTES: usize = 3;
+
+
+// What a vertex carries besides its position, and which floats of `Attributes` each of them takes up
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+pub enum Attribute {
+    Uv,
+    Normal,
+    Color, // RGBA, 0 to 1
+    Tangent, // xyz, w is the handedness of the bitangent
+    Depth, // in view space, for fog
+    Position, // in world space
+    Occlusion, // how much ambient light reaches the pixel, from 0 to 1. Backends set it, the corners' is meaningless.
+    Custom(usize) // one float each, below `CUSTOM_ATTRIBUTES`
+}
+impl Attribute {
+    pub fn range(self) -> Range<usize> {
+        return match self {
+            Attribute::Uv => 0..2,
+            Attribute::Normal => 2..5,
+            Attribute::Color => 5..9,
+            Attribute::Tangent => 9..13,
+            Attribute::Depth => 13..14,
+            Attribute::Position => 14..17,
+            Attribute::Occlusion => 17..18,
+            Attribute::Custom(i) => {
+                assert!(i < CUSTOM_ATTRIBUTES, "there are only {} custom attributes", CUSTOM_ATTRIBUTES);
+                18 + i..19 + i
+            }
+        };
+    }
+}
+
+
+// Every attribute of a vertex as plain floats. Clipping and rasterization only ever blend all of them together,
+// so they don't need to know what the floats mean.
+#[derive(Copy, Clone)]
+pub struct Attributes {
+    values: [f32; MAX_ATTRIBUTES]
+}
+impl Attributes {
+    // everything zero, except for an opaque white colour and nothing occluded
+    pub fn new() -> Self {
+        let mut attributes = Self { values: [0.0; MAX_ATTRIBUTES] };
+        attributes.set(Attribute::Color, &[1.0, 1.0, 1.0, 1.0]);
+        attributes.set(Attribute::Occlusion, &[1.0]);
+        return attributes;
+    }
+
+    pub fn get(&self, attribute: Attribute) -> &[f32] {
+        return &self.values[attribute.range()];
+    }
+
+    // `values` must be as long as the attribute
+    pub fn set(&mut self, attribute: Attribute, values: &[f32]) {
+        self.values[attribute.range()].copy_from_slice(values);
+    }
+
+    pub fn vec2(&self, attribute: Attribute) -> Vec2 {
+        let v = self.get(attribute);
+        return Vec2::new(v[0], v[1]);
+    }
+
+    pub fn vec3(&self, attribute: Attribute) -> Vec3 {
+        let v = self.get(attribute);
+        return Vec3::new(v[0], v[1], v[2]);
+    }
+
+    pub fn vec4(&self, attribute: Attribute) -> Vec4 {
+        let v = self.get(attribute);
+        return Vec4::new(v[0], v[1], v[2], v[3]);
+    }
+
+    pub fn uv(&self) -> Vec2 {
+        return self.vec2(Attribute::Uv);
+    }
+
+    pub fn set_uv(&mut self, uv: Vec2) {
+        self.set(Attribute::Uv, &[uv.x, uv.y]);
+    }
+
+    pub fn normal(&self) -> Vec3 {
+        return self.vec3(Attribute::Normal);
+    }
+
+    pub fn set_normal(&mut self, normal: Vec3) {
+        self.set(Attribute::Normal, &[normal.x, normal.y, normal.z]);
+    }
+
+    // xyz is the tangent, w the handedness of the bitangent
+    pub fn tangent(&self) -> Vec4 {
+        return self.vec4(Attribute::Tangent);
+    }
+
+    pub fn set_tangent(&mut self, tangent: Vec3, handedness: f32) {
+        self.set(Attribute::Tangent, &[tangent.x, tangent.y, tangent.z, handedness]);
+    }
+
+    // the cross product of the normal and the tangent, pointing the way the tangent's w says
+    pub fn bitangent(&self) -> Vec3 {
+        let tangent = self.tangent();
+        return self.normal().cross(&tangent.xyz()) * tangent.w;
+    }
+
+    pub fn depth(&self) -> f32 {
+        return self.get(Attribute::Depth)[0];
+    }
+
+    pub fn position(&self) -> Vec3 {
+        return self.vec3(Attribute::Position);
+    }
+
+    pub fn occlusion(&self) -> f32 {
+        return self.get(Attribute::Occlusion)[0];
+    }
+
+    pub fn color(&self) -> Color {
+        let channel = |value: f32| (value.clamp(0.0, 1.0) * 255.0).round() as u8;
+        let c = self.get(Attribute::Color);
+        return Color::RGBA(channel(c[0]), channel(c[1]), channel(c[2]), channel(c[3]));
+    }
+
+    pub fn set_color(&mut self, color: Color) {
+        self.set(Attribute::Color, &[color.r as f32 / 255.0, color.g as f32 / 255.0, color.b as f32 / 255.0, color.a as f32 / 255.0]);
+    }
+
+    pub fn lerp(&self, other: &Attributes, t: f32) -> Attributes {
+        let mut result = *self;
+        for (value, other) in result.values.iter_mut().zip(other.values) {
+            *value += (other - *value) * t;
+        }
+        return result;
+    }
+
+    // `weights` of the three, for interpolating inside a triangle
+    pub fn blend(attributes: [&Attributes; 3], weights: [f32; 3]) -> Attributes {
+        let mut result = Attributes { values: [0.0; MAX_ATTRIBUTES] };
+        for (i, value) in result.values.iter_mut().enumerate() {
+            *value = attributes[0].values[i] * weights[0] + attributes[1].values[i] * weights[1] + attributes[2].values[i] * weights[2];
+        }
+        return result;
+    }
+}
+impl Default for Attributes {
+    fn default() -> Self {
+        return Self::new();
+    }
+}
+
+
+// A corner of a triangle. `position` is in whatever space the pipeline has got the triangle to: w is 1 up to the view,
+// clip space w after the projection, and on the screen x and y are pixels, z the depth from 0 to 1 and w is 1 / w
+#[derive(Copy, Clone)]
+pub struct Vertex {
+    pub position: Vec4,
+    pub attributes: Attributes
+}
+impl Vertex {
+    pub fn new(position: Vec3) -> Self {
+        return Self { position: Vec4::from_vec3(position, 1.0), attributes: Attributes::new() };
+    }
+
+    pub fn point(&self) -> Vec3 {
+        return self.position.xyz();
+    }
+
+    pub fn lerp(&self, other: &Vertex, t: f32) -> Vertex {
+        return Vertex { position: self.position.lerp(other.position, t), attributes: self.attributes.lerp(&other.attributes, t) };
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::backend::{Image, RenderBackend};
+    use crate::material::Material;
+    use crate::mesh::Triangle;
+    use crate::render::{Renderer, RenderMode};
+    use crate::scene::Scene;
+    use crate::shader::{transform, Shader, Uniforms};
+    use crate::software::SoftwareBackend;
+
+    #[test]
+    fn blending_weighs_every_attribute() {
+        let mut corners = [Attributes::new(); 3];
+        for (i, corner) in corners.iter_mut().enumerate() {
+            corner.set_uv(Vec2::new(i as f32, 0.0));
+            corner.set(Attribute::Custom(1), &[i as f32 * 2.0]);
+        }
+        let blended = Attributes::blend(corners.each_ref(), [0.5, 0.25, 0.25]);
+        assert_eq!(blended.uv().x, 0.75);
+        assert_eq!(blended.get(Attribute::Custom(1))[0], 1.5);
+        assert_eq!(blended.occlusion(), 1.0);
+        assert_eq!(corners[0].lerp(&corners[2], 0.5).get(Attribute::Custom(1))[0], 2.0);
+    }
+
+    // shows `Attribute::Custom(0)` as grey, straight into the HDR buffer
+    struct CustomShader;
+    impl Shader for CustomShader {
+        fn vertex(&self, vertex: &Vertex, uniforms: &Uniforms) -> Vertex {
+            return transform(vertex, uniforms);
+        }
+
+        fn fragment(&self, _varyings: &Attributes, _uniforms: &Uniforms, _material: &Material, _texture: Option<&Image>) -> Option<Color> {
+            return None;
+        }
+
+        fn fragment_hdr(&self, varyings: &Attributes, _uniforms: &Uniforms, _material: &Material, _texture: Option<&Image>) -> Option<(Vec3, u8)> {
+            let value = varyings.get(Attribute::Custom(0))[0];
+            return Some((Vec3::new(value, value, value), 255));
+        }
+    }
+
+    #[test]
+    fn custom_attributes_are_interpolated_perspective_correctly() {
+        // on the screen, the second corner is three times as far away as the others and the only one with a 1
+        let mut triangle = Triangle::new([Vec3::new(0.0, 0.0, 0.5), Vec3::new(32.0, 0.0, 0.5), Vec3::new(0.0, 32.0, 0.5)], [Vec2::new(0.0, 0.0); 3], Color::WHITE);
+        triangle.vertices[1].position.w = 1.0 / 3.0;
+        triangle.vertices[1].attributes.set(Attribute::Custom(0), &[1.0]);
+
+        let scene = Scene::new();
+        let uniforms = Renderer::uniforms(&scene, &scene.camera, 32, 32);
+        let mut backend = SoftwareBackend::new(32, 32, None);
+        backend.begin_frame(Color::BLACK);
+        backend.draw_triangles(&[triangle], RenderMode::Filled, &CustomShader, &uniforms, &[Material::new()]);
+
+        for (x, y) in [(4, 4), (15, 0), (20, 8)] {
+            let far = (x as f32 + 0.5) / 32.0;
+            let near = 1.0 - far;
+            let expected = far / 3.0 / (near + far / 3.0);
+            assert!((backend.hdr[y * 32 + x].x - expected).abs() < 1e-4);
+        }
+    }
+}